@@ -0,0 +1,74 @@
+use nannou::color::{LinSrgb, Srgb};
+
+// Additive buffer of linear light: colored particles are summed per pixel without clamping, so
+// overlapping hues build towards white before the buffer is tone mapped to display range
+pub struct AccumulationBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<LinSrgb>,
+}
+
+impl AccumulationBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        AccumulationBuffer {
+            width,
+            height,
+            cells: vec![LinSrgb::new(0.0, 0.0, 0.0); width * height],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(LinSrgb::new(0.0, 0.0, 0.0));
+    }
+
+    // Scale every cell down by `amount` (0.0 keeps them, 1.0 clears them)
+    pub fn fade(&mut self, amount: f32) {
+        for cell in self.cells.iter_mut() {
+            *cell *= 1.0 - amount;
+        }
+    }
+
+    // Add a color at a pixel position (points outside of the buffer are ignored)
+    pub fn splat(&mut self, x: f64, y: f64, color: LinSrgb) {
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return;
+        }
+
+        let cell = &mut self.cells[y as usize * self.width + x as usize];
+        *cell += color;
+    }
+
+    // Map the unbounded sums into 0 to 1 with an exponential curve (higher exposure saturates
+    // sooner), returning sRGB encoded RGBA bytes
+    pub fn tone_map(&self, exposure: f32) -> Vec<u8> {
+        self.cells
+            .iter()
+            .flat_map(|cell| {
+                let curve = |value: f32| 1.0 - (-value * exposure).exp();
+                let linear = LinSrgb::new(curve(cell.red), curve(cell.green), curve(cell.blue));
+                let color: Srgb<u8> = Srgb::from_linear(linear).into_format();
+                [color.red, color.green, color.blue, 255]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splats_sum() {
+        let mut buffer = AccumulationBuffer::new(4, 4);
+        buffer.splat(1.5, 2.5, LinSrgb::new(1.0, 0.0, 0.0));
+        buffer.splat(1.0, 2.0, LinSrgb::new(0.0, 0.5, 0.25));
+        buffer.splat(1.9, 2.9, LinSrgb::new(1.0, 1.0, 0.0));
+        buffer.splat(-1.0, 2.0, LinSrgb::new(1.0, 1.0, 1.0)); // outside, ignored
+
+        // beyond 1 before tone mapping
+        assert_eq!(buffer.cells[2 * 4 + 1], LinSrgb::new(2.0, 1.5, 0.25));
+        let black = LinSrgb::new(0.0, 0.0, 0.0);
+        let lit = buffer.cells.iter().filter(|cell| **cell != black).count();
+        assert_eq!(lit, 1);
+    }
+}
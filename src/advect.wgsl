@@ -0,0 +1,104 @@
+// Particle advection through a Perlin noise heading field, mirroring `Simulation::step()`
+
+[[block]]
+struct Params {
+    extent: vec2<f32>; // x_end and y_end, particle positions span 0 to extent
+    heading_noise_factor: f32;
+    heading_noise_multiplier: f32;
+    color_noise_factor: f32;
+    color_noise_multiplier: f32;
+    hue_min: f32;
+    hue_max: f32;
+    velocity_multiplier: f32;
+    alpha: f32;
+    count: u32;
+    padding: u32;
+};
+
+[[block]]
+struct Permutation {
+    values: array<u32, 512>; // shuffled 0 to 255, repeated twice
+};
+
+[[block]]
+struct Particles {
+    positions: array<vec2<f32>>;
+};
+
+[[group(0), binding(0)]] var<uniform> params: Params;
+[[group(0), binding(1)]] var<storage, read> permutation: Permutation;
+[[group(0), binding(2)]] var<storage, read_write> particles: Particles;
+
+let TAU: f32 = 6.28318530718;
+
+fn hash(x: i32, y: i32) -> u32 {
+    let i = permutation.values[u32(x & 255)];
+    return permutation.values[(i + u32(y & 255)) & 511u];
+}
+
+// Dot product with one of eight gradient directions
+fn gradient(hash: u32, offset: vec2<f32>) -> f32 {
+    let angle = f32(hash & 7u) / 8.0 * TAU;
+    return dot(vec2<f32>(cos(angle), sin(angle)), offset);
+}
+
+// Gradient noise, roughly -1 to 1
+fn perlin(point: vec2<f32>) -> f32 {
+    let cell = floor(point);
+    let offset = point - cell;
+    let fade = offset * offset * offset * (offset * (offset * 6.0 - 15.0) + 10.0);
+    let x = i32(cell.x);
+    let y = i32(cell.y);
+
+    let a = gradient(hash(x, y), offset);
+    let b = gradient(hash(x + 1, y), offset - vec2<f32>(1.0, 0.0));
+    let c = gradient(hash(x, y + 1), offset - vec2<f32>(0.0, 1.0));
+    let d = gradient(hash(x + 1, y + 1), offset - vec2<f32>(1.0, 1.0));
+
+    return mix(mix(a, b, fade.x), mix(c, d, fade.x), fade.y) * 1.41421356;
+}
+
+[[stage(compute), workgroup_size(64)]]
+fn advect([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    if (id.x >= params.count) {
+        return;
+    }
+
+    let pos = particles.positions[id.x];
+    let heading = perlin(pos / params.extent * params.heading_noise_factor)
+        * params.heading_noise_multiplier * TAU;
+
+    particles.positions[id.x] = pos + vec2<f32>(sin(heading), cos(heading)) * params.velocity_multiplier;
+}
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> VertexOutput {
+    // map to window, like `color_hue()` does
+    let window = pos * 2.0 - params.extent;
+    let value = (perlin(window / params.extent * params.color_noise_factor)
+        * params.color_noise_multiplier + 1.0) / 2.0;
+    let hue = params.hue_min + fract(value) * (params.hue_max - params.hue_min);
+
+    // fully saturated HSL at half lightness, then from sRGB to linear
+    let h = fract(hue) * 6.0;
+    let rgb = clamp(
+        vec3<f32>(abs(h - 3.0) - 1.0, 2.0 - abs(h - 2.0), 2.0 - abs(h - 4.0)),
+        vec3<f32>(0.0),
+        vec3<f32>(1.0),
+    );
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(window / params.extent, 0.0, 1.0);
+    out.color = vec4<f32>(pow(rgb, vec3<f32>(2.2)), params.alpha);
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,207 @@
+use crate::background::Background;
+use crate::config::Config;
+use crate::headless::{self, STEP_DT};
+use crate::metadata::Metadata;
+use crate::particles::Simulation;
+use crate::render::draw_simulation;
+use crate::target::{downscale, Target};
+use crate::wash;
+use nannou::image::gif::{GifEncoder, Repeat};
+use nannou::image::{Delay, Frame as GifFrame, RgbaImage};
+use nannou::prelude::*;
+use rayon::prelude::*;
+use std::path::Path;
+
+const MIN_LOOP: f64 = 0.25; // fraction of the captured frames a found loop spans at least
+
+// Render `animation_frames` frames offscreen like `--render` and encode them into an animated
+// GIF, or an APNG for any other extension, looping forever
+pub fn export(config: &Config, path: &Path) {
+    assert!(
+        !config.additive_hue,
+        "The additive buffer is only drawn once and can't be animated"
+    );
+    // config files aren't checked by the flag's range
+    assert!(
+        config.animation_frames > 0,
+        "An animation needs at least one frame"
+    );
+
+    let seed = crate::seed::resolve(config);
+    let path = crate::seed::expand(path, seed);
+    let metadata = Metadata::new(config, seed);
+    if config.sidecar {
+        metadata.save_sidecar(&path);
+    }
+
+    let mut frames = capture(config, seed);
+    if config.loop_search {
+        let end = loop_point(&frames);
+        log::info!("Found a loop of {} frames", end);
+        frames.truncate(end);
+    }
+    // Play back in reverse after playing forward, without repeating the turning points
+    if config.ping_pong && frames.len() > 2 {
+        let reversed: Vec<RgbaImage> = frames[1..frames.len() - 1].iter().rev().cloned().collect();
+        frames.extend(reversed);
+    }
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory).unwrap();
+    }
+    let file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+    let gif = path.extension().is_some_and(|extension| extension == "gif");
+    match gif {
+        true => save_gif(config, frames, file),
+        false => save_apng(config, &frames, &metadata, file),
+    }
+    log::info!("Saved {}", path.display());
+}
+
+// Run the simulation offscreen, reading the texture back every `animation_steps` steps
+fn capture(config: &Config, seed: u64) -> Vec<RgbaImage> {
+    let (device, queue) = headless::device();
+    let (width, height) = (config.render_width, config.render_height);
+    let mut simulation = Simulation::new(config, seed, width as f64 / 2.0, height as f64 / 2.0);
+
+    let factor = config.supersample.max(1);
+    let mut target = Target::new(
+        &device,
+        [width * factor, height * factor],
+        config.msaa.max(1),
+    );
+
+    let canvas = Draw::new();
+    let draw = canvas.scale(factor as f32);
+    let background = Background::new((&device, &queue), config);
+    background.draw(&draw, config, simulation.size());
+    let wash = match config.background_wash_strength > 0.0 {
+        true => Some(wash::texture((&device, &queue), &simulation, config)),
+        false => None,
+    };
+    if let Some(wash) = &wash {
+        draw.texture(wash).wh(simulation.size());
+    }
+
+    let mut frames = Vec::new();
+    for frame in 0..config.animation_frames {
+        for _ in 0..config.animation_steps.max(1) {
+            simulation.spawn(config, STEP_DT);
+            simulation.step(config);
+            draw_simulation(&draw, config, &simulation, None, true);
+            target.render(&device, &queue, &canvas);
+        }
+        let image = headless::read(&device, &queue, &target.texture);
+        frames.push(downscale(&image, factor));
+        let total = config.animation_frames as usize;
+        crate::logging::progress("Frame", frame as usize + 1, total);
+    }
+
+    frames
+}
+
+// Number of frames after which the animation looks the most like its first frame again, at
+// least `MIN_LOOP` of them, so cutting it there loops with the smallest jump
+fn loop_point(frames: &[RgbaImage]) -> usize {
+    let first = match frames.first() {
+        Some(first) => first,
+        None => return 0,
+    };
+    let difference = |frame: &RgbaImage| -> u64 {
+        let pixels = first.as_raw().iter().zip(frame.as_raw());
+        pixels.map(|(&a, &b)| a.abs_diff(b) as u64).sum()
+    };
+
+    let min = ((frames.len() as f64 * MIN_LOOP).ceil() as usize).max(1);
+    (min..frames.len())
+        .into_par_iter()
+        .min_by_key(|&end| (difference(&frames[end]), end))
+        .unwrap_or(frames.len())
+}
+
+fn delay_ms(config: &Config) -> u32 {
+    (1000.0 / config.animation_fps).round() as u32
+}
+
+fn save_gif(config: &Config, frames: Vec<RgbaImage>, file: impl std::io::Write) {
+    let mut encoder = GifEncoder::new(file);
+    encoder.set_repeat(Repeat::Infinite).unwrap();
+    let delay = Delay::from_numer_denom_ms(delay_ms(config), 1);
+    let frames = frames
+        .into_iter()
+        .map(|image| GifFrame::from_parts(image, 0, 0, delay));
+    encoder.encode_frames(frames).unwrap();
+}
+
+// PNG 0.16 can't write animations, so every frame is encoded as a still PNG whose image data is
+// moved into the animation chunks: `IDAT` for the first frame, so viewers without APNG support
+// show it, and `fdAT` for the others
+fn save_apng(
+    config: &Config,
+    frames: &[RgbaImage],
+    metadata: &Metadata,
+    file: impl std::io::Write,
+) {
+    let (width, height) = frames
+        .first()
+        .map_or((1, 1), |frame| (frame.width(), frame.height()));
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    metadata.write_chunks(&mut writer);
+
+    // frame count and plays, 0 for forever
+    let control = [frames.len() as u32, 0].map(u32::to_be_bytes).concat();
+    writer.write_chunk(*b"acTL", &control).unwrap();
+
+    let delay = (delay_ms(config).min(u16::MAX as u32) as u16).to_be_bytes();
+    let mut sequence = 0u32;
+    for (i, frame) in frames.iter().enumerate() {
+        // sequence, size, offset, delay in milliseconds, no disposal and no blending
+        let mut control: Vec<u8> = [sequence, width, height, 0, 0]
+            .map(u32::to_be_bytes)
+            .concat();
+        control.extend(delay);
+        control.extend(1000u16.to_be_bytes());
+        control.extend([0, 0]);
+        writer.write_chunk(*b"fcTL", &control).unwrap();
+        sequence += 1;
+
+        for data in image_data(frame) {
+            match i {
+                0 => writer.write_chunk(*b"IDAT", &data).unwrap(),
+                _ => {
+                    let chunk = [&sequence.to_be_bytes()[..], &data].concat();
+                    writer.write_chunk(*b"fdAT", &chunk).unwrap();
+                    sequence += 1;
+                }
+            }
+        }
+    }
+}
+
+// Compressed image data of a frame, as the `IDAT` chunks of it encoded on its own
+fn image_data(frame: &RgbaImage) -> Vec<Vec<u8>> {
+    let mut png = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png, frame.width(), frame.height());
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(frame.as_raw()).unwrap();
+    }
+
+    // chunks after the signature: length, type, data and checksum
+    let mut chunks = Vec::new();
+    let mut offset = 8;
+    while offset + 8 <= png.len() {
+        let length = u32::from_be_bytes(png[offset..offset + 4].try_into().unwrap()) as usize;
+        let data = &png[offset + 8..offset + 8 + length];
+        if &png[offset + 4..offset + 8] == b"IDAT" {
+            chunks.push(data.to_vec());
+        }
+        offset += 12 + length;
+    }
+    chunks
+}
@@ -0,0 +1,155 @@
+use crate::config::Config;
+use crate::modulation::Binding;
+use serde::{Deserialize, Serialize};
+
+// Frequency band bound to a parameter, given as `[[audio]]` tables in the config file
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct AudioBinding {
+    pub band: [f64; 2], // lowest and highest frequency in Hz
+    #[serde(default = "default_gain")]
+    pub gain: f64, // level multiplier, so quiet bands can still sweep the whole range
+    #[serde(flatten)]
+    pub binding: Binding,
+}
+
+fn default_gain() -> f64 {
+    1.0
+}
+
+#[cfg(feature = "audio")]
+const FFT_SIZE: usize = 2048; // samples analyzed per frame, about 43 ms at 48 kHz
+
+// Samples captured from an input device on cpal's thread, analyzed once per frame
+#[cfg(feature = "audio")]
+pub struct Audio {
+    _stream: cpal::Stream, // stops capturing when dropped
+    samples: std::sync::Arc<std::sync::Mutex<std::collections::VecDeque<f32>>>, // most recent last
+    sample_rate: f64,
+    fft: std::sync::Arc<dyn rustfft::Fft<f32>>,
+    levels: Vec<f64>, // smoothed level of every binding
+}
+
+#[cfg(feature = "audio")]
+impl Audio {
+    // Capture the first input device whose name contains `name`, or the default one for `default`
+    pub fn connect(name: &str) -> Self {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let host = cpal::default_host();
+        let device = match name {
+            "default" => host.default_input_device(),
+            _ => host
+                .input_devices()
+                .unwrap()
+                .find(|device| device.name().is_ok_and(|device| device.contains(name))),
+        };
+        let device = device.unwrap_or_else(|| panic!("No audio input device matches `{}`", name));
+        log::info!("Capturing audio from {}", device.name().unwrap());
+
+        let supported = device.default_input_config().unwrap();
+        let config: cpal::StreamConfig = supported.config();
+        let samples = std::sync::Arc::new(std::sync::Mutex::new(
+            std::collections::VecDeque::with_capacity(FFT_SIZE),
+        ));
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => capture::<f32>(&device, &config, samples.clone()),
+            cpal::SampleFormat::I16 => capture::<i16>(&device, &config, samples.clone()),
+            cpal::SampleFormat::U16 => capture::<u16>(&device, &config, samples.clone()),
+            other => panic!("Unsupported audio sample format {}", other),
+        };
+        stream.play().unwrap();
+
+        Audio {
+            _stream: stream,
+            samples,
+            sample_rate: config.sample_rate.0 as f64,
+            fft: rustfft::FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            levels: Vec::new(),
+        }
+    }
+
+    // Set the parameters bound to every band from the spectrum of the most recent samples
+    pub fn apply(&mut self, config: &mut Config) {
+        use rustfft::num_complex::Complex;
+
+        // Hann window against the leakage of the cut off ends
+        let mut spectrum: Vec<Complex<f32>> = vec![Complex::default(); FFT_SIZE];
+        let samples = self.samples.lock().unwrap();
+        let offset = FFT_SIZE - samples.len();
+        for (i, sample) in samples.iter().enumerate() {
+            let t = (offset + i) as f32 / FFT_SIZE as f32;
+            let window = 0.5 - 0.5 * (t * std::f32::consts::TAU).cos();
+            spectrum[offset + i] = Complex::new(sample * window, 0.0);
+        }
+        drop(samples);
+        self.fft.process(&mut spectrum);
+
+        // Level of a band relative to a full scale sine in it, rising immediately and falling
+        // off by `audio_smoothing` per frame
+        let bin_width = self.sample_rate / FFT_SIZE as f64;
+        let full_scale = FFT_SIZE as f64 / 4.0;
+        self.levels.resize(config.audio.len(), 0.0);
+        let bindings = config.audio.clone();
+        for (binding, level) in bindings.iter().zip(&mut self.levels) {
+            let [low, high] = binding.band;
+            let bins = (low / bin_width).ceil() as usize..=(high / bin_width).floor() as usize;
+            let power: f64 = spectrum[1..FFT_SIZE / 2]
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| bins.contains(&(i + 1)))
+                .map(|(_, bin)| bin.norm_sqr() as f64)
+                .sum();
+            let current = power.sqrt() / full_scale * binding.gain;
+            *level = current.max(*level * config.audio_smoothing);
+            binding.binding.apply(config, *level);
+        }
+    }
+}
+
+// Input stream pushing the first channel of every frame into `samples`, keeping the latest
+// `FFT_SIZE` of them
+#[cfg(feature = "audio")]
+fn capture<T: cpal::SizedSample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    samples: std::sync::Arc<std::sync::Mutex<std::collections::VecDeque<f32>>>,
+) -> cpal::Stream
+where
+    f32: cpal::FromSample<T>,
+{
+    use cpal::traits::DeviceTrait;
+
+    let channels = config.channels as usize;
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| {
+                let mut samples = samples.lock().unwrap();
+                for frame in data.chunks(channels) {
+                    if samples.len() == FFT_SIZE {
+                        samples.pop_front();
+                    }
+                    samples.push_back(frame[0].to_sample::<f32>());
+                }
+            },
+            |err| log::error!("Audio input error: {}", err),
+            None,
+        )
+        .unwrap_or_else(|err| panic!("Could not capture audio input: {}", err))
+}
+
+// Stand-in without the `audio` feature, which needs the ALSA development files on Linux
+#[cfg(not(feature = "audio"))]
+pub struct Audio;
+
+#[cfg(not(feature = "audio"))]
+impl Audio {
+    pub fn connect(_name: &str) -> Self {
+        log::warn!(
+            "Built without audio support, rebuild with `--features audio` to use --audio-input"
+        );
+        Audio
+    }
+
+    pub fn apply(&mut self, _config: &mut Config) {}
+}
@@ -0,0 +1,135 @@
+use crate::config::Config;
+use nannou::image;
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// What the canvas starts from and is cleared to. Trails fade towards `background` whichever it is.
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackgroundMode {
+    Solid,       // `background` all over
+    Gradient,    // from `background` to `background_gradient` along `background_angle`
+    Image,       // `background_image` stretched over the canvas
+    Transparent, // nothing, so exported PNGs can be composited over something else
+}
+
+// The background along with the texture of its image, if it has one
+pub struct Background {
+    texture: Option<wgpu::Texture>,
+}
+
+impl Background {
+    pub fn new<T: wgpu::WithDeviceQueuePair>(src: T, config: &Config) -> Self {
+        let texture = match (config.background_mode, &config.background_image) {
+            (BackgroundMode::Image, Some(path)) => {
+                let image = image::open(path).unwrap_or_else(|err| {
+                    panic!("Could not load image {}: {}", path.display(), err)
+                });
+                Some(wgpu::Texture::from_image(src, &image))
+            }
+            (BackgroundMode::Image, None) => {
+                panic!("The image background needs `--background-image`")
+            }
+            _ => None,
+        };
+        Background { texture }
+    }
+
+    // Whether the canvas has to be cleared, and the background rebuilt, to follow a change from
+    // `old` to `config`
+    pub fn changed(old: &Config, config: &Config) -> bool {
+        old.background != config.background
+            || old.background_mode != config.background_mode
+            || old.background_gradient != config.background_gradient
+            || old.background_angle != config.background_angle
+            || old.background_image != config.background_image
+    }
+
+    // Color the canvas is cleared to, the background made fully transparent in transparent mode
+    pub fn clear_color(config: &Config) -> LinSrgba {
+        let color = config.background.linear();
+        let alpha = match config.background_mode {
+            BackgroundMode::Transparent => 0.0,
+            _ => 1.0,
+        };
+        lin_srgba(color.red, color.green, color.blue, alpha)
+    }
+
+    // Clear to the background, over a canvas `size` points large centered on the origin
+    pub fn draw(&self, draw: &Draw, config: &Config, size: Vec2) {
+        draw.background().color(Background::clear_color(config));
+        if config.background_mode == BackgroundMode::Gradient {
+            gradient(draw, config, size);
+        }
+        if let Some(texture) = &self.texture {
+            draw.texture(texture).wh(size);
+        }
+    }
+}
+
+// Two triangles whose corners are colored by how far along the gradient they are, which
+// interpolates to the exact linear gradient in between
+fn gradient(draw: &Draw, config: &Config, size: Vec2) {
+    let angle = config.background_angle.to_radians() as f32;
+    let direction = vec2(angle.cos(), angle.sin());
+    let half = size / 2.0;
+    let corners = [
+        vec2(-1.0, -1.0),
+        vec2(1.0, -1.0),
+        vec2(1.0, 1.0),
+        vec2(-1.0, 1.0),
+    ]
+    .map(|corner| corner * half);
+
+    let along = corners.map(|corner| corner.dot(direction));
+    let (min, max) = along.iter().fold((f32::MAX, f32::MIN), |(min, max), &t| {
+        (min.min(t), max.max(t))
+    });
+    let (from, to) = (
+        config.background.linear(),
+        config.background_gradient.linear(),
+    );
+    let color = |i: usize| {
+        let t = match max > min {
+            true => (along[i] - min) / (max - min),
+            false => 0.0,
+        };
+        let color = from * (1.0 - t) + to * t;
+        (
+            corners[i].extend(0.0),
+            lin_srgba(color.red, color.green, color.blue, 1.0),
+        )
+    };
+
+    draw.mesh().points_colored([0, 1, 2, 0, 2, 3].map(color));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picked_background_clears_next_frame() {
+        let old = Config::default();
+        let mut config = old.clone();
+        config.background.0 = [255, 0, 0]; // what the panel's color picker edits
+
+        // a changed background clears the canvas to it before the next frame is drawn
+        assert!(Background::changed(&old, &config));
+        assert_eq!(
+            Background::clear_color(&config),
+            lin_srgba(1.0, 0.0, 0.0, 1.0)
+        );
+
+        // and the config dump carries it
+        let dump = toml::to_string(&config).unwrap();
+        assert!(dump.contains("background = \"#ff0000\""));
+
+        // a transparent background clears to the same color without opacity
+        config.background_mode = BackgroundMode::Transparent;
+        assert_eq!(
+            Background::clear_color(&config),
+            lin_srgba(1.0, 0.0, 0.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use crate::config::Config;
+use crate::headless::STEP_DT;
+use crate::particles::Simulation;
+use crate::render::draw_simulation;
+use crate::web::Instant;
+use nannou::prelude::*;
+
+const SIZE: [f64; 2] = [1024.0, 768.0]; // of the canvas, in pixels
+const SEED: u64 = 1; // unless one is given, so every run steps the same particles
+const WARMUP: usize = 10; // iterations run before timing, filling caches and spawning
+
+// Time `bench_iterations` updates and draws of the sketch without a window at every one of
+// `bench_points`, and print the mean and 95th percentile of each stage in milliseconds. Drawing
+// only records the draw commands, rendering them is left to the GPU. Everything else comes from
+// the config, so a bench run with the same flags is comparable across commits.
+pub fn bench(config: &Config) {
+    let seed = match config.seed {
+        0 => SEED,
+        seed => seed,
+    };
+    log::info!(
+        "Benchmarking {} iterations at seed {}",
+        config.bench_iterations,
+        seed
+    );
+
+    println!(
+        "{:>8} {:>10} {:>12} {:>12} {:>12} {:>12}",
+        "points", "particles", "update mean", "update p95", "draw mean", "draw p95"
+    );
+    for &points in &config.bench_points {
+        let mut config = config.clone();
+        config.point_count = points;
+        let mut simulation = Simulation::new(&config, seed, SIZE[0] / 2.0, SIZE[1] / 2.0);
+        let draw = Draw::new();
+
+        let (mut update, mut drawing) = (Vec::new(), Vec::new());
+        for iteration in 0..WARMUP + config.bench_iterations {
+            let start = Instant::now();
+            simulation.spawn(&config, STEP_DT);
+            simulation.step(&config);
+            let stepped = Instant::now();
+            draw_simulation(&draw, &config, &simulation, None, true);
+            draw.reset();
+
+            if iteration >= WARMUP {
+                update.push((stepped - start).as_secs_f64() * 1000.0);
+                drawing.push(stepped.elapsed().as_secs_f64() * 1000.0);
+            }
+        }
+
+        println!(
+            "{:>8} {:>10} {:>12.3} {:>12.3} {:>12.3} {:>12.3}",
+            points,
+            simulation.flow_field.len(),
+            mean(&update),
+            percentile(&mut update, 0.95),
+            mean(&drawing),
+            percentile(&mut drawing, 0.95),
+        );
+    }
+}
+
+fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / samples.len().max(1) as f64
+}
+
+// Nearest-rank percentile, 0 without samples
+fn percentile(samples: &mut [f64], fraction: f64) -> f64 {
+    samples.sort_by(f64::total_cmp);
+    let rank = (fraction * samples.len() as f64).ceil() as usize;
+    samples.get(rank.saturating_sub(1)).copied().unwrap_or(0.0)
+}
@@ -0,0 +1,93 @@
+// Value precomputed from the field, tagged with the parameters it was built from. Once the
+// parameters change the cache is dirty and gets rebuilt the next time it is requested, while
+// changes to parameters outside of the key (e.g. colors) leave it untouched.
+pub struct FieldCache<K, T> {
+    key: Option<K>,
+    value: Option<T>,
+}
+
+impl<K: PartialEq, T> FieldCache<K, T> {
+    pub fn new() -> Self {
+        FieldCache {
+            key: None,
+            value: None,
+        }
+    }
+
+    pub fn is_dirty(&self, key: &K) -> bool {
+        self.key.as_ref() != Some(key)
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    // Rebuild the value if `key` differs from the one it was built from, returning whether it did
+    pub fn update(&mut self, key: K, build: impl FnOnce() -> T) -> bool {
+        if !self.is_dirty(&key) {
+            return false;
+        }
+
+        self.key = Some(key);
+        self.value = Some(build());
+
+        true
+    }
+}
+
+impl<K: PartialEq, T> Default for FieldCache<K, T> {
+    fn default() -> Self {
+        FieldCache::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::palette::HexColor;
+    use crate::particles::NoiseParams;
+
+    #[test]
+    fn dirty_on_noise_changes_only() {
+        let config = Config::default();
+        let mut cache = FieldCache::new();
+        assert!(cache.is_dirty(&NoiseParams::new(&config, 1)));
+        assert!(cache.update(NoiseParams::new(&config, 1), || ()));
+        assert!(!cache.update(NoiseParams::new(&config, 1), || ()));
+
+        let noise_changes = [
+            Config {
+                heading_noise_factor: config.heading_noise_factor * 2.0,
+                ..config.clone()
+            },
+            Config {
+                heading_noise_multiplier: config.heading_noise_multiplier * 2.0,
+                ..config.clone()
+            },
+            Config {
+                octaves: Some(3),
+                ..config.clone()
+            },
+        ];
+        for changed in &noise_changes {
+            assert!(cache.is_dirty(&NoiseParams::new(changed, 1)));
+        }
+        assert!(cache.is_dirty(&NoiseParams::new(&config, 2))); // a new seed
+
+        let color_changes = [
+            Config {
+                background: HexColor([12, 34, 56]),
+                ..config.clone()
+            },
+            Config {
+                hue_min: 0.5,
+                color_noise_multiplier: config.color_noise_multiplier * 2.0,
+                ..config.clone()
+            },
+        ];
+        for changed in &color_changes {
+            assert!(!cache.is_dirty(&NoiseParams::new(changed, 1)));
+        }
+    }
+}
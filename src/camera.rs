@@ -0,0 +1,46 @@
+use nannou::prelude::*;
+
+const MAX_ZOOM: f32 = 16.0; // closest the camera zooms in, in window points per canvas point
+
+// View of the canvas in the window, so a simulation domain larger than the window can be explored.
+// The canvas is drawn `zoom` times its size with its center at `pan`, both in window points.
+pub struct Camera {
+    pub pan: Vec2,
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new()
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Camera {
+            pan: Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.pan == Vec2::ZERO && self.zoom == 1.0
+    }
+
+    // From window points to canvas points, centered like the window
+    pub fn to_canvas(&self, point: Vec2) -> Vec2 {
+        (point - self.pan) / self.zoom
+    }
+
+    // Zoom by `factor`, keeping what is under `anchor` in the window in place
+    pub fn zoom_at(&mut self, anchor: Vec2, factor: f32, min_zoom: f32) {
+        let point = self.to_canvas(anchor);
+        self.zoom = (self.zoom * factor).clamp(min_zoom, MAX_ZOOM);
+        self.pan = anchor - point * self.zoom;
+    }
+
+    // `draw` transformed so canvas points land where the camera shows them
+    pub fn transform(&self, draw: &Draw) -> Draw {
+        draw.translate(self.pan.extend(0.0)).scale(self.zoom)
+    }
+}
@@ -0,0 +1,65 @@
+use crate::target::Target;
+use nannou::prelude::*;
+
+// Offscreen texture the sketch is drawn into. It is only cleared on request, so trails keep
+// accumulating on it while the window itself is redrawn from scratch every frame, with the
+// control panel on top.
+pub struct Canvas {
+    pub target: Target,
+    pub draw: Draw,
+    reshaper: wgpu::TextureReshaper,
+}
+
+impl Canvas {
+    pub fn new(window: &Window, size: [u32; 2], background: LinSrgb, samples: u32) -> Self {
+        let target = Target::new(window.device(), size, samples);
+        let texture = &target.texture;
+        let reshaper = wgpu::TextureReshaper::new(
+            window.device(),
+            &texture.view().build(),
+            1,
+            texture.sample_type(),
+            window.msaa_samples(),
+            Frame::TEXTURE_FORMAT,
+        );
+
+        // Start from the background rather than transparent
+        let draw = Draw::new();
+        draw.background().color(background);
+
+        Canvas {
+            target,
+            draw,
+            reshaper,
+        }
+    }
+
+    // New canvas of another size or sample count, starting from this one stretched to fill it
+    pub fn resized(
+        &self,
+        window: &Window,
+        size: [u32; 2],
+        background: LinSrgb,
+        samples: u32,
+    ) -> Self {
+        let canvas = Canvas::new(window, size, background, samples);
+        canvas
+            .draw
+            .texture(&self.target.texture)
+            .wh(vec2(size[0] as f32, size[1] as f32));
+        canvas
+    }
+
+    // Render everything drawn since the last call on top of the canvas
+    pub fn render(&mut self, window: &Window) {
+        self.target
+            .render(window.device(), window.queue(), &self.draw);
+    }
+
+    // Copy the canvas onto the window's frame, stretched to fill it
+    pub fn present(&self, frame: &Frame) {
+        let mut encoder = frame.command_encoder();
+        self.reshaper
+            .encode_render_pass(frame.texture_view(), &mut encoder);
+    }
+}
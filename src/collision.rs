@@ -0,0 +1,54 @@
+use crate::config::Config;
+use crate::Vector2;
+use std::collections::HashMap;
+
+// Owner of a drawn position: the index of the flow and the step its current life started on
+pub type Owner = (usize, u64);
+
+type Point = (Vector2, Owner, u64); // with the owner's age at the time
+
+// Every position flows were drawn at, bucketed into square cells `path_spacing` wide so a flow
+// only looks at the nine cells around it, stopping flows before they come within `path_spacing`
+// of a path already drawn
+pub struct DrawnPaths {
+    spacing: f64,
+    cells: HashMap<(i64, i64), Vec<Point>>,
+}
+
+impl DrawnPaths {
+    // Paths are free to overlap unless a spacing is set
+    pub fn build(config: &Config) -> Option<Self> {
+        (config.path_spacing > 0.0).then(|| DrawnPaths {
+            spacing: config.path_spacing,
+            cells: HashMap::new(),
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn add(&mut self, pos: Vector2, owner: Owner, age: u64) {
+        let cell = cell(pos, self.spacing);
+        self.cells.entry(cell).or_default().push((pos, owner, age));
+    }
+
+    // Whether `pos` is within the spacing of a drawn path, ignoring the positions its owner was
+    // drawn at during the last `recent` steps, which it always is close to
+    pub fn blocked(&self, pos: Vector2, owner: Owner, age: u64, recent: u64) -> bool {
+        let (cx, cy) = cell(pos, self.spacing);
+        (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dy| (cx + dx, cy + dy)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .any(|&(other, other_owner, other_age)| {
+                let own = other_owner == owner && age.saturating_sub(other_age) <= recent;
+                let offset = pos - other;
+                !own && offset.length() < self.spacing
+            })
+    }
+}
+
+fn cell(pos: Vector2, size: f64) -> (i64, i64) {
+    ((pos.x / size).floor() as i64, (pos.y / size).floor() as i64)
+}
@@ -0,0 +1,68 @@
+use crate::config::Config;
+use nannou::color::{LinSrgb, Srgb};
+use nannou::image;
+use std::path::Path;
+
+// Colors particles take from where they are instead of from the palette, sampled at normalized
+// positions (0 to 1 across the canvas, y pointing up)
+pub trait ColorSource: Send + Sync {
+    fn color(&self, u: f64, v: f64) -> LinSrgb;
+}
+
+// Source for `--color-mode image`, if an image is given
+pub fn build(config: &Config) -> Option<Box<dyn ColorSource>> {
+    let path = config.color_image.as_deref()?;
+    Some(Box::new(ImageColors::load(path)))
+}
+
+// Reference image stretched over the canvas, so particles inherit its palette and composition
+pub struct ImageColors {
+    width: usize,
+    height: usize,
+    pixels: Vec<LinSrgb>, // row-major from the top left
+}
+
+impl ImageColors {
+    pub fn load(path: &Path) -> Self {
+        let image = image::open(path)
+            .unwrap_or_else(|err| panic!("Could not load image {}: {}", path.display(), err))
+            .to_rgb8();
+        let pixels = image
+            .pixels()
+            .map(|pixel| {
+                let [red, green, blue] = pixel.0;
+                Srgb::new(red, green, blue)
+                    .into_format::<f32>()
+                    .into_linear()
+            })
+            .collect();
+
+        ImageColors {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels,
+        }
+    }
+}
+
+impl ColorSource for ImageColors {
+    // Interpolated bilinearly between pixel centers, clamped to the edges of the image
+    fn color(&self, u: f64, v: f64) -> LinSrgb {
+        let x = (u * self.width as f64 - 0.5).clamp(0.0, (self.width - 1) as f64);
+        let y = ((1.0 - v) * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+        let (column, row) = (x as usize, y as usize);
+        let (right, below) = (
+            (column + 1).min(self.width - 1),
+            (row + 1).min(self.height - 1),
+        );
+        let (tx, ty) = ((x - column as f64) as f32, (y - row as f64) as f32);
+
+        let at = |column: usize, row: usize| self.pixels[row * self.width + column];
+        let lerp = |a: LinSrgb, b: LinSrgb, t: f32| a * (1.0 - t) + b * t;
+        lerp(
+            lerp(at(column, row), at(right, row), tx),
+            lerp(at(column, below), at(right, below), tx),
+            ty,
+        )
+    }
+}
@@ -0,0 +1,1095 @@
+use crate::audio::AudioBinding;
+use crate::background::BackgroundMode;
+use crate::edge::EdgeMode;
+use crate::emitter::EmitterKind;
+use crate::field::FieldKind;
+use crate::field_view::FieldView;
+use crate::integrator::Integrator;
+use crate::midi::MidiBinding;
+use crate::noise::NoiseKind;
+use crate::obstacle::{Obstacle, ObstacleMode};
+use crate::output::Output;
+use crate::palette::{ColorMode, ColorSpace, HexColor, PaletteKind, PaletteMapping};
+use crate::radial::Radial;
+use crate::respawn::RespawnMode;
+use crate::ribbon::RenderMode;
+use crate::seed;
+use crate::shape::{ParticleShape, SizeMode};
+use crate::sketch::SketchKind;
+use crate::species::SpeciesConfig;
+use crate::stack::FieldLayer;
+use crate::streamline::StreamlineColor;
+use crate::timeline::Track;
+use crate::web;
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches, Parser};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Every tunable of the sketch, parsed from the command line so sketches can be iterated on
+// without recompiling, e.g. `cargo run -- --points 128 --velocity 0.5 --seed 42`. The same
+// fields can be saved to a TOML or JSON file and loaded with `--config` or `--preset`.
+#[derive(Parser, Serialize, Deserialize, Clone, PartialEq)]
+#[command(about = "A visual flow field using Perlin noise")]
+#[serde(default)]
+pub struct Config {
+    /// Load parameters from a TOML or JSON file (command line flags take precedence)
+    #[arg(long = "config")]
+    #[serde(skip)]
+    pub config_file: Option<PathBuf>,
+
+    /// Load parameters from a named preset in the `presets/` directory
+    #[arg(long, conflicts_with = "config_file")]
+    #[serde(skip)]
+    pub preset: Option<String>,
+
+    /// Sketch to run
+    #[arg(value_enum, default_value_t = SketchKind::FlowField)]
+    pub sketch: SketchKind,
+
+    /// Number of starting points along each axis
+    #[arg(long = "points", default_value_t = 64)]
+    pub point_count: u32,
+
+    /// Randomized position delta for each point
+    #[arg(long, default_value_t = 0.1)]
+    pub point_delta: f64,
+
+    /// Where particles are placed when seeding and spawning
+    #[arg(long, value_enum, default_value_t = EmitterKind::Grid)]
+    pub emitter: EmitterKind,
+
+    /// Image the image emitter places particles on, brighter pixels more often
+    #[arg(long)]
+    pub emitter_image: Option<PathBuf>,
+
+    /// Center of the ring emitter, where the shorter side of the window spans -1 to 1
+    #[arg(long, num_args = 2, default_values_t = [0.0, 0.0])]
+    pub emitter_center: Vec<f64>,
+
+    /// Radius of the ring emitter
+    #[arg(long, default_value_t = 0.5)]
+    pub emitter_radius: f64,
+
+    /// Start and end of the line emitter
+    #[arg(long, num_args = 4, allow_negative_numbers = true, default_values_t = [-0.5, 0.0, 0.5, 0.0])]
+    pub emitter_line: Vec<f64>,
+
+    /// Simulate a domain this many times the size of the window, explored by dragging with the
+    /// middle mouse button and scrolling
+    #[arg(long, default_value_t = 1.0)]
+    pub domain_scale: f64,
+
+    /// Noise function the field is sampled from
+    #[arg(long = "noise", value_enum, default_value_t = NoiseKind::Perlin)]
+    pub noise_kind: NoiseKind,
+
+    /// Derive the field from the luminance of this image instead of noise, following its edges
+    /// with `--field curl`
+    #[arg(long)]
+    pub image: Option<PathBuf>,
+
+    /// Define the field by an expression instead, either a vector like `sin(y), cos(x)` or an
+    /// angle like `angle = theta + pi / 2 + r`, over `x`, `y`, `r`, `theta` and `t`
+    #[arg(long)]
+    pub expression: Option<String>,
+
+    /// Define the heading and the color by the functions of a Rhai script instead, reloaded
+    /// whenever it changes (the color with `--color-mode script`, needs `--features script`)
+    #[arg(long)]
+    pub script: Option<PathBuf>,
+
+    /// Load the field from a `.csv` or `.npy` file of vectors on a lattice instead, like the ones
+    /// `--export-field` saves
+    #[arg(long)]
+    pub field_file: Option<PathBuf>,
+
+    /// Weight of the main field under the `[[fields]]` layers of the config file, the reaction and
+    /// the fluid
+    #[arg(long, default_value_t = 1.0)]
+    pub field_weight: f64,
+
+    /// Bend the field along the outlines of this text, so the flow traces the letters
+    #[arg(long)]
+    pub guide_text: Option<String>,
+
+    /// Bend the field along SVG path data instead, like `M 0 0 L 10 0 L 5 8 Z`
+    #[arg(long)]
+    pub guide_path: Option<String>,
+
+    /// How strongly particles near the guide outline are turned along and onto it
+    #[arg(long, default_value_t = 2.0)]
+    pub guide_weight: f64,
+
+    /// Distance from the guide outline the field is bent within, where the shorter side of the
+    /// window spans 2
+    #[arg(long, default_value_t = 0.1)]
+    pub guide_band: f64,
+
+    /// Blend in the gradient of a Gray-Scott reaction-diffusion grid evolving with the simulation,
+    /// flowing up it with `--field angle` or along its contours with `--field curl`
+    #[arg(long)]
+    pub reaction: bool,
+
+    /// Weight of the reaction gradient relative to the main field
+    #[arg(long, default_value_t = 1.0)]
+    pub reaction_weight: f64,
+
+    /// Cells of the reaction grid along the longer side of the window
+    #[arg(long, default_value_t = 256)]
+    pub reaction_resolution: u32,
+
+    /// Rate the substrate of the reaction is fed in at, which with the kill rate picks the pattern
+    /// (0.055 and 0.062 grow coral, 0.03 and 0.062 split into dots)
+    #[arg(long, default_value_t = 0.055)]
+    pub reaction_feed: f32,
+
+    /// Rate the catalyst of the reaction is removed at
+    #[arg(long, default_value_t = 0.062)]
+    pub reaction_kill: f32,
+
+    /// Reaction iterations per simulation step
+    #[arg(long, default_value_t = 8)]
+    pub reaction_speed: u32,
+
+    /// Brightness of the reaction added over the canvas as a backdrop (0.0 hides it)
+    #[arg(long, default_value_t = 0.0)]
+    pub reaction_strength: f32,
+
+    /// Blend in the velocity of a stable fluids simulation, pushed by seeded jets and stirred by
+    /// dragging with a mouse button held
+    #[arg(long)]
+    pub fluid: bool,
+
+    /// Weight of the fluid velocity relative to the main field, in cells per step
+    #[arg(long, default_value_t = 1.0)]
+    pub fluid_weight: f64,
+
+    /// Cells of the fluid grid along the longer side of the window
+    #[arg(long, default_value_t = 64)]
+    pub fluid_resolution: u32,
+
+    /// How quickly the fluid's velocity diffuses, in cells squared per step
+    #[arg(long, default_value_t = 0.0)]
+    pub fluid_viscosity: f32,
+
+    /// Jets at seeded positions and directions pushing the fluid every step
+    #[arg(long, default_value_t = 4)]
+    pub fluid_jets: u32,
+
+    /// Force of every jet, in cells per step squared
+    #[arg(long, default_value_t = 0.05)]
+    pub fluid_force: f32,
+
+    /// Use an analytic field around a point as the main field instead
+    #[arg(long, value_enum)]
+    pub radial: Option<Radial>,
+
+    /// Center of the radial field, where the shorter side of the window spans -1 to 1
+    #[arg(long, num_args = 2, default_values_t = [0.0, 0.0])]
+    pub radial_center: Vec<f64>,
+
+    /// How the noise or image is turned into a heading
+    #[arg(long = "field", value_enum, default_value_t = FieldKind::Angle)]
+    pub field_kind: FieldKind,
+
+    /// Sample the field on a lattice of this many cells along the longer side of the window and
+    /// interpolate between them, faster with many particles (0 samples it at every particle)
+    #[arg(long, default_value_t = 0)]
+    pub grid_resolution: u32,
+
+    /// Multiplies noise input (higher makes more frequent changes in heading)
+    #[arg(long, default_value_t = 15.0)]
+    pub heading_noise_factor: f64,
+
+    /// Multiplies noise output (higher makes larger changes in heading, no effect on curl fields)
+    #[arg(long, default_value_t = 1.0)]
+    pub heading_noise_multiplier: f64,
+
+    /// Octaves the heading noise is summed over (unset for one octave of plain noise, or the
+    /// default six of `fbm`, `ridged-multi` and `billow`)
+    #[arg(long)]
+    pub octaves: Option<usize>,
+
+    /// Frequency ratio between heading noise octaves (2.0 for plain noise, about 2.1 for the
+    /// fractal noises if unset)
+    #[arg(long)]
+    pub lacunarity: Option<f64>,
+
+    /// Amplitude ratio between heading noise octaves (higher gives more detailed turbulence)
+    #[arg(long)]
+    pub persistence: Option<f64>,
+
+    /// Warp the heading noise by feeding it coordinates displaced by itself, once per strength
+    /// given, e.g. `--domain-warp 4,2` (one to three iterations give marbled, organic flow)
+    #[arg(long, value_delimiter = ',')]
+    pub domain_warp: Vec<f64>,
+
+    /// Distance the heading noise moves along time per step, so the field evolves (0.0 for a
+    /// static field)
+    #[arg(long, default_value_t = 0.0)]
+    pub evolution_speed: f64,
+
+    /// Steps after which the evolving field returns exactly to where it started, moving the
+    /// heading noise around a circle in time instead of along a line
+    #[arg(long)]
+    pub loop_period: Option<u64>,
+
+    /// Multiplies noise input (higher makes more frequent changes in color)
+    #[arg(long, default_value_t = 1.0)]
+    pub color_noise_factor: f64,
+
+    /// Multiplies noise output (higher makes larger changes in color)
+    #[arg(long, default_value_t = 1.1)]
+    pub color_noise_multiplier: f64,
+
+    /// Noise function the color is sampled from (unset for the heading's `--noise`)
+    #[arg(long, value_enum)]
+    pub color_noise: Option<NoiseKind>,
+
+    /// Seed of the color noise, so the colors stay put while the heading is reseeded (unset for
+    /// `--seed`)
+    #[arg(long, value_parser = seed_parser())]
+    pub color_seed: Option<u64>,
+
+    /// Octaves the color noise is summed over, like `--octaves`
+    #[arg(long)]
+    pub color_octaves: Option<usize>,
+
+    /// Frequency ratio between color noise octaves, like `--lacunarity`
+    #[arg(long)]
+    pub color_lacunarity: Option<f64>,
+
+    /// Amplitude ratio between color noise octaves, like `--persistence`
+    #[arg(long)]
+    pub color_persistence: Option<f64>,
+
+    /// Start of the hue slice colors are picked from (0.0 to 1.0 is the full hue circle)
+    #[arg(long, default_value_t = 0.0)]
+    pub hue_min: f64,
+
+    /// End of the hue slice colors are picked from
+    #[arg(long, default_value_t = 1.0)]
+    pub hue_max: f64,
+
+    /// What particles are colored by
+    #[arg(long, value_enum, default_value_t = ColorMode::PositionNoise)]
+    pub color_mode: ColorMode,
+
+    /// Image stretched over the window particles take their color from with `--color-mode image`
+    #[arg(long)]
+    pub color_image: Option<PathBuf>,
+
+    /// Palette the color mode indexes into
+    #[arg(long, value_enum, default_value_t = PaletteKind::Hue)]
+    pub palette: PaletteKind,
+
+    /// How colors outside of the palette wrap around into it
+    #[arg(long, value_enum, default_value_t = PaletteMapping::Repeat)]
+    pub palette_mapping: PaletteMapping,
+
+    /// Color space palettes are interpolated and hues are swept in (`oklab` and `lch` are
+    /// perceptually even)
+    #[arg(long, value_enum, default_value_t = ColorSpace::Srgb)]
+    pub color_space: ColorSpace,
+
+    /// Colors of the custom palette, from its start to its end
+    #[arg(long, value_delimiter = ',', default_values_t = [HexColor([0x1b, 0x26, 0x5e]), HexColor([0xff, 0xb0, 0x3b])])]
+    pub palette_stops: Vec<HexColor>,
+
+    /// Color the canvas starts from and trails fade towards
+    #[arg(long, default_value_t = HexColor([0, 0, 0]))]
+    pub background: HexColor,
+
+    /// What the canvas starts from and is cleared to (`transparent` exports PNGs with a transparent
+    /// background for compositing)
+    #[arg(long, value_enum, default_value_t = BackgroundMode::Solid)]
+    pub background_mode: BackgroundMode,
+
+    /// Color the gradient background ends at, starting from `background`
+    #[arg(long, default_value_t = HexColor([0x20, 0x20, 0x30]))]
+    pub background_gradient: HexColor,
+
+    /// Direction the gradient background runs in, in degrees counterclockwise from the right
+    #[arg(long, default_value_t = 90.0)]
+    pub background_angle: f64,
+
+    /// Image stretched over the canvas as the image background
+    #[arg(long)]
+    pub background_image: Option<PathBuf>,
+
+    /// Multiplies velocity (higher makes faster but coarser)
+    #[arg(long = "velocity", default_value_t = 0.25)]
+    pub velocity_multiplier: f64,
+
+    /// Degrees every particle's heading randomly turns by each step, up to either side
+    #[arg(long, default_value_t = 0.0)]
+    pub heading_jitter: f64,
+
+    /// Fraction every particle's speed randomly varies by each step, up or down
+    #[arg(long, default_value_t = 0.0)]
+    pub speed_jitter: f64,
+
+    /// Portion of its previous velocity every particle keeps each step, smoothing its turns
+    /// (0.0 follows the field directly)
+    #[arg(long, default_value_t = 0.0)]
+    pub inertia: f64,
+
+    /// Degrees every particle's heading can turn by each step at most (0.0 turns freely)
+    #[arg(long, default_value_t = 0.0)]
+    pub max_turn: f64,
+
+    /// How particles are advanced over a step (`midpoint` and `rk4` follow the field more closely
+    /// at high velocities, sampling it two or four times per step)
+    #[arg(long, value_enum, default_value_t = Integrator::Euler)]
+    pub integrator: Integrator,
+
+    /// Length of a step, multiplying the velocity (smaller steps trace the field more smoothly
+    /// but move slower)
+    #[arg(long, default_value_t = 1.0)]
+    pub dt: f64,
+
+    /// Advance the simulation by this many fixed steps per second regardless of the frame rate,
+    /// so runs look the same on every machine (unset takes one step per frame)
+    #[arg(long)]
+    pub steps_per_second: Option<f64>,
+
+    /// Cap the window at this many frames per second, sleeping in between to spare the CPU
+    #[arg(long)]
+    pub max_fps: Option<f64>,
+
+    /// Present frames as soon as they are drawn instead of waiting for the display, so the
+    /// simulation runs as fast as it can, e.g. to bake trails (only read when the window opens)
+    #[arg(long)]
+    pub no_vsync: bool,
+
+    /// Draw particles between their last two fixed steps once per frame rather than at every
+    /// step, for smooth motion when stepping slower than the frame rate
+    #[arg(long)]
+    pub interpolate: bool,
+
+    /// Multiplies the steps taken per frame, or per fixed timestep (0.1 takes a step every tenth
+    /// frame)
+    #[arg(long, default_value_t = 1.0)]
+    pub speed: f64,
+
+    /// Steps taken per frame while fast-forwarding, only drawing the last
+    #[arg(long, default_value_t = 16)]
+    pub fast_forward: u32,
+
+    /// Steps kept to rewind through with the left arrow, each holding a copy of every particle
+    /// (0 disables rewinding)
+    #[arg(long, default_value_t = 60)]
+    pub history: usize,
+
+    /// Size of rendered points (1.0 is one pixel)
+    #[arg(long, default_value_t = 1.0)]
+    pub point_size: f64,
+
+    /// Shape each point is drawn as
+    #[arg(long = "shape", value_enum, default_value_t = ParticleShape::Circle)]
+    pub particle_shape: ParticleShape,
+
+    /// Draw every particle this many times, rotated evenly around the center of the window
+    #[arg(long, default_value_t = 1)]
+    pub symmetry: u32,
+
+    /// Also draw every particle mirrored across the vertical axis, a kaleidoscope with
+    /// `--symmetry`
+    #[arg(long)]
+    pub mirror: bool,
+
+    /// Vary the size of points and the width of paths by noise or by speed instead
+    #[arg(long, value_enum, default_value_t = SizeMode::Constant)]
+    pub size_mode: SizeMode,
+
+    /// Size of points and paths where the size noise or speed is lowest
+    #[arg(long, default_value_t = 0.5)]
+    pub size_min: f64,
+
+    /// Size of points and paths where the size noise or speed is highest
+    #[arg(long, default_value_t = 3.0)]
+    pub size_max: f64,
+
+    /// Multiplies size noise input (higher makes more frequent changes in size)
+    #[arg(long, default_value_t = 4.0)]
+    pub size_noise_factor: f64,
+
+    /// Draw particles as points, or their recent paths as lines or ribbons (best with
+    /// `--trail-fade 1`, so only the current paths show)
+    #[arg(long, value_enum, default_value_t = RenderMode::Points)]
+    pub render_mode: RenderMode,
+
+    /// Number of recent positions lines and ribbons are drawn through
+    #[arg(long, default_value_t = 16)]
+    pub path_length: usize,
+
+    /// Width of lines, and of ribbons at the particle (1.0 is one pixel)
+    #[arg(long, default_value_t = 2.0)]
+    pub path_width: f64,
+
+    /// How quickly lines and ribbons fade out towards their tail (0.0 keeps them opaque)
+    #[arg(long, default_value_t = 1.0)]
+    pub path_falloff: f64,
+
+    /// Brightness of the field heading drawn behind the particles (0.0 for a plain background)
+    #[arg(long = "wash-strength", default_value_t = 0.0)]
+    pub background_wash_strength: f64,
+
+    /// Width of the low resolution field image the wash is scaled up from
+    #[arg(long = "wash-resolution", default_value_t = 64)]
+    pub background_wash_resolution: u32,
+
+    /// How the field is drawn over the canvas while its debug view is toggled with F2
+    #[arg(long, value_enum, default_value_t = FieldView::Arrows)]
+    pub field_view: FieldView,
+
+    /// Distance between the arrows or streamlines of the field debug view, in points
+    #[arg(long, default_value_t = 32.0)]
+    pub field_view_spacing: f64,
+
+    /// Sum colored trails as light so dense crossings glow white
+    #[arg(long)]
+    pub additive_hue: bool,
+
+    /// Exposure of the additive hue tone mapping (higher saturates sooner)
+    #[arg(long, default_value_t = 0.2)]
+    pub additive_hue_exposure: f32,
+
+    /// Opacity of the background drawn over the canvas every frame, fading older trails (0.0 keeps
+    /// trails forever, 1.0 only shows the current positions)
+    #[arg(long, default_value_t = 0.0)]
+    pub trail_fade: f32,
+
+    /// Color trails fade towards instead of the background, e.g. `#ffffff` to bleach them
+    #[arg(long)]
+    pub fade_color: Option<HexColor>,
+
+    /// Radius around the mouse particles are attracted within while left clicking, or repelled
+    /// within while right clicking, in points
+    #[arg(long, default_value_t = 100.0)]
+    pub mouse_radius: f64,
+
+    /// Strength of the mouse attraction relative to the field (1.0 is as strong as the field)
+    #[arg(long, default_value_t = 1.0)]
+    pub mouse_strength: f64,
+
+    /// How strongly particles steer away from neighbors crowding them, like a flock of boids
+    #[arg(long, default_value_t = 0.0)]
+    pub separation: f64,
+
+    /// How strongly particles steer along the average heading of their neighbors
+    #[arg(long, default_value_t = 0.0)]
+    pub alignment: f64,
+
+    /// How strongly particles steer towards the center of their neighbors
+    #[arg(long, default_value_t = 0.0)]
+    pub cohesion: f64,
+
+    /// Distance within which other particles count as neighbors for flocking
+    #[arg(long, default_value_t = 10.0)]
+    pub flock_radius: f64,
+
+    /// Angle between the sensors of physarum agents, in degrees to either side of ahead
+    #[arg(long, default_value_t = 22.5)]
+    pub sensor_angle: f64,
+
+    /// How far ahead physarum agents sense the trail map, in pixels
+    #[arg(long, default_value_t = 9.0)]
+    pub sensor_distance: f64,
+
+    /// How far physarum agents turn towards the stronger trail each step, in degrees
+    #[arg(long, default_value_t = 45.0)]
+    pub turn_angle: f64,
+
+    /// How far physarum agents move each step, in pixels
+    #[arg(long, default_value_t = 1.0)]
+    pub agent_speed: f64,
+
+    /// Trail each physarum agent deposits per step
+    #[arg(long, default_value_t = 5.0)]
+    pub deposit: f32,
+
+    /// Portion of the physarum trail map blurred into its neighbors each step
+    #[arg(long, default_value_t = 1.0)]
+    pub diffusion: f32,
+
+    /// Portion of the physarum trail map lost each step
+    #[arg(long, default_value_t = 0.1)]
+    pub trail_decay: f32,
+
+    /// Degrees per second the camera of the volume sketch orbits around its vertical axis
+    #[arg(long, default_value_t = 10.0)]
+    pub orbit_speed: f64,
+
+    /// Degrees the camera of the volume sketch looks down onto the volume from
+    #[arg(long, default_value_t = 20.0)]
+    pub camera_elevation: f64,
+
+    /// Distance of the camera of the volume sketch from the center, in half sides of the volume
+    #[arg(long, default_value_t = 3.0)]
+    pub camera_distance: f64,
+
+    /// Vertical field of view of the camera of the volume sketch, in degrees
+    #[arg(long, default_value_t = 50.0)]
+    pub field_of_view: f64,
+
+    /// Portion of their opacity particles at the far side of the volume lose against those at
+    /// the near side
+    #[arg(long, default_value_t = 0.8)]
+    pub depth_fade: f32,
+
+    /// Particles spawned per second, removing them once they leave the window (0.0 for a fixed
+    /// population)
+    #[arg(long, default_value_t = 0.0)]
+    pub spawn_rate: f64,
+
+    /// What happens to particles leaving the window
+    #[arg(long = "edge", value_enum, default_value_t = EdgeMode::Exit)]
+    pub edge_mode: EdgeMode,
+
+    /// Steps a particle lives before it respawns, keeping the density even over long runs (0
+    /// for particles that live forever)
+    #[arg(long, default_value_t = 0)]
+    pub max_lifetime: u64,
+
+    /// Where particles respawn once they reach the end of their lifetime
+    #[arg(long, value_enum, default_value_t = RespawnMode::Random)]
+    pub respawn: RespawnMode,
+
+    /// Cells across the shorter side of the window particles are counted in for
+    /// `--respawn coverage`
+    #[arg(long, default_value_t = 16)]
+    pub coverage_cells: usize,
+
+    /// What obstacles from the config file do to particles
+    #[arg(long, value_enum, default_value_t = ObstacleMode::Deflect)]
+    pub obstacle_mode: ObstacleMode,
+
+    /// Distance from obstacles particles start being deflected at, in the centered units
+    /// obstacles are defined in
+    #[arg(long, default_value_t = 0.05)]
+    pub obstacle_margin: f64,
+
+    /// Distance particles keep from paths already drawn, stopping once they come closer, for
+    /// evenly spaced lines that never overlap. Off if 0.
+    #[arg(long, default_value_t = 0.0)]
+    pub path_spacing: f64,
+
+    /// Seed for random number generator and noise functions (set to 0 for a random seed, noise
+    /// only uses a 32-bit hash of it). The seed is printed and `{seed}` in output paths is
+    /// replaced with it, so any output can be reproduced. At most 2^63 - 1, the largest integer
+    /// config files hold.
+    #[arg(long, default_value_t = 0, value_parser = seed_parser())]
+    pub seed: u64,
+
+    /// Start from randomized parameters, printed so they can be reproduced (`X` picks new ones
+    /// while running, `P` saves them as a preset)
+    #[arg(long)]
+    pub randomize: bool,
+
+    /// Number of threads particles are stepped on (0 for one per core)
+    #[arg(long, default_value_t = 0)]
+    pub threads: usize,
+
+    /// File the state of the sketch is saved to with F5, to continue it later with `--resume`
+    #[arg(long, default_value = "state.json")]
+    pub save_state: PathBuf,
+
+    /// Continue from a state saved with F5, with the parameters it was saved with
+    #[arg(long)]
+    #[serde(skip)]
+    pub resume: Option<PathBuf>,
+
+    /// Save the final frame and the config reproducing it into the capture directory when the
+    /// window closes or Ctrl+C is pressed
+    #[arg(long)]
+    pub save_on_exit: bool,
+
+    /// Log the timings of every frame to this CSV file, for benchmarking
+    #[arg(long)]
+    pub stats_csv: Option<PathBuf>,
+
+    /// Only log warnings and errors
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Also log the timings of every frame, and the progress of every step given twice
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Log every line as a JSON object, with the frame timings as fields, for other programs
+    #[arg(long)]
+    pub log_json: bool,
+
+    /// Advect and draw particles in a compute shader instead of on the CPU. Only supports the
+    /// Perlin angle field with a fixed population of one pixel points.
+    #[arg(long)]
+    pub gpu: bool,
+
+    /// Samples per pixel the canvas and offscreen renders are drawn with, smoothing the edges of
+    /// dots and lines (1 for none, up to 4 on most GPUs, ignored with `--gpu`)
+    #[arg(long, default_value_t = 1)]
+    pub msaa: u32,
+
+    /// Render this many seeds headlessly and save the best scoring ones instead of opening a
+    /// window
+    #[arg(long)]
+    pub search: Option<u32>,
+
+    /// Number of simulation steps rendered for each seed in search mode
+    #[arg(long, default_value_t = 1000)]
+    pub search_steps: u32,
+
+    /// Number of best scoring seeds saved in search mode
+    #[arg(long, default_value_t = 5)]
+    pub search_top_k: usize,
+
+    /// Width of the virtual canvas rendered in search mode
+    #[arg(long, default_value_t = 1024)]
+    pub search_width: u32,
+
+    /// Height of the virtual canvas rendered in search mode
+    #[arg(long, default_value_t = 768)]
+    pub search_height: u32,
+
+    /// Time updating and drawing the sketch without a window at several particle counts and print
+    /// a summary instead of opening a window
+    #[arg(long)]
+    pub bench: bool,
+
+    /// Number of timed iterations at every particle count in bench mode
+    #[arg(long, default_value_t = 300)]
+    pub bench_iterations: usize,
+
+    /// Starting points along each axis benchmarked in bench mode, one row each
+    #[arg(long, value_delimiter = ',', default_values_t = [16, 32, 64, 128])]
+    pub bench_points: Vec<u32>,
+
+    /// Render offscreen to this PNG file instead of opening a window
+    #[arg(long)]
+    pub render: Option<PathBuf>,
+
+    /// Width of the offscreen render, in pixels
+    #[arg(long, default_value_t = 3840)]
+    pub render_width: u32,
+
+    /// Height of the offscreen render, in pixels
+    #[arg(long, default_value_t = 2160)]
+    pub render_height: u32,
+
+    /// Number of simulation steps run before saving the offscreen render
+    #[arg(long, default_value_t = 1000)]
+    pub render_steps: u64,
+
+    /// Render in tiles of at most this many pixels along each side, replaying the simulation for
+    /// every tile, for images larger than the GPU can hold
+    #[arg(long)]
+    pub render_tile: Option<u32>,
+
+    /// Draw offscreen renders and animations this many times larger, then average them back down
+    /// to their size, for prints
+    #[arg(long, default_value_t = 1)]
+    pub supersample: u32,
+
+    /// Trace every particle's whole streamline before drawing them all at once, instead of
+    /// stepping the particles together, when rendering or exporting paths
+    #[arg(long)]
+    pub streamlines: bool,
+
+    /// Steps a streamline is traced for at most, if it doesn't leave the window or stop first
+    #[arg(long, default_value_t = 1000)]
+    pub streamline_steps: usize,
+
+    /// How traced streamlines are colored
+    #[arg(long, value_enum, default_value_t = StreamlineColor::Start)]
+    pub streamline_color: StreamlineColor,
+
+    /// Draw streamlines from the shortest to the longest, so the long ones end up on top
+    #[arg(long)]
+    pub sort_streamlines: bool,
+
+    /// Also save the parameters of every render as JSON next to it, on top of embedding them
+    #[arg(long)]
+    pub sidecar: bool,
+
+    /// Save the field sampled on a lattice to a `.csv` or `.npy` file, at the size of `--render`
+    #[arg(long)]
+    pub export_field: Option<PathBuf>,
+
+    /// Cells along the longer side of the lattice `--export-field` samples
+    #[arg(long, default_value_t = 128)]
+    pub field_file_resolution: u32,
+
+    /// Export the particle paths as SVG polylines for pen plotting, rendered like `--render`
+    #[arg(long)]
+    pub svg: Option<PathBuf>,
+
+    /// Export the particle paths as G-code for pen plotting, rendered like `--render`
+    #[arg(long)]
+    pub gcode: Option<PathBuf>,
+
+    /// Stroke width of exported SVG paths, in pixels
+    #[arg(long, default_value_t = 1.0)]
+    pub stroke_width: f64,
+
+    /// Number of color layers exported paths are split into, one pen each
+    #[arg(long, default_value_t = 4)]
+    pub plot_layers: usize,
+
+    /// Width of the G-code plot, in millimeters
+    #[arg(long, default_value_t = 200.0)]
+    pub plot_width: f64,
+
+    /// Save a screenshot once this many frames have been simulated, then exit
+    #[arg(long)]
+    pub capture: Option<u64>,
+
+    /// Save every frame to this directory as a numbered PNG sequence
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+
+    /// Stop recording and exit after this many frames
+    #[arg(long)]
+    pub record_frames: Option<u64>,
+
+    /// Frame rate the recording is simulated at, independent of how fast frames are rendered
+    #[arg(long, default_value_t = 60.0)]
+    pub record_fps: f64,
+
+    /// Write every frame of the canvas as raw RGBA to this file or named pipe, for a tool like
+    /// ffmpeg to pick up live
+    #[arg(long)]
+    pub stream: Option<PathBuf>,
+
+    /// Publish the canvas as an NDI source of this name every frame, for OBS, Resolume or
+    /// TouchDesigner to receive over the network (needs `--features ndi` and the NDI runtime)
+    #[arg(long)]
+    pub ndi: Option<String>,
+
+    /// Render an animated GIF, or an APNG for any other extension, offscreen like `--render`
+    #[arg(long)]
+    pub animation: Option<PathBuf>,
+
+    /// Number of frames captured for the animation, at least 1
+    #[arg(long, default_value_t = 120, value_parser = clap::value_parser!(u64).range(1..))]
+    pub animation_frames: u64,
+
+    /// Simulation steps between captured frames
+    #[arg(long, default_value_t = 1)]
+    pub animation_steps: u64,
+
+    /// Frame rate the animation plays back at
+    #[arg(long, default_value_t = 30.0)]
+    pub animation_fps: f64,
+
+    /// Play the animation forward and then backward, so it loops without a jump
+    #[arg(long)]
+    pub ping_pong: bool,
+
+    /// Cut the animation at the frame most like the first one, so it loops with the smallest jump
+    #[arg(long)]
+    pub loop_search: bool,
+
+    /// Record the mouse, keyboard, panel and controller input of the session to this file
+    #[arg(long)]
+    pub record_input: Option<PathBuf>,
+
+    /// Replay a session recorded with `--record-input`, or re-render it offline with `--render`
+    #[arg(long, visible_alias = "replay")]
+    pub replay_input: Option<PathBuf>,
+
+    /// Listen to control changes from the MIDI input port whose name contains this, driving the
+    /// parameters bound to them by the `[[midi]]` tables of the config file
+    #[arg(long)]
+    pub midi_port: Option<String>,
+
+    /// Capture the audio input device whose name contains this, or the default one for
+    /// `default`, driving the parameters bound to frequency bands by the `[[audio]]` tables of the
+    /// config file
+    #[arg(long)]
+    pub audio_input: Option<String>,
+
+    /// Portion of a band's level kept per frame as it falls off, so parameters pulse with the beat
+    /// instead of jittering
+    #[arg(long, default_value_t = 0.85)]
+    pub audio_smoothing: f64,
+
+    /// Listen for OSC messages on this UDP port, setting parameters from `/flow/<parameter>`,
+    /// the seed from `/flow/seed` and the palette from `/flow/palette`
+    #[arg(long)]
+    pub osc_port: Option<u16>,
+
+    /// Cycle through the presets unattended, showing each for this many seconds with a fresh seed
+    #[arg(long)]
+    pub gallery: Option<f64>,
+
+    /// Presets the gallery cycles through, in order (every one in `presets/` by default)
+    #[arg(long, value_delimiter = ',')]
+    pub gallery_presets: Vec<String>,
+
+    /// Seconds at the end of each gallery preset over which the trails fade out
+    #[arg(long, default_value_t = 2.0)]
+    pub gallery_fade: f64,
+
+    /// Restart the simulation after this many frames, at least 1, fading in and out at the loop
+    /// point
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub loop_frames: Option<u64>,
+
+    /// Portion of a loop spent fading in and again fading out, above 0 and at most 0.5
+    #[arg(long, default_value_t = 0.2, value_parser = fade_portion_parser)]
+    pub loop_fade_portion: f64,
+
+    /// Additional species advected alongside the main particles, only read from config files
+    #[arg(skip)]
+    pub species: Vec<SpeciesConfig>,
+
+    /// Shapes particles flow around or are absorbed by, only read from config files
+    #[arg(skip)]
+    pub obstacles: Vec<Obstacle>,
+
+    /// Extra windows showing parts of the canvas, e.g. one per monitor of a video wall, only read
+    /// from config files when the window opens
+    #[arg(skip)]
+    pub outputs: Vec<Output>,
+
+    /// Fields blended on top of the main one, only read from config files
+    #[arg(skip)]
+    pub fields: Vec<FieldLayer>,
+
+    /// Parameters driven by MIDI control changes, only read from config files
+    #[arg(skip)]
+    pub midi: Vec<MidiBinding>,
+
+    /// Parameters driven by audio frequency bands, only read from config files
+    #[arg(skip)]
+    pub audio: Vec<AudioBinding>,
+
+    /// Parameters animated by keyframes over time, only read from config files
+    #[arg(skip)]
+    pub timeline: Vec<Track>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::parse_from([env!("CARGO_PKG_NAME")])
+    }
+}
+
+impl Config {
+    // Parse the command line, layered over the config file or preset if one is given
+    pub fn load() -> Config {
+        Config::try_load().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_load() -> Result<Config, String> {
+        Config::try_load_with(|_| {})
+    }
+
+    // The command line layered over a preset instead of the file or preset it names
+    pub fn try_load_preset(name: &str) -> Result<Config, String> {
+        Config::try_load_with(|config| {
+            config.config_file = None;
+            config.preset = Some(name.to_string());
+        })
+    }
+
+    fn try_load_with(edit: impl FnOnce(&mut Config)) -> Result<Config, String> {
+        let matches = Config::command().get_matches_from(web::args());
+        let mut config = Config::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+        edit(&mut config);
+
+        let path = match config.path() {
+            Some(path) => path,
+            None => return Ok(config),
+        };
+
+        let mut table = read_table(&path)?;
+
+        // Flags given explicitly on the command line override the file, fields without a flag are
+        // only read from the file
+        let flags = toml::Value::try_from(&config)
+            .map_err(|err| format!("Invalid flags for {}: {}", path.display(), err))?;
+        for (key, value) in flags.as_table().unwrap() {
+            let flag = matches.ids().any(|id| id.as_str() == key);
+            if flag && matches.value_source(key) == Some(ValueSource::CommandLine) {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+
+        let mut loaded: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|err| format!("Invalid config {}: {}", path.display(), err))?;
+        loaded.config_file = config.config_file;
+        loaded.preset = config.preset;
+
+        Ok(loaded)
+    }
+
+    // Whether switching to `config` starts a new simulation rather than reconfiguring this one
+    pub fn reseeds(&self, config: &Config) -> bool {
+        config.seed != self.seed
+            || config.point_count != self.point_count
+            || config.point_delta != self.point_delta
+    }
+
+    // File the config was loaded from, if any
+    pub fn path(&self) -> Option<PathBuf> {
+        match (&self.config_file, &self.preset) {
+            (Some(path), _) => Some(path.clone()),
+            (None, Some(preset)) => Some(preset_path(preset)),
+            (None, None) => None,
+        }
+    }
+}
+
+// Watches the config file for changes by polling its modification time, so parameters can be
+// tuned live without restarting and losing the accumulated trails
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified(&path);
+        ConfigWatcher { path, modified }
+    }
+
+    // Reload the config if the file changed since the last poll
+    pub fn poll(&mut self) -> Option<Result<Config, String>> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+
+        self.modified = modified;
+        Some(Config::try_load())
+    }
+}
+
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+// Seeds up to the largest TOML integer, so every config can be saved to a file
+fn seed_parser() -> clap::builder::RangedU64ValueParser {
+    clap::value_parser!(u64).range(..=seed::MAX_SEED)
+}
+
+// Fade portions above 0, which would divide by zero, and up to half, where the fades meet
+fn fade_portion_parser(value: &str) -> Result<f64, String> {
+    let portion: f64 = value.parse().map_err(|err| format!("{}", err))?;
+    match portion > 0.0 && portion <= 0.5 {
+        true => Ok(portion),
+        false => Err(format!("{} isn't above 0 and at most 0.5", portion)),
+    }
+}
+
+// Presets are shipped in the crate's `presets/` directory as TOML files
+fn preset_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("presets")
+        .join(name)
+        .with_extension("toml")
+}
+
+impl Config {
+    // Save as a TOML file that `--config` loads these parameters from
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        // loading it reproduces these parameters rather than picking new ones
+        let config = Config {
+            randomize: false,
+            ..self.clone()
+        };
+        let table = toml::Value::try_from(&config).map_err(|err| err.to_string())?;
+        let text = toml::to_string(&table).map_err(|err| err.to_string())?;
+        std::fs::write(path, text).map_err(|err| err.to_string())
+    }
+
+    // Save as a preset named `name`, returning its path
+    pub fn save_preset(&self, name: &str) -> Result<PathBuf, String> {
+        let path = preset_path(name);
+        self.save(&path)?;
+        Ok(path)
+    }
+}
+
+// Names of every preset shipped, sorted
+pub fn presets() -> Vec<String> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("presets");
+    let mut presets: Vec<String> = std::fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.path().file_stem()?.to_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+    presets.sort();
+    presets
+}
+
+fn read_table(path: &Path) -> Result<toml::value::Table, String> {
+    if !web::FILES {
+        return Err(format!(
+            "Config {} can't be read on the web",
+            path.display()
+        ));
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read config {}: {}", path.display(), err))?;
+
+    let value: Result<toml::Value, String> = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&contents).map_err(|err| err.to_string()),
+        _ => toml::from_str(&contents).map_err(|err| err.to_string()),
+    };
+
+    match value {
+        Ok(toml::Value::Table(table)) => Ok(table),
+        Ok(_) => Err(format!("Config {} is not a table", path.display())),
+        Err(err) => Err(format!(
+            "Could not parse config {}: {}",
+            path.display(),
+            err
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_round_trips() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", std::process::id()));
+        let config = Config {
+            seed: seed::MAX_SEED,
+            randomize: true,
+            ..Config::default()
+        };
+        config.save(&path).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let loaded: Config = toml::from_str(&text).unwrap();
+        assert!(
+            loaded
+                == Config {
+                    randomize: false,
+                    ..config
+                }
+        );
+    }
+
+    #[test]
+    fn save_reports_io_errors() {
+        let path = std::env::temp_dir()
+            .join("missing-directory")
+            .join("config.toml");
+        assert!(Config::default().save(&path).is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::Vector2;
+use rand::Rng;
+
+// Coarse grid counting the particles in every cell, so respawned particles can fill in the
+// patches the field has emptied out
+pub struct CoverageMap {
+    counts: Vec<u32>, // row-major
+    columns: usize,
+    rows: usize,
+    cell: Vector2, // size of a cell
+}
+
+impl CoverageMap {
+    // `cells` across the shorter side of the window, square-ish cells across the longer one
+    pub fn new(
+        positions: impl Iterator<Item = Vector2>,
+        cells: usize,
+        x_end: f64,
+        y_end: f64,
+    ) -> Self {
+        let scale = x_end.min(y_end) / cells.max(1) as f64;
+        let columns = ((x_end / scale).round() as usize).max(1);
+        let rows = ((y_end / scale).round() as usize).max(1);
+
+        let mut map = CoverageMap {
+            counts: vec![0; columns * rows],
+            columns,
+            rows,
+            cell: Vector2 {
+                x: x_end / columns as f64,
+                y: y_end / rows as f64,
+            },
+        };
+        for pos in positions {
+            map.add(pos);
+        }
+        map
+    }
+
+    // Cell a position falls in, if it is within the window
+    fn index(&self, pos: Vector2) -> Option<usize> {
+        let (column, row) = (pos.x / self.cell.x, pos.y / self.cell.y);
+        let inside =
+            (0.0..self.columns as f64).contains(&column) && (0.0..self.rows as f64).contains(&row);
+        inside.then(|| row as usize * self.columns + column as usize)
+    }
+
+    pub fn add(&mut self, pos: Vector2) {
+        if let Some(i) = self.index(pos) {
+            self.counts[i] += 1;
+        }
+    }
+
+    pub fn remove(&mut self, pos: Vector2) {
+        if let Some(i) = self.index(pos) {
+            self.counts[i] = self.counts[i].saturating_sub(1);
+        }
+    }
+
+    // Random position in a cell picked by how far it is below the average count, anywhere if
+    // coverage is already even. Positions should be added back as they are placed so a batch of
+    // respawns spreads out.
+    pub fn position(&self, rng: &mut impl Rng) -> Vector2 {
+        let mean = self.counts.iter().sum::<u32>() as f64 / self.counts.len() as f64;
+        let deficits: Vec<f64> = self
+            .counts
+            .iter()
+            .map(|&count| (mean - count as f64).max(0.0))
+            .collect();
+        let total: f64 = deficits.iter().sum();
+
+        let i = if total > 0.0 {
+            let mut target = rng.gen_range(0.0..total);
+            deficits
+                .iter()
+                .position(|&deficit| {
+                    target -= deficit;
+                    target < 0.0
+                })
+                .unwrap_or(self.counts.len() - 1)
+        } else {
+            rng.gen_range(0..self.counts.len())
+        };
+
+        let (column, row) = (i % self.columns, i / self.columns);
+        Vector2 {
+            x: (column as f64 + rng.gen_range(0.0..1.0)) * self.cell.x,
+            y: (row as f64 + rng.gen_range(0.0..1.0)) * self.cell.y,
+        }
+    }
+}
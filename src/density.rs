@@ -0,0 +1,42 @@
+use nannou::image::{GrayImage, Luma};
+
+// Counts how many times particles landed on each pixel of a canvas
+pub struct DensityGrid {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<u32>,
+}
+
+impl DensityGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        DensityGrid {
+            width,
+            height,
+            cells: vec![0; width * height],
+        }
+    }
+
+    // Add a hit at a pixel position (points outside of the canvas are ignored)
+    pub fn splat(&mut self, x: f64, y: f64) {
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return;
+        }
+
+        self.cells[y as usize * self.width + x as usize] += 1;
+    }
+
+    // Tone map the counts logarithmically so sparse trails stay visible next to dense ones
+    pub fn to_image(&self) -> GrayImage {
+        let max = self.cells.iter().copied().max().unwrap_or(0);
+        let scale = if max == 0 {
+            0.0
+        } else {
+            255.0 / (max as f64).ln_1p()
+        };
+
+        GrayImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let count = self.cells[y as usize * self.width + x as usize];
+            Luma([((count as f64).ln_1p() * scale) as u8])
+        })
+    }
+}
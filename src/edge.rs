@@ -0,0 +1,56 @@
+use crate::Vector2;
+use serde::{Deserialize, Serialize};
+
+// What happens to particles leaving the window
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeMode {
+    Exit,    // keep going outside of the window, never to be seen again
+    Wrap,    // reappear on the opposite side, as if the window were a torus
+    Bounce,  // reflect off the border
+    Respawn, // reappear where `--respawn` places them
+    Clamp,   // stick to the border
+}
+
+// Outcome of keeping a particle within the window
+#[derive(Copy, Clone, PartialEq)]
+pub enum Crossing {
+    Stayed,  // moved continuously, so its path goes on
+    Jumped,  // teleported, so its path is broken
+    Respawn, // should be respawned
+}
+
+impl EdgeMode {
+    pub fn apply(self, pos: &mut Vector2, vel: &mut Vector2, x_end: f64, y_end: f64) -> Crossing {
+        let inside = (0.0..=x_end).contains(&pos.x) && (0.0..=y_end).contains(&pos.y);
+        if inside {
+            return Crossing::Stayed;
+        }
+
+        match self {
+            EdgeMode::Exit => Crossing::Stayed,
+            EdgeMode::Wrap => {
+                pos.x = pos.x.rem_euclid(x_end);
+                pos.y = pos.y.rem_euclid(y_end);
+                Crossing::Jumped
+            }
+            EdgeMode::Bounce => {
+                let reflect = |value: &mut f64, velocity: &mut f64, end: f64| {
+                    if *value < 0.0 || *value > end {
+                        *value = value.clamp(0.0, end) * 2.0 - *value;
+                        *velocity = -*velocity;
+                    }
+                };
+                reflect(&mut pos.x, &mut vel.x, x_end);
+                reflect(&mut pos.y, &mut vel.y, y_end);
+                Crossing::Stayed
+            }
+            EdgeMode::Respawn => Crossing::Respawn,
+            EdgeMode::Clamp => {
+                pos.x = pos.x.clamp(0.0, x_end);
+                pos.y = pos.y.clamp(0.0, y_end);
+                Crossing::Stayed
+            }
+        }
+    }
+}
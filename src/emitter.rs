@@ -0,0 +1,219 @@
+use crate::config::Config;
+use crate::Vector2;
+use nannou::image;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+const POISSON_ATTEMPTS: usize = 30; // candidates tried around every point before it's retired
+const POISSON_PACKING: f64 = 0.7; // of the spacing giving `count` points in a hexagonal packing
+
+// Where particles are placed when seeding, spawning and, with `--respawn emitter`, respawning
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmitterKind {
+    Grid,        // a `point_count` by `point_count` grid offset by `point_delta`, spawning anywhere
+    Random,      // anywhere, uniformly
+    Ring,        // around the circle of `emitter_radius` at `emitter_center`
+    Line,        // along the segment `emitter_line`
+    PoissonDisk, // evenly but randomly spaced, without the grid's rows or random clumps
+    Image,       // on the pixels of `emitter_image`, more likely the brighter they are
+}
+
+pub struct Emitter {
+    kind: EmitterKind,
+    center: [f64; 2], // in centered units, the shorter side of the canvas spanning -1 to 1
+    radius: f64,
+    line: [f64; 4],                          // start and end, in centered units
+    image: Option<(usize, usize, Vec<f64>)>, // width, height and cumulative luminance, row-major
+}
+
+impl Emitter {
+    pub fn new(config: &Config) -> Self {
+        let image = match (config.emitter, &config.emitter_image) {
+            (EmitterKind::Image, Some(path)) => {
+                let image = image::open(path)
+                    .unwrap_or_else(|err| {
+                        panic!("Could not load image {}: {}", path.display(), err)
+                    })
+                    .to_luma8();
+                let mut total = 0.0;
+                let cumulative = image
+                    .pixels()
+                    .map(|pixel| {
+                        total += pixel[0] as f64 / 255.0;
+                        total
+                    })
+                    .collect();
+                Some((image.width() as usize, image.height() as usize, cumulative))
+            }
+            (EmitterKind::Image, None) => panic!("The image emitter needs `--emitter-image`"),
+            _ => None,
+        };
+
+        Emitter {
+            kind: config.emitter,
+            center: [config.emitter_center[0], config.emitter_center[1]],
+            radius: config.emitter_radius,
+            line: [0, 1, 2, 3].map(|i| config.emitter_line[i]),
+            image,
+        }
+    }
+
+    // Positions of the `point_count` squared flows a simulation is seeded with
+    pub fn positions(
+        &self,
+        config: &Config,
+        rng: &mut impl Rng,
+        x_end: f64,
+        y_end: f64,
+    ) -> Vec<Vector2> {
+        let point_count = config.point_count as usize;
+        let count = point_count * point_count;
+        let along = |i: usize| i as f64 / count.max(1) as f64;
+
+        match self.kind {
+            EmitterKind::Grid => {
+                let mut positions = Vec::with_capacity(count);
+                for y in 0..point_count {
+                    for x in 0..point_count {
+                        let (u, v) = (x as f64, y as f64);
+                        positions.push(Vector2 {
+                            x: x_end
+                                * (u / point_count as f64 + rng.gen_range(0.0..config.point_delta)),
+                            y: y_end
+                                * (v / point_count as f64 + rng.gen_range(0.0..config.point_delta)),
+                        });
+                    }
+                }
+                positions
+            }
+            EmitterKind::Ring => (0..count)
+                .map(|i| self.ring(along(i), x_end, y_end))
+                .collect(),
+            EmitterKind::Line => (0..count)
+                .map(|i| self.segment((i as f64 + 0.5) / count as f64, x_end, y_end))
+                .collect(),
+            EmitterKind::PoissonDisk => poisson_disk(rng, count, x_end, y_end),
+            EmitterKind::Random | EmitterKind::Image => (0..count)
+                .map(|_| self.position(rng, x_end, y_end))
+                .collect(),
+        }
+    }
+
+    // A single random position, for spawning and respawning
+    pub fn position(&self, rng: &mut impl Rng, x_end: f64, y_end: f64) -> Vector2 {
+        match (self.kind, &self.image) {
+            (EmitterKind::Ring, _) => self.ring(rng.gen_range(0.0..1.0), x_end, y_end),
+            (EmitterKind::Line, _) => self.segment(rng.gen_range(0.0..1.0), x_end, y_end),
+            (EmitterKind::Image, Some((width, height, cumulative))) => {
+                let total = cumulative.last().copied().unwrap_or(0.0);
+                let target = rng.gen_range(0.0..total.max(f64::MIN_POSITIVE));
+                let pixel = cumulative.partition_point(|&sum| sum <= target);
+                let (px, py) = (pixel % width, pixel / width);
+
+                // images go down from the top, positions go up
+                Vector2 {
+                    x: (px as f64 + rng.gen_range(0.0..1.0)) / *width as f64 * x_end,
+                    y: (1.0 - (py as f64 + rng.gen_range(0.0..1.0)) / *height as f64) * y_end,
+                }
+            }
+            _ => Vector2 {
+                x: rng.gen_range(0.0..x_end),
+                y: rng.gen_range(0.0..y_end),
+            },
+        }
+    }
+
+    // Point `t` of the way around the ring, starting on the right
+    fn ring(&self, t: f64, x_end: f64, y_end: f64) -> Vector2 {
+        let angle = t * TAU;
+        let [cx, cy] = self.center;
+        let point = [
+            cx + self.radius * angle.cos(),
+            cy + self.radius * angle.sin(),
+        ];
+        position(point, x_end, y_end)
+    }
+
+    // Point `t` of the way along the line
+    fn segment(&self, t: f64, x_end: f64, y_end: f64) -> Vector2 {
+        let [x0, y0, x1, y1] = self.line;
+        position([x0 + (x1 - x0) * t, y0 + (y1 - y0) * t], x_end, y_end)
+    }
+}
+
+// Position of a point in centered units
+fn position([x, y]: [f64; 2], x_end: f64, y_end: f64) -> Vector2 {
+    let scale = x_end.min(y_end);
+    Vector2 {
+        x: x_end / 2.0 + x * scale / 2.0,
+        y: y_end / 2.0 + y * scale / 2.0,
+    }
+}
+
+// Bridson's algorithm, spaced so about `count` points fit
+fn poisson_disk(rng: &mut impl Rng, count: usize, x_end: f64, y_end: f64) -> Vec<Vector2> {
+    let spacing = (x_end * y_end * 2.0 / (3f64.sqrt() * count.max(1) as f64)).sqrt();
+    let radius = spacing * POISSON_PACKING;
+    let cell = radius / std::f64::consts::SQRT_2; // holds at most one point
+    let (columns, rows) = (
+        (x_end / cell).ceil() as usize,
+        (y_end / cell).ceil() as usize,
+    );
+    let mut grid: Vec<Option<usize>> = vec![None; columns * rows];
+    let cell_of = |p: Vector2| {
+        let column = ((p.x / cell) as usize).min(columns - 1);
+        let row = ((p.y / cell) as usize).min(rows - 1);
+        (column, row)
+    };
+
+    let first = Vector2 {
+        x: rng.gen_range(0.0..x_end),
+        y: rng.gen_range(0.0..y_end),
+    };
+    let mut points = vec![first];
+    let (column, row) = cell_of(first);
+    grid[row * columns + column] = Some(0);
+    let mut active = vec![0];
+
+    while !active.is_empty() && points.len() < count {
+        let index = rng.gen_range(0..active.len());
+        let around = points[active[index]];
+
+        let candidate = (0..POISSON_ATTEMPTS).find_map(|_| {
+            let angle = rng.gen_range(0.0..TAU);
+            let distance = rng.gen_range(radius..radius * 2.0);
+            let p = Vector2 {
+                x: around.x + distance * angle.cos(),
+                y: around.y + distance * angle.sin(),
+            };
+            if !(0.0..x_end).contains(&p.x) || !(0.0..y_end).contains(&p.y) {
+                return None;
+            }
+
+            let (column, row) = cell_of(p);
+            let near = (row.saturating_sub(2)..(row + 3).min(rows)).flat_map(|row| {
+                (column.saturating_sub(2)..(column + 3).min(columns)).map(move |c| (c, row))
+            });
+            let clear = near
+                .filter_map(|(c, row)| grid[row * columns + c])
+                .all(|other| (points[other].x - p.x).hypot(points[other].y - p.y) >= radius);
+            clear.then_some(p)
+        });
+
+        match candidate {
+            Some(p) => {
+                let (column, row) = cell_of(p);
+                grid[row * columns + column] = Some(points.len());
+                active.push(points.len());
+                points.push(p);
+            }
+            None => {
+                active.swap_remove(index);
+            }
+        }
+    }
+
+    points
+}
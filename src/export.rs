@@ -0,0 +1,51 @@
+use crate::config::Config;
+use crate::web::{self, SystemTime};
+use crate::{animation, bench, field_file, headless, plot, search};
+use std::path::PathBuf;
+
+const CAPTURE_DIRECTORY: &str = "captures";
+
+// Outputs of the flow field produced without opening a window: a benchmark, a seed search, the
+// sampled field, plotter paths, offscreen renders and animations. Returns whether there were any.
+pub fn export(config: &Config) -> bool {
+    if !web::FILES {
+        return false;
+    }
+
+    if config.bench {
+        bench::bench(config);
+        return true;
+    }
+    if let Some(count) = config.search {
+        if let Err(err) = search::search(config, count, search::score) {
+            log::warn!("{}", err);
+        }
+        return true;
+    }
+
+    if let Some(path) = &config.export_field {
+        field_file::export(config, path);
+    }
+    if config.svg.is_some() || config.gcode.is_some() {
+        plot::export(config);
+    }
+    if let Some(path) = &config.render {
+        headless::render(config, path);
+    }
+    if let Some(path) = &config.animation {
+        animation::export(config, path);
+    }
+    config.export_field.is_some()
+        || config.svg.is_some()
+        || config.gcode.is_some()
+        || config.render.is_some()
+        || config.animation.is_some()
+}
+
+// Named after the seed to reproduce it, timestamped so repeated captures never overwrite each other
+pub fn capture_path(seed: u64) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    PathBuf::from(CAPTURE_DIRECTORY).join(format!("{}_{}.png", seed, timestamp.as_millis()))
+}
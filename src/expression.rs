@@ -0,0 +1,312 @@
+use crate::config::Config;
+use crate::field::VectorField;
+use crate::Vector2;
+
+// Variables an expression can refer to
+#[derive(Copy, Clone)]
+enum Variable {
+    X,     // horizontal position, -1 to 1 across the shorter side of the canvas, 0 at the center
+    Y,     // vertical position, pointing up
+    R,     // distance from the center
+    Theta, // angle around the center, in radians
+    T,     // time, advancing by `evolution_speed` per step
+}
+
+#[derive(Copy, Clone)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Sinh,
+    Cosh,
+    Tanh,
+    Sqrt,
+    Abs,
+    Exp,
+    Ln,
+    Floor,
+    Ceil,
+    Min,
+    Max,
+    Pow,
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<(Function, usize)> {
+        Some(match name {
+            "sin" => (Function::Sin, 1),
+            "cos" => (Function::Cos, 1),
+            "tan" => (Function::Tan, 1),
+            "asin" => (Function::Asin, 1),
+            "acos" => (Function::Acos, 1),
+            "atan" => (Function::Atan, 1),
+            "atan2" => (Function::Atan2, 2),
+            "sinh" => (Function::Sinh, 1),
+            "cosh" => (Function::Cosh, 1),
+            "tanh" => (Function::Tanh, 1),
+            "sqrt" => (Function::Sqrt, 1),
+            "abs" => (Function::Abs, 1),
+            "exp" => (Function::Exp, 1),
+            "ln" => (Function::Ln, 1),
+            "floor" => (Function::Floor, 1),
+            "ceil" => (Function::Ceil, 1),
+            "min" => (Function::Min, 2),
+            "max" => (Function::Max, 2),
+            "pow" => (Function::Pow, 2),
+            _ => return None,
+        })
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Function::Sin => args[0].sin(),
+            Function::Cos => args[0].cos(),
+            Function::Tan => args[0].tan(),
+            Function::Asin => args[0].asin(),
+            Function::Acos => args[0].acos(),
+            Function::Atan => args[0].atan(),
+            Function::Atan2 => args[0].atan2(args[1]),
+            Function::Sinh => args[0].sinh(),
+            Function::Cosh => args[0].cosh(),
+            Function::Tanh => args[0].tanh(),
+            Function::Sqrt => args[0].sqrt(),
+            Function::Abs => args[0].abs(),
+            Function::Exp => args[0].exp(),
+            Function::Ln => args[0].ln(),
+            Function::Floor => args[0].floor(),
+            Function::Ceil => args[0].ceil(),
+            Function::Min => args[0].min(args[1]),
+            Function::Max => args[0].max(args[1]),
+            Function::Pow => args[0].powf(args[1]),
+        }
+    }
+}
+
+enum Expr {
+    Number(f64),
+    Variable(Variable),
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>), // one of `+-*/%^`
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, vars: &[f64; 5]) -> f64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Variable(variable) => vars[*variable as usize],
+            Expr::Negate(expr) => -expr.eval(vars),
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(vars), rhs.eval(vars));
+                match op {
+                    '+' => lhs + rhs,
+                    '-' => lhs - rhs,
+                    '*' => lhs * rhs,
+                    '/' => lhs / rhs,
+                    '%' => lhs.rem_euclid(rhs),
+                    _ => lhs.powf(rhs),
+                }
+            }
+            Expr::Call(function, args) => {
+                let args: Vec<f64> = args.iter().map(|arg| arg.eval(vars)).collect();
+                function.apply(&args)
+            }
+        }
+    }
+}
+
+// Recursive descent parser over the characters of an expression, ignoring whitespace
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.chars.next();
+                Ok(())
+            }
+            Some(c) => Err(format!("expected `{}`, found `{}`", expected, c)),
+            None => Err(format!("expected `{}`, found end of expression", expected)),
+        }
+    }
+
+    // sum = product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.product()?;
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            self.chars.next();
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    // product = unary (('*' | '/' | '%') unary)*
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while let Some(op @ ('*' | '/' | '%')) = self.peek() {
+            self.chars.next();
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+
+    // unary = '-' unary | power
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some('-') {
+            self.chars.next();
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    // power = atom ('^' unary)?, right associative
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.peek() == Some('^') {
+            self.chars.next();
+            return Ok(Expr::Binary('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    // atom = number | variable | function '(' sum (',' sum)* ')' | '(' sum ')'
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some('(') => {
+                self.chars.next();
+                let expr = self.sum()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                }
+                number
+                    .parse()
+                    .map(Expr::Number)
+                    .map_err(|_| format!("invalid number `{}`", number))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut name = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphanumeric()) {
+                    name.push(c);
+                }
+                self.identifier(&name)
+            }
+            Some(c) => Err(format!("unexpected `{}`", c)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn identifier(&mut self, name: &str) -> Result<Expr, String> {
+        let variable = match name {
+            "x" => Variable::X,
+            "y" => Variable::Y,
+            "r" => Variable::R,
+            "theta" => Variable::Theta,
+            "t" => Variable::T,
+            "pi" => return Ok(Expr::Number(std::f64::consts::PI)),
+            "e" => return Ok(Expr::Number(std::f64::consts::E)),
+            _ => {
+                let (function, arity) =
+                    Function::parse(name).ok_or_else(|| format!("unknown name `{}`", name))?;
+
+                self.expect('(')?;
+                let mut args = vec![self.sum()?];
+                while self.peek() == Some(',') {
+                    self.chars.next();
+                    args.push(self.sum()?);
+                }
+                self.expect(')')?;
+
+                if args.len() != arity {
+                    return Err(format!(
+                        "`{}` takes {} arguments, got {}",
+                        name,
+                        arity,
+                        args.len()
+                    ));
+                }
+                return Ok(Expr::Call(function, args));
+            }
+        };
+
+        Ok(Expr::Variable(variable))
+    }
+}
+
+// Position on the canvas centered and scaled so the shorter side of the canvas spans -1 to 1, as
+// expressions and scripts see it
+pub fn centered(pos: Vector2, extent: Vector2) -> [f64; 2] {
+    let scale = extent.x.min(extent.y);
+    [
+        (pos.x - extent.x / 2.0) * 2.0 / scale,
+        (pos.y - extent.y / 2.0) * 2.0 / scale,
+    ]
+}
+
+// Field defined by an expression, either a vector `vx, vy` or an angle `angle = ...` in radians
+// counterclockwise from +x, over the variables `x`, `y`, `r`, `theta` and `t`
+pub fn field(source: &str) -> Result<Box<dyn VectorField>, String> {
+    let error = |err| format!("Invalid expression `{}`: {}", source, err);
+
+    let trimmed = source.trim_start();
+    let angle = trimmed
+        .strip_prefix("angle")
+        .and_then(|rest| rest.trim_start().strip_prefix('='));
+    let mut parser = Parser {
+        chars: angle.unwrap_or(trimmed).chars().peekable(),
+    };
+
+    let first = parser.sum().map_err(error)?;
+    let field = match (angle, parser.peek()) {
+        (Some(_), _) => ExpressionField::Angle(first),
+        (None, Some(',')) => {
+            parser.chars.next();
+            ExpressionField::Vector(first, parser.sum().map_err(error)?)
+        }
+        (None, _) => return Err(error("expected `vx, vy` or `angle = ...`".to_string())),
+    };
+
+    match parser.peek() {
+        Some(c) => Err(error(format!("unexpected `{}`", c))),
+        None => Ok(Box::new(field)),
+    }
+}
+
+enum ExpressionField {
+    Vector(Expr, Expr),
+    Angle(Expr),
+}
+
+impl VectorField for ExpressionField {
+    fn vector(&self, config: &Config, pos: Vector2, extent: Vector2, steps: u64) -> Vector2 {
+        let [x, y] = centered(pos, extent);
+        let time = steps as f64 * config.evolution_speed;
+        let vars = [x, y, x.hypot(y), y.atan2(x), time];
+
+        match self {
+            ExpressionField::Vector(x, y) => Vector2 {
+                x: x.eval(&vars),
+                y: y.eval(&vars),
+            },
+            ExpressionField::Angle(angle) => {
+                let (sin, cos) = angle.eval(&vars).sin_cos();
+                Vector2 { x: cos, y: sin }
+            }
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use crate::config::Config;
+use crate::field_file::FieldFile;
+use crate::fluid::{Fluid, FluidField};
+use crate::guide::{GuidedField, Outline};
+use crate::noise::{Noise, NoiseConfig};
+use crate::radial::RadialField;
+use crate::reaction::{Reaction, ReactionField};
+use crate::script::{Script, ScriptField};
+use crate::stack::FieldStack;
+use crate::Vector2;
+use nannou::image::{self, GrayImage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+const GRADIENT_EPSILON: f64 = 1e-5; // step of the finite differences gradients are computed with
+const WARP_OFFSET: (f64, f64) = (5.2, 1.3); // between the noise displacing x and y, decorrelating them
+
+// How the field source is turned into a flow direction
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FieldKind {
+    Angle, // the source value is the heading angle
+    Curl,  // the flow follows the curl of the source as a potential, divergence-free and swirling
+}
+
+// Scalar field the heading is derived from, sampled at normalized positions (0 to 1 across the
+// canvas, y pointing up) and at a time
+pub trait FieldSource: Send + Sync {
+    fn potential(&self, config: &Config, u: f64, v: f64, time: f64) -> f64;
+
+    // Partial derivatives of the potential along u and v
+    fn gradient(&self, config: &Config, u: f64, v: f64, time: f64) -> (f64, f64) {
+        let e = GRADIENT_EPSILON;
+        (
+            (self.potential(config, u + e, v, time) - self.potential(config, u - e, v, time))
+                / (2.0 * e),
+            (self.potential(config, u, v + e, time) - self.potential(config, u, v - e, time))
+                / (2.0 * e),
+        )
+    }
+}
+
+// Vector field particles flow along, sampled at positions from 0 to `extent` (y pointing up) after
+// a number of steps. Only the direction of the vector matters.
+pub trait VectorField: Send + Sync {
+    fn vector(&self, config: &Config, pos: Vector2, extent: Vector2, steps: u64) -> Vector2;
+}
+
+// Field for `config`: the script's heading, expression, radial primitive or field file if one is
+// given, else the image or noise turned into a heading according to `field_kind`, then blended with
+// the field layers, the reaction and the fluid and bent along the guide
+pub fn build(
+    config: &Config,
+    noise_seed: u32,
+    reaction: Option<Arc<Reaction>>,
+    fluid: Option<Arc<Fluid>>,
+    script: Option<Arc<Script>>,
+) -> Box<dyn VectorField> {
+    let script = script.filter(|script| script.has_heading());
+    let field: Box<dyn VectorField> = match (
+        script,
+        &config.expression,
+        config.radial,
+        &config.field_file,
+    ) {
+        (Some(script), _, _, _) => Box::new(ScriptField(script)),
+        (None, Some(expression), _, _) => {
+            crate::expression::field(expression).unwrap_or_else(|err| panic!("{}", err))
+        }
+        (None, None, Some(radial), _) => Box::new(RadialField {
+            radial,
+            center: [config.radial_center[0], config.radial_center[1]],
+        }),
+        (None, None, None, Some(path)) => {
+            Box::new(FieldFile::load(path).unwrap_or_else(|err| panic!("{}", err)))
+        }
+        (None, None, None, None) => {
+            let source: Box<dyn FieldSource> = match &config.image {
+                Some(path) => Box::new(ImageSource::load(path)),
+                None => Box::new(NoiseSource(NoiseConfig::heading(config).build(noise_seed))),
+            };
+            Box::new(SourceField(source))
+        }
+    };
+
+    // combined with the `[[fields]]` layers, if there are any
+    let field = match config.fields.is_empty() {
+        true => field,
+        false => Box::new(FieldStack::new(config, field, noise_seed)),
+    };
+
+    // and with the gradient of the reaction, if it runs
+    let field = match reaction {
+        Some(reaction) => Box::new(ReactionField { field, reaction }),
+        None => field,
+    };
+
+    // and with the velocity of the fluid, if it runs
+    let field = match fluid {
+        Some(fluid) => Box::new(FluidField { field, fluid }),
+        None => field,
+    };
+
+    // bent towards the guide outline, if there is one
+    let outline = match (&config.guide_text, &config.guide_path) {
+        (Some(text), _) => Outline::text(text),
+        (None, Some(path)) => Outline::svg_path(path).unwrap_or_else(|err| panic!("{}", err)),
+        (None, None) => return field,
+    };
+    Box::new(GuidedField { field, outline })
+}
+
+// Scalar source turned into a vector field, by its value as an angle or by its curl
+pub struct SourceField(pub Box<dyn FieldSource>);
+
+impl VectorField for SourceField {
+    fn vector(&self, config: &Config, pos: Vector2, extent: Vector2, steps: u64) -> Vector2 {
+        let (u, v) = (pos.x / extent.x, pos.y / extent.y);
+        let time = steps as f64 * config.evolution_speed;
+        source_vector(&*self.0, config, config.field_kind, [u, v], extent, time)
+    }
+}
+
+// Vector of `source` at a normalized position, turned into a heading the way `kind` says
+pub fn source_vector(
+    source: &dyn FieldSource,
+    config: &Config,
+    kind: FieldKind,
+    [u, v]: [f64; 2],
+    extent: Vector2,
+    time: f64,
+) -> Vector2 {
+    match kind {
+        FieldKind::Angle => {
+            let heading = source.potential(config, u, v, time)
+                * config.heading_noise_multiplier
+                * 2.0
+                * std::f64::consts::PI;
+
+            // measured like `step()` does
+            Vector2 {
+                x: heading.sin(),
+                y: heading.cos(),
+            }
+        }
+        FieldKind::Curl => {
+            // Gradient of the potential, scaled from normalized positions back to positions
+            let (gradient_u, gradient_v) = source.gradient(config, u, v, time);
+            let (gradient_x, gradient_y) = (gradient_u / extent.x, gradient_v / extent.y);
+
+            // The curl is the gradient turned a quarter turn, running along the contour lines
+            // of the potential
+            Vector2 {
+                x: gradient_y,
+                y: -gradient_x,
+            }
+        }
+    }
+}
+
+// Heading noise, moving through time as a third dimension if the field evolves, or around a
+// circle through a third and fourth one if it loops
+pub struct NoiseSource(pub Box<dyn Noise>);
+
+impl FieldSource for NoiseSource {
+    fn potential(&self, config: &Config, u: f64, v: f64, time: f64) -> f64 {
+        let speed = config.evolution_speed;
+        let sample = |x: f64, y: f64| match config.loop_period {
+            _ if speed == 0.0 => self.0.sample([x, y]),
+            Some(period) if period > 0 => {
+                // as long around as time moves over a period, so the field evolves as fast
+                let radius = period as f64 * speed / std::f64::consts::TAU;
+                let angle = time / radius;
+                self.0
+                    .sample_4d([x, y, radius * angle.cos(), radius * angle.sin()])
+            }
+            _ => self.0.sample_3d([x, y, time]),
+        };
+
+        let (mut x, mut y) = (
+            u * config.heading_noise_factor,
+            v * config.heading_noise_factor,
+        );
+
+        // Domain warping: displace the coordinates by the noise itself, once per strength, so
+        // the field folds into marbled swirls
+        for &strength in &config.domain_warp {
+            let dx = sample(x, y);
+            let dy = sample(x + WARP_OFFSET.0, y + WARP_OFFSET.1);
+            x += dx * strength;
+            y += dy * strength;
+        }
+
+        sample(x, y)
+    }
+}
+
+// Luminance of an image stretched over the canvas, with its Sobel gradient precomputed so curl
+// fields trace the edges of the image
+pub struct ImageSource {
+    width: usize,
+    height: usize,
+    luminance: Vec<f64>,       // 0 to 1, row-major from the top left
+    gradient: Vec<(f64, f64)>, // per pixel, along u and v
+}
+
+impl ImageSource {
+    pub fn load(path: &Path) -> Self {
+        let image = image::open(path)
+            .unwrap_or_else(|err| panic!("Could not load image {}: {}", path.display(), err));
+        ImageSource::new(&image.to_luma8())
+    }
+
+    pub fn new(image: &GrayImage) -> Self {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let luminance: Vec<f64> = image
+            .pixels()
+            .map(|pixel| pixel[0] as f64 / 255.0)
+            .collect();
+
+        let at = |x: isize, y: isize| {
+            let x = x.clamp(0, width as isize - 1) as usize;
+            let y = y.clamp(0, height as isize - 1) as usize;
+            luminance[y * width + x]
+        };
+
+        let mut gradient = Vec::with_capacity(width * height);
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                // Sobel, normalized to the change per pixel
+                let sobel_x = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                    - at(x - 1, y - 1)
+                    - 2.0 * at(x - 1, y)
+                    - at(x - 1, y + 1))
+                    / 8.0;
+                let sobel_y = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                    - at(x - 1, y - 1)
+                    - 2.0 * at(x, y - 1)
+                    - at(x + 1, y - 1))
+                    / 8.0;
+
+                // rows go down while v goes up
+                gradient.push((sobel_x * width as f64, -sobel_y * height as f64));
+            }
+        }
+
+        ImageSource {
+            width,
+            height,
+            luminance,
+            gradient,
+        }
+    }
+
+    // Bilinear sample of per pixel values at a normalized position
+    fn sample<T, F>(&self, values: &[T], u: f64, v: f64, lerp: F) -> T
+    where
+        T: Copy,
+        F: Fn(T, T, f64) -> T,
+    {
+        let x = (u * self.width as f64 - 0.5).clamp(0.0, (self.width - 1) as f64);
+        let y = ((1.0 - v) * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (x - x0 as f64, y - y0 as f64);
+
+        let at = |x: usize, y: usize| values[y * self.width + x];
+        lerp(
+            lerp(at(x0, y0), at(x1, y0), tx),
+            lerp(at(x0, y1), at(x1, y1), tx),
+            ty,
+        )
+    }
+}
+
+impl FieldSource for ImageSource {
+    fn potential(&self, _config: &Config, u: f64, v: f64, _time: f64) -> f64 {
+        self.sample(&self.luminance, u, v, |a, b, t| a + (b - a) * t)
+    }
+
+    fn gradient(&self, _config: &Config, u: f64, v: f64, _time: f64) -> (f64, f64) {
+        self.sample(&self.gradient, u, v, |a, b, t| {
+            (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+        })
+    }
+}
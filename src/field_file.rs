@@ -0,0 +1,244 @@
+use crate::config::Config;
+use crate::field::VectorField;
+use crate::particles::Simulation;
+use crate::Vector2;
+use std::path::Path;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+// Field vectors on a lattice spanning the canvas, corners included, saved to or loaded from a
+// `.csv` or `.npy` file so fields can be made elsewhere or reused across sketches. Rows run from
+// the top of the canvas down like image rows, and positive y points up.
+//
+// CSV files hold a row of the lattice per line as `x,y` pairs one after the other, like
+// `numpy.savetxt` writes an array of shape (rows, columns * 2). NPY files hold an array of shape
+// (rows, columns, 2) of little-endian floats.
+pub struct FieldFile {
+    columns: usize,
+    rows: usize,
+    vectors: Vec<Vector2>, // row-major from the top left
+}
+
+impl FieldFile {
+    // Lattice of `field_file_resolution` cells along the longer side of the simulation, sampling
+    // its field as it is before the first step
+    pub fn sample(config: &Config, simulation: &Simulation) -> Self {
+        let (x_end, y_end) = (simulation.x_end, simulation.y_end);
+        let resolution = config.field_file_resolution.max(1) as f64;
+        let cells = |side: f64| ((resolution * side / x_end.max(y_end)).round() as usize).max(1);
+        let (columns, rows) = (cells(x_end) + 1, cells(y_end) + 1);
+
+        let vectors = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let pos = Vector2 {
+                    x: x_end * column as f64 / (columns - 1) as f64,
+                    y: y_end * (1.0 - row as f64 / (rows - 1) as f64),
+                };
+                simulation.field_vector(config, pos)
+            })
+            .collect();
+
+        FieldFile {
+            columns,
+            rows,
+            vectors,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let invalid = |err: String| format!("Invalid field file {}: {}", path.display(), err);
+        let bytes = std::fs::read(path)
+            .map_err(|err| format!("Could not read field file {}: {}", path.display(), err))?;
+
+        let file = match extension(path) {
+            "npy" => FieldFile::from_npy(&bytes),
+            _ => FieldFile::from_csv(&String::from_utf8_lossy(&bytes)),
+        }
+        .map_err(invalid)?;
+
+        match file.columns >= 2 && file.rows >= 2 {
+            true => Ok(file),
+            false => Err(invalid("needs at least 2 by 2 vectors".to_string())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).unwrap();
+        }
+        let bytes = match extension(path) {
+            "npy" => self.to_npy(),
+            _ => self.to_csv().into_bytes(),
+        };
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    fn from_csv(text: &str) -> Result<Self, String> {
+        let lines = text.lines().filter(|line| !line.trim().is_empty());
+        let rows: Vec<Vec<f64>> = lines
+            .map(|line| {
+                line.split(',')
+                    .map(|value| value.trim().parse::<f64>().map_err(|err| err.to_string()))
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let width = rows.first().map_or(0, Vec::len);
+        if !width.is_multiple_of(2) || rows.iter().any(|row| row.len() != width) {
+            return Err("every line needs the same number of x,y pairs".to_string());
+        }
+
+        Ok(FieldFile {
+            columns: width / 2,
+            rows: rows.len(),
+            vectors: pairs(rows.into_iter().flatten()),
+        })
+    }
+
+    fn to_csv(&self) -> String {
+        let lines = self.vectors.chunks(self.columns).map(|row| {
+            let values: Vec<String> = row
+                .iter()
+                .map(|vector| format!("{},{}", vector.x, vector.y))
+                .collect();
+            values.join(",") + "\n"
+        });
+        lines.collect()
+    }
+
+    // The header is a Python dict literal padded with spaces, only as much of it parsed as needed
+    fn from_npy(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(NPY_MAGIC) || bytes.len() < 10 {
+            return Err("not an NPY file".to_string());
+        }
+        let (length, start) = match bytes[6] {
+            1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+            _ if bytes.len() >= 12 => (
+                u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize,
+                12,
+            ),
+            _ => return Err("truncated header".to_string()),
+        };
+        let header = bytes.get(start..start + length).ok_or("truncated header")?;
+        let header = String::from_utf8_lossy(header);
+        let data = &bytes[start + length..];
+
+        if header.contains("'fortran_order': True") {
+            return Err("the array needs to be in C order".to_string());
+        }
+        let size = if header.contains("'<f8'") {
+            8
+        } else if header.contains("'<f4'") {
+            4
+        } else {
+            return Err("the array needs to hold little-endian floats".to_string());
+        };
+        let shape: Vec<usize> = header
+            .split("'shape':")
+            .nth(1)
+            .and_then(|rest| rest.split(['(', ')']).nth(1))
+            .ok_or("no shape")?
+            .split(',')
+            .filter(|dimension| !dimension.trim().is_empty())
+            .map(|dimension| dimension.trim().parse().map_err(|_| "invalid shape"))
+            .collect::<Result<_, _>>()?;
+        let (rows, columns) = match shape[..] {
+            [rows, columns, 2] => (rows, columns),
+            _ => return Err("the array needs a shape of (rows, columns, 2)".to_string()),
+        };
+        if data.len() < rows * columns * 2 * size {
+            return Err("truncated data".to_string());
+        }
+
+        let values = data
+            .chunks_exact(size)
+            .take(rows * columns * 2)
+            .map(|value| match size {
+                8 => f64::from_le_bytes(value.try_into().unwrap()),
+                _ => f32::from_le_bytes(value.try_into().unwrap()) as f64,
+            });
+        Ok(FieldFile {
+            columns,
+            rows,
+            vectors: pairs(values),
+        })
+    }
+
+    // Version 1 header padded so the data starts on a multiple of 64 bytes
+    fn to_npy(&self) -> Vec<u8> {
+        let mut header = format!(
+            "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, {}, 2), }}",
+            self.rows, self.columns
+        );
+        let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+        header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+        header.push('\n');
+
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.bytes());
+        for vector in &self.vectors {
+            bytes.extend(vector.x.to_le_bytes());
+            bytes.extend(vector.y.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+// Stretched over the canvas and interpolated bilinearly, clamped to the lattice outside of it
+impl VectorField for FieldFile {
+    fn vector(&self, _config: &Config, pos: Vector2, extent: Vector2, _steps: u64) -> Vector2 {
+        let u = (pos.x / extent.x).clamp(0.0, 1.0) * (self.columns - 1) as f64;
+        let v = (1.0 - pos.y / extent.y).clamp(0.0, 1.0) * (self.rows - 1) as f64;
+        let (column, row) = (
+            (u as usize).min(self.columns - 2),
+            (v as usize).min(self.rows - 2),
+        );
+        let (tx, ty) = (u - column as f64, v - row as f64);
+
+        let at = |column: usize, row: usize| self.vectors[row * self.columns + column];
+        let lerp = |a: Vector2, b: Vector2, t: f64| a * (1.0 - t) + b * t;
+
+        lerp(
+            lerp(at(column, row), at(column + 1, row), tx),
+            lerp(at(column, row + 1), at(column + 1, row + 1), tx),
+            ty,
+        )
+    }
+}
+
+// Save the field of a freshly seeded simulation the size of `--render`
+pub fn export(config: &Config, path: &Path) {
+    let seed = crate::seed::resolve(config);
+    let path = crate::seed::expand(path, seed);
+    let (width, height) = (config.render_width as f64, config.render_height as f64);
+    let simulation = Simulation::new(config, seed, width / 2.0, height / 2.0);
+
+    let file = FieldFile::sample(config, &simulation);
+    file.save(&path);
+    log::info!(
+        "Saved {} ({} by {} vectors)",
+        path.display(),
+        file.columns,
+        file.rows
+    );
+}
+
+fn extension(path: &Path) -> &str {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+}
+
+fn pairs(values: impl Iterator<Item = f64>) -> Vec<Vector2> {
+    let values: Vec<f64> = values.collect();
+    values
+        .chunks_exact(2)
+        .map(|pair| Vector2 {
+            x: pair[0],
+            y: pair[1],
+        })
+        .collect()
+}
@@ -0,0 +1,92 @@
+use crate::config::Config;
+use crate::particles::Simulation;
+use crate::Vector2;
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const ARROW_LENGTH: f64 = 0.8; // of the spacing, for the strongest vector
+const STREAMLINE_STEPS: usize = 12; // segments each streamline is traced over
+const LINE_WEIGHT: f32 = 1.0;
+
+// How the field is drawn over the canvas while debugging it
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FieldView {
+    Arrows,      // an arrow per lattice point, as long as the vector is strong
+    Streamlines, // a short line traced along the field from every lattice point
+}
+
+// Draw the field of `simulation` on a lattice `field_view_spacing` points apart, in canvas
+// coordinates. Vectors are scaled and colored through the palette by their magnitude relative to
+// the strongest one, as only their direction moves particles.
+pub fn draw(draw: &Draw, config: &Config, simulation: &Simulation) {
+    let spacing = config.field_view_spacing.max(1.0);
+    let (x_end, y_end) = (simulation.x_end, simulation.y_end);
+    let (columns, rows) = (
+        (x_end * 2.0 / spacing) as usize,
+        (y_end * 2.0 / spacing) as usize,
+    );
+
+    // lattice points centered in their cells, in simulation positions
+    let points: Vec<Vector2> = (0..columns * rows)
+        .map(|i| Vector2 {
+            x: ((i % columns) as f64 + 0.5) * spacing / 2.0,
+            y: ((i / columns) as f64 + 0.5) * spacing / 2.0,
+        })
+        .collect();
+    let vectors: Vec<Vector2> = points
+        .iter()
+        .map(|&pos| simulation.field_vector(config, pos))
+        .collect();
+
+    let strongest = vectors.iter().map(Vector2::length).fold(0.0, f64::max);
+    if strongest == 0.0 || !strongest.is_finite() {
+        return;
+    }
+
+    let window = |pos: Vector2| simulation.canvas_point(pos);
+
+    for (&pos, vector) in points.iter().zip(&vectors) {
+        let magnitude = vector.length() / strongest;
+        let rgb = crate::palette::color(config, magnitude);
+        let color = lin_srgba(rgb.red, rgb.green, rgb.blue, 1.0);
+
+        match config.field_view {
+            FieldView::Arrows => {
+                if magnitude == 0.0 {
+                    continue;
+                }
+
+                // centered on the lattice point, halving like the positions
+                let offset = *vector / vector.length() * (magnitude * spacing * ARROW_LENGTH / 4.0);
+                draw.arrow()
+                    .start(window(pos + offset * -1.0))
+                    .end(window(pos + offset))
+                    .weight(LINE_WEIGHT)
+                    .head_length(spacing as f32 * 0.15)
+                    .head_width(spacing as f32 * 0.1)
+                    .color(color);
+            }
+            FieldView::Streamlines => {
+                // normalized steps of equal length, so the line spans about one cell
+                let step = spacing / 2.0 / STREAMLINE_STEPS as f64;
+                let mut line = vec![window(pos)];
+                let mut pos = pos;
+                for _ in 0..STREAMLINE_STEPS {
+                    let vector = simulation.field_vector(config, pos);
+                    let length = vector.length();
+                    if length == 0.0 || !length.is_finite() {
+                        break;
+                    }
+                    pos += vector / length * step;
+                    line.push(window(pos));
+                }
+
+                draw.polyline()
+                    .weight(LINE_WEIGHT)
+                    .points(line)
+                    .color(color);
+            }
+        }
+    }
+}
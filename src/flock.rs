@@ -0,0 +1,75 @@
+use crate::config::Config;
+use crate::Vector2;
+use std::collections::HashMap;
+
+// Positions and velocities of every flow at the start of a step, bucketed into square cells
+// `flock_radius` wide so each flow only looks at the nine cells around it for neighbors
+pub struct Flock {
+    radius: f64,
+    states: Vec<(Vector2, Vector2)>,
+    cells: HashMap<(i64, i64), Vec<usize>>, // indices into `states`
+}
+
+impl Flock {
+    // Flocking is off unless one of the weights is set
+    pub fn enabled(config: &Config) -> bool {
+        config.flock_radius > 0.0
+            && (config.separation != 0.0 || config.alignment != 0.0 || config.cohesion != 0.0)
+    }
+
+    pub fn new(config: &Config, states: Vec<(Vector2, Vector2)>) -> Self {
+        let radius = config.flock_radius;
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (i, (pos, _)) in states.iter().enumerate() {
+            cells.entry(cell(*pos, radius)).or_default().push(i);
+        }
+        Flock {
+            radius,
+            states,
+            cells,
+        }
+    }
+
+    // Steering of flow `index` away from crowding neighbors, along their average heading and
+    // towards their center, each weighted by its config weight and fading out with distance
+    pub fn steering(&self, config: &Config, index: usize) -> Vector2 {
+        let (pos, vel) = self.states[index];
+        let (cx, cy) = cell(pos, self.radius);
+
+        let mut separation = Vector2 { x: 0.0, y: 0.0 };
+        let mut heading = Vector2 { x: 0.0, y: 0.0 };
+        let mut center = Vector2 { x: 0.0, y: 0.0 };
+        let mut count = 0;
+        for neighbor in (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dy| (cx + dx, cy + dy)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+        {
+            let (other, other_vel) = self.states[*neighbor];
+            let offset = pos - other;
+            let distance = offset.length();
+            if *neighbor == index || distance >= self.radius {
+                continue;
+            }
+
+            if distance > 0.0 {
+                separation += offset / distance * (1.0 - distance / self.radius);
+            }
+            heading += other_vel;
+            center += other;
+            count += 1;
+        }
+        if count == 0 {
+            return Vector2 { x: 0.0, y: 0.0 };
+        }
+
+        let count = count as f64;
+        let alignment = heading / count + vel * -1.0;
+        let cohesion = (center / count + pos * -1.0) / self.radius;
+        separation * config.separation + alignment * config.alignment + cohesion * config.cohesion
+    }
+}
+
+fn cell(pos: Vector2, size: f64) -> (i64, i64) {
+    ((pos.x / size).floor() as i64, (pos.y / size).floor() as i64)
+}
@@ -0,0 +1,1088 @@
+use crate::accumulation::AccumulationBuffer;
+use crate::audio::Audio;
+use crate::background::Background;
+use crate::cache::FieldCache;
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::config::{Config, ConfigWatcher};
+use crate::gallery::Gallery;
+use crate::gpu::GpuSimulation;
+use crate::history::{History, Snapshot};
+use crate::layer::BlendMode;
+use crate::metadata::Metadata;
+use crate::midi::Midi;
+use crate::ndi::Ndi;
+use crate::osc::Osc;
+use crate::output::{self, Output};
+use crate::pacing::{self, Pacer};
+use crate::particles::{Attractor, NoiseParams, Simulation};
+use crate::render::{
+    accumulation, draw_layer, draw_particles, draw_simulation, loop_alpha, splat_accumulation,
+    splat_particles, upload_accumulation,
+};
+use crate::replay::{self, Command, FrameInput, Player, Recorder, Replay};
+use crate::script::ScriptWatcher;
+use crate::sketch::Sketch;
+use crate::species::Species;
+use crate::stats::Stats;
+use crate::stream::FrameStream;
+use crate::{
+    export, field_view, gui, headless, logging, randomize, seed, session, species, state, target,
+    timeline, wash,
+};
+use nannou::prelude::*;
+use nannou_egui::Egui;
+use std::sync::Arc;
+
+const MAX_STEPS_PER_FRAME: usize = 8; // fixed steps caught up on per frame, dropping the rest
+const SLOW_MOTION: f64 = 0.1; // speed set with 1
+const ZOOM_STEP: f32 = 1.1; // zoom factor per line scrolled
+const PIXELS_PER_LINE: f32 = 50.0; // of scrolling on touchpads, which scroll by pixels
+const NDI_FPS: f64 = 60.0; // frame rate advertised to NDI receivers without `max_fps`
+
+pub struct Model {
+    pub window: window::Id,
+    pub config: Config,
+    pub egui: Egui,
+    pub show_panel: bool,       // toggled with Tab
+    pub show_stats: bool,       // performance overlay, toggled with F1
+    pub show_field: bool,       // field debug view, toggled with F2
+    pub paused: bool,           // toggled with space
+    pub step_once: bool,        // advance one step while paused, requested with `.`
+    pub capture_next: bool,     // save the next frame, requested with S
+    pub capturing: bool,        // whether the frame being drawn is saved, hiding the panel
+    pub step_accumulator: f64,  // fraction of a step carried over to the next frame
+    pub fast_forwarding: bool,  // toggled with 3
+    pub recorded: u64,          // number of frames written in recording mode
+    pub time: f64,              // seconds into the timeline, only advancing while running
+    pub camera: Camera, // panned by dragging with the middle mouse button, zoomed by scrolling
+    pub mouse: Vec2,    // last position of the mouse, dragged from
+    pub canvas: Canvas, // persistent offscreen target the trails accumulate on
+    pub background: Background, // drawn onto the canvas whenever it is cleared
+    pub simulation: Simulation,
+    pub species: Vec<Species>, // extra species from the config file, advected alongside `simulation`
+    pub wash: FieldCache<NoiseParams, wgpu::Texture>, // rebuilt whenever the noise parameters change
+    pub reaction: FieldCache<u64, wgpu::Texture>,     // the reaction, rebuilt every step
+    pub accumulation: Option<(AccumulationBuffer, wgpu::Texture)>, // additive hue buffer and its texture
+    pub gpu: Option<GpuSimulation>, // advects the particles instead of `simulation` when enabled
+    pub history: History,           // recent steps, rewound through with the left arrow
+    pub watcher: Option<ConfigWatcher>, // reloads the config file when it changes
+    pub script_watcher: Option<ScriptWatcher>, // reloads the script when it changes
+    pub midi: Option<Midi>,         // sets parameters from a MIDI controller
+    pub audio: Option<Audio>,       // sets parameters from the spectrum of an audio input
+    pub osc: Option<Osc>,           // sets parameters from OSC messages
+    pub gallery: Option<Gallery>,   // cycles through the presets
+    pub recorder: Option<Recorder>, // writes the input of the session to a replay file
+    pub replay: Option<Replay>,     // plays back a recorded session instead of taking input
+    pub pacer: Pacer,               // holds frames to `max_fps`
+    pub outputs: Vec<(window::Id, Output)>, // extra windows showing parts of the canvas
+    pub stream: Option<FrameStream>, // writes the canvas out every frame
+    pub ndi: Option<Ndi>,           // sends the canvas as an NDI source every frame
+    pub device: Arc<wgpu::DeviceQueuePair>, // kept to read the canvas back once the window closed
+    pub stats: Stats,
+}
+
+impl Sketch for Model {
+    fn export(config: &Config) -> bool {
+        export::export(config)
+    }
+
+    fn model(app: &App) -> Self {
+        model(app)
+    }
+
+    fn update(app: &App, model: &mut Self, frame: Update) {
+        update(app, model, frame)
+    }
+
+    fn exit(app: &App, model: Self) {
+        exit(app, model)
+    }
+}
+
+fn model(app: &App) -> Model {
+    let config = Config::load();
+
+    // A replayed session starts like the recorded one did, in a window of the same size
+    let replay = config.replay_input.as_deref().map(Replay::load);
+    let config = match &replay {
+        Some(replay) => replay.config(&config),
+        None => config,
+    };
+    let config = match config.randomize {
+        true => randomize::randomize(&config),
+        false => config,
+    };
+
+    // A resumed piece continues with the parameters it was saved with
+    let resumed = config.resume.clone().map(|path| (state::load(&path), path));
+    let config = match &resumed {
+        Some((state, _)) => state.config.clone(),
+        None => config,
+    };
+    let mut builder = app.new_window();
+    if let Some(replay) = &replay {
+        builder = builder.size(replay.size[0] as u32, replay.size[1] as u32);
+    }
+    let window_id = builder
+        .surface_conf_builder(pacing::surface(&config))
+        .view(view)
+        .raw_event(raw_window_event)
+        .key_pressed(key_pressed)
+        .resized(resized)
+        .mouse_moved(mouse_moved)
+        .mouse_wheel(mouse_wheel)
+        .build()
+        .unwrap();
+    let window = app.window(window_id).unwrap();
+    pacing::apply(app, &config);
+
+    let x_end: f64 = app.window_rect().x.end as f64 * config.domain_scale;
+    let y_end: f64 = app.window_rect().y.end as f64 * config.domain_scale;
+
+    let seed = seed::resolve(&config);
+    let simulation = Simulation::new(&config, seed, x_end, y_end);
+
+    let mut model = Model {
+        window: window_id,
+        egui: Egui::from_window(&window),
+        show_panel: true,
+        show_stats: false,
+        show_field: false,
+        paused: false,
+        step_once: false,
+        capture_next: false,
+        capturing: false,
+        step_accumulator: 0.0,
+        fast_forwarding: false,
+        recorded: 0,
+        time: 0.0,
+        camera: Camera::new(),
+        mouse: Vec2::ZERO,
+        canvas: Canvas::new(
+            &window,
+            [(x_end * 2.0) as u32, (y_end * 2.0) as u32],
+            config.background.linear(),
+            target::samples(&config),
+        ),
+        background: Background::new(app, &config),
+        wash: FieldCache::new(),
+        reaction: FieldCache::new(),
+        accumulation: accumulation(window.device(), &config, x_end, y_end),
+        gpu: config.gpu.then(|| GpuSimulation::new(&window, &simulation)),
+        species: species::build(&window, &config, seed, x_end, y_end),
+        simulation,
+        history: History::new(config.history),
+        watcher: config.path().map(ConfigWatcher::new),
+        script_watcher: config.script.clone().map(ScriptWatcher::new),
+        midi: config.midi_port.as_deref().map(Midi::connect),
+        audio: config.audio_input.as_deref().map(Audio::connect),
+        osc: config.osc_port.map(Osc::listen),
+        gallery: config.gallery.is_some().then(|| Gallery::new(&config)),
+        recorder: config.record_input.as_deref().map(|path| {
+            let size = app.window_rect().wh().to_array();
+            Recorder::create(path, &config, seed, size)
+        }),
+        replay,
+        pacer: Pacer::new(),
+        device: window.device_queue_pair().clone(),
+        outputs: Vec::new(),
+        stream: config.stream.as_deref().map(|path| {
+            let size = [(x_end * 2.0) as u32, (y_end * 2.0) as u32];
+            FrameStream::open(path, size)
+        }),
+        // a missing NDI runtime only costs the source, the sketch keeps running without it
+        ndi: config.ndi.as_deref().and_then(|name| {
+            Ndi::open(name, config.max_fps.unwrap_or(NDI_FPS))
+                .map_err(|err| log::error!("{}", err))
+                .ok()
+        }),
+        stats: Stats::new(
+            config
+                .stats_csv
+                .as_ref()
+                .map(|path| seed::expand(path, seed))
+                .as_deref(),
+        ),
+        config,
+    };
+    let size = model.simulation.size();
+    model
+        .background
+        .draw(&model.canvas.draw, &model.config, size);
+
+    if let Some((state, path)) = resumed {
+        state::resume(app, &mut model, &state, &path);
+        log::info!(
+            "Resumed {} at step {}",
+            path.display(),
+            model.simulation.steps
+        );
+    }
+
+    // Opened last, as building a window needs the main one no longer borrowed
+    drop(window);
+    model.outputs = output::open(app, &model.config);
+
+    model
+}
+
+// Swap in a reloaded config, only rebuilding what the changed parameters require so the trails
+// survive tuning the field
+fn apply_config(app: &App, model: &mut Model, config: Config) {
+    let (x_end, y_end) = (model.simulation.x_end, model.simulation.y_end);
+    logging::init(&config);
+
+    let reseeded = model.config.reseeds(&config);
+    if config.script != model.config.script {
+        model.script_watcher = config.script.clone().map(ScriptWatcher::new);
+    }
+    replay::reconfigure(&mut model.simulation, &model.config, &config);
+
+    // Species inherit the main parameters, so they follow every change to them
+    if reseeded || config.species != model.config.species {
+        let window = app.window(model.window).unwrap();
+        let seed = model.simulation.seed;
+        model.species = species::build(&window, &config, seed, x_end, y_end);
+    } else {
+        for (species, overrides) in model.species.iter_mut().zip(&config.species) {
+            let species_config = overrides.apply(&config);
+            species
+                .simulation
+                .reconfigure(&species.config, &species_config);
+            species.config = species_config;
+        }
+    }
+
+    if config.additive_hue != model.config.additive_hue {
+        let window = app.window(model.window).unwrap();
+        model.accumulation = accumulation(window.device(), &config, x_end, y_end);
+    }
+    if reseeded || config.gpu != model.config.gpu {
+        let window = app.window(model.window).unwrap();
+        model.gpu = config
+            .gpu
+            .then(|| GpuSimulation::new(&window, &model.simulation));
+    }
+
+    // States of a different simulation can't be restored
+    if reseeded || config.species != model.config.species || config.history != model.config.history
+    {
+        model.history = History::new(config.history);
+    }
+
+    // The trails carry over onto canvases with the new sample count
+    let samples = target::samples(&config);
+    if samples != target::samples(&model.config) {
+        let window = app.window(model.window).unwrap();
+        let size = model.canvas.target.texture.size();
+        let background = config.background.linear();
+        model.canvas = model.canvas.resized(&window, size, background, samples);
+        for species in &mut model.species {
+            if let Some((blend, layer)) = &mut species.layer {
+                *layer = layer.resized(&window, size, blend.neutral(), samples);
+            }
+        }
+    }
+
+    let background = Background::changed(&model.config, &config);
+    if background {
+        model.background = Background::new(app, &config);
+    }
+    let domain = config.domain_scale != model.config.domain_scale;
+    if config.max_fps != model.config.max_fps {
+        pacing::apply(app, &config);
+    }
+    model.config = config;
+
+    if domain {
+        let size = app.window(model.window).unwrap().rect().wh();
+        resized(app, model, size);
+    }
+
+    // The canvas is never cleared, so a new background only shows once it is
+    if background {
+        clear(model);
+    }
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.pacer.wait(&model.config);
+
+    // Start the timer
+    use crate::web::Instant;
+    let now = Instant::now();
+
+    // A replayed frame stands in for the input of this one
+    let replayed = model.replay.as_mut().map(Replay::next_frame);
+    if let Some(None) = replayed {
+        log::info!("Replay finished");
+        model.replay = None;
+    }
+    let replayed = replayed.flatten();
+    if let Some(frame) = &replayed {
+        if let Some([x, y, zoom]) = frame.camera {
+            model.camera.pan = vec2(x, y);
+            model.camera.zoom = zoom;
+        }
+    }
+
+    if let Some(result) = model.watcher.as_mut().and_then(ConfigWatcher::poll) {
+        match result {
+            Ok(config) => {
+                log::info!("Reloaded config");
+                apply_config(app, model, config);
+            }
+            Err(err) => log::warn!("{}", err),
+        }
+    }
+    if model
+        .script_watcher
+        .as_mut()
+        .is_some_and(ScriptWatcher::poll)
+    {
+        model.simulation.reload_script(&model.config);
+        for species in &mut model.species {
+            species.simulation.reload_script(&species.config);
+        }
+    }
+
+    // Captures requested last frame have been written by now
+    let config = &model.config;
+    let recorded_all = config.record.is_some() && config.record_frames == Some(model.recorded);
+    if recorded_all || (model.capturing && config.capture == Some(model.simulation.steps)) {
+        app.quit();
+        return;
+    }
+
+    let capture_next = std::mem::take(&mut model.capture_next);
+    let path = match &config.record {
+        Some(directory) => {
+            model.recorded += 1;
+            let directory = seed::expand(directory, model.simulation.seed);
+            Some(directory.join(format!("frame_{:05}.png", model.recorded)))
+        }
+        None if capture_next || config.capture == Some(model.simulation.steps + 1) => {
+            let path = export::capture_path(model.simulation.seed);
+            log::info!("Saving {}", path.display());
+            Some(path)
+        }
+        None => None,
+    };
+
+    // Window captures can't embed the parameters, so the first frame of a recording and every
+    // screenshot get them next to it
+    model.capturing = path.is_some();
+    if let Some(path) = path {
+        if config.record.is_none() || model.recorded == 1 {
+            Metadata::new(config, model.simulation.seed).save_sidecar(&path);
+        }
+        app.window(model.window).unwrap().capture_frame(path);
+    }
+
+    model.egui.set_elapsed_time(update.since_start);
+    let mut edited = model.config.clone();
+    let particles = particle_count(model);
+    let actions = {
+        let ctx = model.egui.begin_frame();
+        if model.show_stats {
+            gui::hud(&ctx, &model.stats, particles);
+        }
+        match model.show_panel {
+            true => gui::panel(&ctx, &mut edited, model.simulation.seed),
+            false => gui::PanelActions::default(),
+        }
+    };
+
+    if let Some(midi) = &model.midi {
+        midi.apply(&mut edited);
+    }
+    if let Some(audio) = &mut model.audio {
+        audio.apply(&mut edited);
+    }
+    if let Some(osc) = &model.osc {
+        osc.apply(&mut edited);
+    }
+    if let Some(gallery) = &mut model.gallery {
+        gallery.apply(&mut edited, update.since_last.as_secs_f64());
+    }
+    timeline::apply(&mut edited, model.time);
+
+    // Replays discard the live input for the recorded one, which `Session` plays
+    let actions = match &replayed {
+        Some(_) => {
+            edited = model.config.clone();
+            gui::PanelActions::default()
+        }
+        None => actions,
+    };
+
+    if edited != model.config {
+        apply_config(app, model, edited);
+    }
+    if actions.reseed {
+        reseed(app, model);
+    }
+    if actions.reset {
+        command(app, model, Command::Reset);
+    }
+    if actions.randomize {
+        randomize(app, model);
+    }
+    if actions.save_preset {
+        save_preset(model);
+    }
+
+    let step_once = std::mem::take(&mut model.step_once);
+    let config = &model.config;
+
+    // Recordings advance by a fixed timestep so the output doesn't depend on the frame rate
+    let frame_dt = match config.record {
+        Some(_) => 1.0 / config.record_fps,
+        None => update.since_last.as_secs_f64(),
+    };
+    if !model.paused {
+        model.time += frame_dt;
+    }
+
+    // While paused the simulation only advances when single stepping, and while fast-forwarding
+    // it takes a fixed number of steps. Otherwise it takes `speed` steps per frame, or per fixed
+    // timestep with one, carrying fractional steps over to the next frame.
+    let steps = match (model.paused, model.fast_forwarding) {
+        (true, _) => step_once as usize,
+        (false, true) => config.fast_forward as usize,
+        (false, false) => {
+            let steps_per_frame = match config.steps_per_second {
+                Some(rate) => frame_dt * rate,
+                None => 1.0,
+            };
+            model.step_accumulator += steps_per_frame * config.speed;
+            let steps = model.step_accumulator.floor();
+            model.step_accumulator -= steps;
+
+            let max_steps = MAX_STEPS_PER_FRAME as f64 * config.speed.max(1.0);
+            (steps as usize).min(max_steps as usize)
+        }
+    };
+    let dt = match config.steps_per_second {
+        Some(rate) => 1.0 / rate,
+        None => frame_dt,
+    };
+
+    // A live frame plays like a replayed one, its edits and commands already applied
+    let frame = match replayed {
+        Some(frame) => frame,
+        None => FrameInput {
+            steps,
+            dt,
+            attractor: attractor(app, model),
+            ..FrameInput::default()
+        },
+    };
+    let mut session = Session {
+        app,
+        model,
+        start: now,
+        elapsed: Default::default(),
+    };
+    replay::play(&mut session, &frame);
+    let [update_elapsed, mut draw_elapsed] = session.elapsed;
+
+    let steps = frame.steps;
+    let window = app.window(model.window).unwrap();
+    let config = &model.config;
+    let draw = &model.canvas.draw;
+
+    let now = Instant::now();
+
+    // Particles are drawn between their last two steps once per frame instead when interpolating
+    let whole_steps = config.steps_per_second.is_none() && config.speed == 1.0;
+    let fraction = match whole_steps {
+        true => 1.0,
+        false => model.step_accumulator.min(1.0),
+    };
+    if config.interpolate && model.accumulation.is_none() && (steps > 0 || !model.paused) {
+        let alpha = loop_alpha(config, &model.simulation);
+        if model.gpu.is_none() {
+            model.simulation.interpolation = fraction;
+            draw_particles(draw, config, &model.simulation, alpha);
+        }
+        for species in &mut model.species {
+            species.simulation.interpolation = fraction;
+            let (config, simulation) = (&species.config, &species.simulation);
+            match &species.layer {
+                Some((_, layer)) => draw_particles(&layer.draw, config, simulation, alpha),
+                None => draw_particles(draw, config, simulation, alpha),
+            }
+        }
+    }
+
+    if let (Some((buffer, texture)), true) = (&model.accumulation, steps > 0) {
+        upload_accumulation(&window, buffer, texture, config.additive_hue_exposure);
+        draw_simulation(draw, config, &model.simulation, Some(texture), false);
+    }
+
+    for species in &mut model.species {
+        if let Some((_, layer)) = &mut species.layer {
+            layer.render(&window);
+        }
+    }
+    model.canvas.render(&window);
+
+    // Composited over the canvas in `view()`, as the canvas keeps the trails
+    if let (Some(reaction), true) = (&model.simulation.reaction, config.reaction_strength > 0.0) {
+        model
+            .reaction
+            .update(model.simulation.steps, || reaction.texture(app, config));
+    }
+
+    if let Some(gpu) = &model.gpu {
+        let alpha = loop_alpha(config, &model.simulation);
+        for _ in 0..steps {
+            gpu.step(
+                &window,
+                config,
+                &model.simulation,
+                &model.canvas.target.texture,
+                alpha,
+            );
+        }
+    }
+
+    // Streamed once the compute shader has drawn onto the canvas too
+    if let Some(stream) = &mut model.stream {
+        if !stream.write(&window, &model.canvas.target.texture) {
+            log::info!("Stream closed");
+            model.stream = None;
+        }
+    }
+    if let Some(ndi) = &mut model.ndi {
+        ndi.send(&window, &model.canvas.target.texture);
+    }
+    draw_elapsed += now.elapsed();
+
+    let particles = particle_count(model);
+    let step = model.simulation.steps;
+    let frame = update.since_last;
+    model
+        .stats
+        .record(step, particles, update_elapsed, draw_elapsed, frame);
+}
+
+// The sketch playing a frame of input, live or replayed, timing its steps for the stats
+struct Session<'a> {
+    app: &'a App,
+    model: &'a mut Model,
+    start: crate::web::Instant,        // of the update
+    elapsed: [std::time::Duration; 2], // updating and drawing the steps
+}
+
+impl Player for Session<'_> {
+    fn resize(&mut self, [width, height]: [f32; 2]) {
+        resize(self.app, self.model, vec2(width, height));
+    }
+
+    fn configure(&mut self, config: &Config) {
+        if *config != self.model.config {
+            apply_config(self.app, self.model, config.clone());
+        }
+    }
+
+    fn command(&mut self, command: Command) {
+        run(self.app, self.model, command);
+    }
+
+    fn steps(&mut self, steps: usize, dt: f64, attractor: Option<Attractor>) {
+        use crate::web::Instant;
+        let (app, model) = (self.app, &mut *self.model);
+        let window = app.window(model.window).unwrap();
+        let config = &model.config;
+        let draw = &model.canvas.draw;
+
+        // Trails accumulate because the canvas is never cleared, so the wash is only drawn when
+        // rebuilt
+        if config.background_wash_strength > 0.0 {
+            let simulation = &model.simulation;
+            let params = NoiseParams::new(config, simulation.seed);
+            let rebuilt = model
+                .wash
+                .update(params, || wash::texture(app, simulation, config));
+
+            if let (Some(wash), true) = (model.wash.get(), rebuilt) {
+                draw.texture(wash).wh(simulation.size());
+            }
+        }
+
+        if let Some(recorder) = &mut model.recorder {
+            recorder.frame.steps = steps;
+            recorder.frame.dt = dt;
+            recorder.frame.attractor = attractor.clone();
+            let camera = [model.camera.pan.x, model.camera.pan.y, model.camera.zoom];
+            recorder.record(config, model.simulation.seed, camera);
+        }
+        let mut update_elapsed = self.start.elapsed();
+        let mut draw_elapsed = std::time::Duration::ZERO;
+
+        // Every step is drawn, so the trails don't depend on how many steps a frame takes
+        for step in 0..steps {
+            let now = Instant::now();
+
+            // a loop of no frames from a config file doesn't loop, rather than resetting every step
+            if let Some(frames) = config.loop_frames.filter(|&frames| frames > 0) {
+                if model.simulation.steps >= frames {
+                    model.simulation.run_to(config, 0);
+                    for species in &mut model.species {
+                        species.simulation.run_to(&species.config, 0);
+                    }
+
+                    if let Some((buffer, _)) = &mut model.accumulation {
+                        buffer.clear();
+                    }
+                    if let Some(gpu) = &model.gpu {
+                        gpu.upload(&window, &model.simulation);
+                    }
+                }
+            }
+
+            // The compute shader steps the particles while drawing them, after the canvas is
+            // rendered
+            match model.gpu {
+                Some(_) => model.simulation.steps += 1,
+                None => {
+                    model.simulation.spawn(config, dt);
+                    model.simulation.attractor = attractor.clone();
+                    model.simulation.step(config);
+                }
+            }
+            for species in &mut model.species {
+                species.simulation.spawn(&species.config, dt);
+                species.simulation.attractor = attractor.clone();
+                species.simulation.step(&species.config);
+            }
+
+            if let Some((buffer, _)) = &mut model.accumulation {
+                splat_accumulation(buffer, config, &model.simulation);
+                for species in &model.species {
+                    splat_particles(buffer, &species.config, &species.simulation);
+                }
+            }
+
+            update_elapsed += now.elapsed();
+
+            // Draw onto the canvas here rather than in `view()`, which only presents it. The
+            // additive buffer holds the trails itself, so it replaces drawing the particles and is
+            // drawn once.
+            let now = Instant::now();
+            // fast-forwarding only draws the last
+            let drawn = !model.fast_forwarding || step + 1 == steps;
+            if model.accumulation.is_none() && drawn {
+                draw_step(model);
+            }
+            draw_elapsed += now.elapsed();
+
+            // The compute shader's particles never reach the CPU, so they can't be rewound
+            if model.gpu.is_none() {
+                model.history.push(snapshots(model));
+            }
+        }
+
+        self.elapsed = [update_elapsed, draw_elapsed];
+    }
+}
+
+// The window is gone by now, so the canvas is read back through the device kept for it
+fn exit(app: &App, model: Model) {
+    let (config, seed) = (&model.config, model.simulation.seed);
+    session::summarize(
+        app,
+        config,
+        seed,
+        model.simulation.steps,
+        Some(&model.stats),
+    );
+    if config.save_on_exit {
+        let (device, queue) = (model.device.device(), model.device.queue());
+        let image = headless::read(device, queue, &model.canvas.target.texture);
+        session::save(config, seed, &image);
+    }
+}
+
+// Draw the last step of the main simulation and every species onto the canvas or their layers,
+// unless the additive buffer holds the trails instead
+fn draw_step(model: &Model) {
+    let (config, draw) = (&model.config, &model.canvas.draw);
+    let particles = model.gpu.is_none() && !config.interpolate;
+    draw_simulation(draw, config, &model.simulation, None, particles);
+
+    // on top of the main particles, in the order they are defined in
+    let alpha = loop_alpha(config, &model.simulation);
+    for species in &model.species {
+        let (config, simulation) = (&species.config, &species.simulation);
+        let particles = !config.interpolate;
+        match &species.layer {
+            Some((blend, layer)) => {
+                draw_layer(&layer.draw, config, simulation, *blend, alpha, particles);
+            }
+            None if particles => draw_particles(draw, config, simulation, alpha),
+            None => {}
+        }
+    }
+}
+
+// Current state of the main simulation and every species
+pub fn snapshots(model: &Model) -> Vec<Snapshot> {
+    let species = model
+        .species
+        .iter()
+        .map(|species| species.simulation.snapshot());
+    std::iter::once(model.simulation.snapshot())
+        .chain(species)
+        .collect()
+}
+
+// Step back to the state before the current one and pause there. The trails can't be undone, so
+// the canvas is cleared and every step still in the history is drawn again.
+fn rewind(app: &App, model: &mut Model) {
+    if !model.history.rewind() {
+        return;
+    }
+    model.paused = true;
+    clear(model);
+
+    let history = std::mem::replace(&mut model.history, History::new(0));
+    for snapshots in history.iter() {
+        model.simulation.restore(&snapshots[0]);
+        for (species, snapshot) in model.species.iter_mut().zip(&snapshots[1..]) {
+            species.simulation.restore(snapshot);
+        }
+
+        match &mut model.accumulation {
+            Some((buffer, _)) => {
+                splat_accumulation(buffer, &model.config, &model.simulation);
+                for species in &model.species {
+                    splat_particles(buffer, &species.config, &species.simulation);
+                }
+            }
+            None => draw_step(model),
+        }
+    }
+    model.history = history;
+
+    let window = app.window(model.window).unwrap();
+    if let Some((buffer, texture)) = &model.accumulation {
+        let config = &model.config;
+        upload_accumulation(&window, buffer, texture, config.additive_hue_exposure);
+        draw_simulation(
+            &model.canvas.draw,
+            config,
+            &model.simulation,
+            Some(texture),
+            false,
+        );
+    }
+}
+
+// Number of particles across the main simulation and every species
+fn particle_count(model: &Model) -> usize {
+    let species = model
+        .species
+        .iter()
+        .map(|species| species.simulation.flow_field.len());
+    model.simulation.flow_field.len() + species.sum::<usize>()
+}
+
+// Left click attracts particles around the mouse, right click repels them
+fn attractor(app: &App, model: &Model) -> Option<Attractor> {
+    if model.egui.ctx().wants_pointer_input() {
+        return None;
+    }
+
+    let buttons = &app.mouse.buttons;
+    let sign = match (buttons.left().is_down(), buttons.right().is_down()) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => return None,
+    };
+
+    let mouse = model.camera.to_canvas(app.mouse.position());
+    Some(Attractor {
+        pos: model.simulation.position(mouse),
+        radius: model.config.mouse_radius / 2.0,
+        strength: sign * model.config.mouse_strength,
+    })
+}
+
+// Restart the simulation from its seed, wiping the trails
+fn reset(app: &App, model: &mut Model) {
+    model.simulation.run_to(&model.config, 0);
+    for species in &mut model.species {
+        species.simulation.run_to(&species.config, 0);
+    }
+    if let Some(gpu) = &model.gpu {
+        gpu.upload(&app.window(model.window).unwrap(), &model.simulation);
+    }
+    model.history.clear();
+    clear(model);
+}
+
+fn randomize(app: &App, model: &mut Model) {
+    let config = randomize::randomize(&model.config);
+    apply_config(app, model, config);
+}
+
+// Named after the seed, which the randomized parameters were picked along with
+fn save_preset(model: &Model) {
+    let name = format!("random_{}", model.simulation.seed);
+    match model.config.save_preset(&name) {
+        Ok(path) => log::info!(
+            "Saved preset {}, load with `--preset {}`",
+            path.display(),
+            name
+        ),
+        Err(err) => log::warn!("Could not save preset {}: {}", name, err),
+    }
+}
+
+// Pick a new random seed, rebuilding the noise, the random number generator and the flow field
+fn reseed(app: &App, model: &mut Model) {
+    let mut config = model.config.clone();
+    config.seed = seed::random(&mut rand::thread_rng());
+    apply_config(app, model, config);
+}
+
+fn set_speed(app: &App, model: &mut Model, speed: f64) {
+    let mut config = model.config.clone();
+    config.speed = speed;
+    apply_config(app, model, config);
+}
+
+// Wipe the trails, keeping the simulation as it is
+fn clear(model: &mut Model) {
+    if let Some((buffer, _)) = &mut model.accumulation {
+        buffer.clear();
+    }
+
+    let size = model.simulation.size();
+    model
+        .background
+        .draw(&model.canvas.draw, &model.config, size);
+    for (blend, layer) in model
+        .species
+        .iter()
+        .filter_map(|species| species.layer.as_ref())
+    {
+        layer.draw.background().color(blend.neutral());
+    }
+    model.wash = FieldCache::new(); // redraw the wash over the cleared canvas
+}
+
+// Rescale everything sized after the window when it is resized or made fullscreen, or the domain
+// is scaled, stretching the trails so far onto the new canvas
+fn resized(app: &App, model: &mut Model, size: Vec2) {
+    // Replays resize to the recorded sizes instead
+    if model.replay.is_some() {
+        return;
+    }
+    if let Some(recorder) = &mut model.recorder {
+        recorder.frame.size = Some(size.to_array());
+    }
+    resize(app, model, size);
+}
+
+fn resize(app: &App, model: &mut Model, size: Vec2) {
+    let scale = model.config.domain_scale;
+    let (x_end, y_end) = (size.x as f64 / 2.0 * scale, size.y as f64 / 2.0 * scale);
+    let unchanged = x_end == model.simulation.x_end && y_end == model.simulation.y_end;
+    if unchanged || x_end < 1.0 || y_end < 1.0 {
+        return; // also minimized
+    }
+
+    model.simulation.resize(&model.config, x_end, y_end);
+    for species in &mut model.species {
+        species.simulation.resize(&species.config, x_end, y_end);
+    }
+
+    let window = app.window(model.window).unwrap();
+    let pixels = [(x_end * 2.0) as u32, (y_end * 2.0) as u32];
+    let samples = target::samples(&model.config);
+    model.canvas = model
+        .canvas
+        .resized(&window, pixels, model.config.background.linear(), samples);
+    for species in &mut model.species {
+        if let Some((blend, layer)) = &mut species.layer {
+            *layer = layer.resized(&window, pixels, blend.neutral(), samples);
+        }
+    }
+
+    // The additive buffer and the compute shader's particles are sized after the window
+    model.accumulation = accumulation(window.device(), &model.config, x_end, y_end);
+    if model.gpu.is_some() {
+        model.gpu = Some(GpuSimulation::new(&window, &model.simulation));
+    }
+
+    model.history.clear(); // positions of another size
+    model.wash = FieldCache::new();
+}
+
+// Pan the camera while dragging with the middle mouse button
+fn mouse_moved(app: &App, model: &mut Model, pos: Point2) {
+    let dragging = app.mouse.buttons.middle().is_down() && model.replay.is_none();
+    if dragging && !model.egui.ctx().wants_pointer_input() {
+        model.camera.pan += pos - model.mouse;
+    }
+    model.mouse = pos;
+}
+
+// Zoom the camera around the mouse, no further out than the whole domain fitting the window
+fn mouse_wheel(app: &App, model: &mut Model, delta: MouseScrollDelta, _phase: TouchPhase) {
+    if model.egui.ctx().wants_pointer_input() || model.replay.is_some() {
+        return;
+    }
+
+    let lines = match delta {
+        MouseScrollDelta::LineDelta(_, y) => y,
+        MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / PIXELS_PER_LINE,
+    };
+    let min_zoom = 1.0 / model.config.domain_scale.max(1.0) as f32;
+    model
+        .camera
+        .zoom_at(app.mouse.position(), ZOOM_STEP.powf(lines), min_zoom);
+}
+
+fn raw_window_event(_app: &App, model: &mut Model, event: &nannou::winit::event::WindowEvent) {
+    model.egui.handle_raw_event(event);
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if model.egui.ctx().wants_keyboard_input() {
+        return;
+    }
+
+    let command = match key {
+        Key::Tab => Command::TogglePanel,
+        Key::F1 => Command::ToggleStats,
+        Key::F2 => Command::ToggleField,
+        Key::Space => Command::TogglePause,
+        Key::Period => Command::StepOnce,
+        Key::Left => Command::Rewind,
+        Key::Key3 => Command::ToggleFastForward,
+        Key::Home => Command::ResetCamera,
+        Key::C => Command::Clear,
+        _ => return other_key_pressed(app, model, key),
+    };
+
+    // Replays play back the recorded commands instead
+    if model.replay.is_none() {
+        self::command(app, model, command);
+    }
+}
+
+// Keys that aren't recorded, either because they change the config, which is recorded instead, or
+// because they only save files
+fn other_key_pressed(app: &App, model: &mut Model, key: Key) {
+    let replaying = model.replay.is_some();
+    match key {
+        Key::F5 => {
+            let path = seed::expand(&model.config.save_state, model.simulation.seed);
+            state::save(&app.window(model.window).unwrap(), model, &path);
+            log::info!("Saved state {}", path.display());
+        }
+        Key::F11 => {
+            let window = app.window(model.window).unwrap();
+            window.set_fullscreen(!window.is_fullscreen());
+        }
+        Key::S => model.capture_next = true,
+        Key::P => save_preset(model),
+        _ if replaying => {}
+        Key::Key1 => set_speed(app, model, SLOW_MOTION),
+        Key::Key2 => set_speed(app, model, 1.0),
+        Key::R => reseed(app, model),
+        Key::X => randomize(app, model),
+        _ => {}
+    }
+}
+
+// Run a command, recording it
+fn command(app: &App, model: &mut Model, command: Command) {
+    if let Some(recorder) = &mut model.recorder {
+        recorder.frame.commands.push(command);
+    }
+    run(app, model, command);
+}
+
+fn run(app: &App, model: &mut Model, command: Command) {
+    match command {
+        Command::TogglePanel => model.show_panel = !model.show_panel,
+        Command::ToggleStats => model.show_stats = !model.show_stats,
+        Command::ToggleField => model.show_field = !model.show_field,
+        Command::TogglePause => model.paused = !model.paused,
+        Command::StepOnce => model.step_once = true,
+        Command::Rewind => rewind(app, model),
+        Command::ToggleFastForward => model.fast_forwarding = !model.fast_forwarding,
+        Command::ResetCamera => model.camera = Camera::new(),
+        Command::Clear => clear(model),
+        Command::Reset => reset(app, model),
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let camera = &model.camera;
+    let size = model.simulation.size();
+
+    // The canvas fills the window unless the camera moved or the domain is larger
+    let stretched = camera.is_identity() && model.config.domain_scale == 1.0;
+    if stretched {
+        model.canvas.present(&frame);
+    } else {
+        let draw = app.draw();
+        draw.background().color(model.config.background.linear());
+        camera
+            .transform(&draw)
+            .texture(&model.canvas.target.texture)
+            .wh(size);
+        draw.to_frame(app, &frame).unwrap();
+    }
+
+    if let (Some(reaction), true) = (model.reaction.get(), model.config.reaction_strength > 0.0) {
+        let draw = app.draw();
+        let (draw, size) = match stretched {
+            true => (draw, frame.rect().wh()),
+            false => (camera.transform(&draw), size),
+        };
+        draw.blend(BlendMode::Add.component())
+            .texture(reaction)
+            .wh(size);
+        draw.to_frame(app, &frame).unwrap();
+    }
+
+    // Layers are composited over the canvas afresh every frame, as they accumulate trails too
+    let layers: Vec<_> = model
+        .species
+        .iter()
+        .filter_map(|species| species.layer.as_ref())
+        .collect();
+    if !layers.is_empty() {
+        let draw = app.draw();
+        let (draw, size) = match stretched {
+            true => (draw, frame.rect().wh()),
+            false => (camera.transform(&draw), size),
+        };
+        for (blend, layer) in layers {
+            draw.blend(blend.component())
+                .texture(&layer.target.texture)
+                .wh(size);
+        }
+        draw.to_frame(app, &frame).unwrap();
+    }
+
+    // Drawn over the canvas rather than onto it, so the trails are untouched when hidden again
+    if model.show_field && !model.capturing {
+        let draw = camera.transform(&app.draw());
+        field_view::draw(&draw, &model.config, &model.simulation);
+        draw.to_frame(app, &frame).unwrap();
+    }
+
+    if (model.show_panel || model.show_stats) && !model.capturing {
+        model.egui.draw_to_frame(&frame).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+use crate::config::Config;
+use crate::field::VectorField;
+use crate::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use rayon::prelude::*;
+use std::sync::{Arc, RwLock};
+
+const SOLVER_ITERATIONS: usize = 30; // Jacobi iterations of the diffusion and pressure solves
+const DAMPING: f32 = 0.995; // portion of the velocity kept every step, so jets don't blow it up
+const JET_RADIUS: f64 = 3.0; // in cells
+
+// Parameters the fluid is computed from, unless it is off
+#[derive(Clone, PartialEq)]
+pub struct FluidConfig {
+    resolution: u32,
+    viscosity: f32,
+    jets: u32,
+    force: f32,
+}
+
+impl FluidConfig {
+    pub fn new(config: &Config) -> Option<Self> {
+        config.fluid.then(|| FluidConfig {
+            resolution: config.fluid_resolution.max(2),
+            viscosity: config.fluid_viscosity,
+            jets: config.fluid_jets,
+            force: config.fluid_force,
+        })
+    }
+}
+
+// Stam's stable fluids on a grid wrapping around the edges, in cells per step: forces are added,
+// the velocity diffuses by the viscosity, is made divergence-free, advects itself
+// semi-Lagrangian and is made divergence-free again. Rows go up like positions do.
+struct StableFluid {
+    width: usize,
+    height: usize,
+    vx: Vec<f32>,
+    vy: Vec<f32>,
+    jets: Vec<([f64; 2], [f32; 2])>, // normalized position and force of every jet
+    stir: Option<[f64; 2]>,          // where the mouse stirred last step, in cells
+    steps: u64,
+}
+
+impl StableFluid {
+    fn new(config: &FluidConfig, seed: u32, aspect: f64) -> Self {
+        let resolution = config.resolution as f64;
+        let (width, height) = match aspect >= 1.0 {
+            true => (resolution, (resolution / aspect).round().max(2.0)),
+            false => ((resolution * aspect).round().max(2.0), resolution),
+        };
+        let (width, height) = (width as usize, height as usize);
+
+        let mut rng = ChaCha12Rng::seed_from_u64(seed as u64);
+        let jets = (0..config.jets)
+            .map(|_| {
+                let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                let pos = [rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0)];
+                (
+                    pos,
+                    [angle.cos() * config.force, angle.sin() * config.force],
+                )
+            })
+            .collect();
+
+        StableFluid {
+            width,
+            height,
+            vx: vec![0.0; width * height],
+            vy: vec![0.0; width * height],
+            jets,
+            stir: None,
+            steps: 0,
+        }
+    }
+
+    fn index(&self, x: i64, y: i64) -> usize {
+        let column = x.rem_euclid(self.width as i64) as usize;
+        let row = y.rem_euclid(self.height as i64) as usize;
+        row * self.width + column
+    }
+
+    // Sum of the four neighbors of every cell
+    fn neighbors(&self, values: &[f32], i: usize) -> f32 {
+        let (x, y) = ((i % self.width) as i64, (i / self.width) as i64);
+        values[self.index(x - 1, y)]
+            + values[self.index(x + 1, y)]
+            + values[self.index(x, y - 1)]
+            + values[self.index(x, y + 1)]
+    }
+
+    // Add `force` to the cells within `radius` of a point in cells, fading out towards it
+    fn push(&mut self, [px, py]: [f64; 2], [fx, fy]: [f32; 2], radius: f64) {
+        let reach = radius.ceil() as i64;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let distance = (dx as f64).hypot(dy as f64);
+                if distance < radius {
+                    let weight = (1.0 - distance / radius) as f32;
+                    let i = self.index(px as i64 + dx, py as i64 + dy);
+                    self.vx[i] += fx * weight;
+                    self.vy[i] += fy * weight;
+                }
+            }
+        }
+    }
+
+    fn step(&mut self, config: &FluidConfig, stir: Option<([f64; 2], f64)>) {
+        for (pos, force) in self.jets.clone() {
+            let cell = [pos[0] * self.width as f64, pos[1] * self.height as f64];
+            self.push(cell, force, JET_RADIUS);
+        }
+
+        // dragging the held mouse pushes the fluid along with it
+        let stir = stir.map(|([s, t], radius)| {
+            let cell = [s * self.width as f64, t * self.height as f64];
+            (cell, radius * self.width as f64)
+        });
+        if let (Some((cell, radius)), Some(last)) = (stir, self.stir) {
+            let drag = [(cell[0] - last[0]) as f32, (cell[1] - last[1]) as f32];
+            self.push(cell, drag, radius);
+        }
+        self.stir = stir.map(|(cell, _)| cell);
+
+        if config.viscosity > 0.0 {
+            let a = config.viscosity;
+            for _ in 0..SOLVER_ITERATIONS {
+                let (vx, vy) = (&self.vx, &self.vy);
+                let solve = |values: &[f32], previous: &[f32]| -> Vec<f32> {
+                    (0..values.len())
+                        .into_par_iter()
+                        .map(|i| (previous[i] + a * self.neighbors(values, i)) / (1.0 + 4.0 * a))
+                        .collect()
+                };
+                let (next_x, next_y) = (solve(vx, vx), solve(vy, vy));
+                self.vx = next_x;
+                self.vy = next_y;
+            }
+        }
+        self.project();
+        self.advect();
+        self.project();
+
+        self.vx
+            .iter_mut()
+            .chain(&mut self.vy)
+            .for_each(|v| *v *= DAMPING);
+        self.steps += 1;
+    }
+
+    // Subtract the gradient of the pressure solved from the divergence
+    fn project(&mut self) {
+        let (w, h) = (self.width, self.height);
+        let at = |values: &[f32], x: i64, y: i64| values[self.index(x, y)];
+        let divergence: Vec<f32> = (0..w * h)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = ((i % w) as i64, (i / w) as i64);
+                -0.5 * (at(&self.vx, x + 1, y) - at(&self.vx, x - 1, y) + at(&self.vy, x, y + 1)
+                    - at(&self.vy, x, y - 1))
+            })
+            .collect();
+
+        let mut pressure = vec![0.0; w * h];
+        for _ in 0..SOLVER_ITERATIONS {
+            pressure = (0..w * h)
+                .into_par_iter()
+                .map(|i| (divergence[i] + self.neighbors(&pressure, i)) / 4.0)
+                .collect();
+        }
+
+        let (vx, vy): (Vec<f32>, Vec<f32>) = (0..w * h)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = ((i % w) as i64, (i / w) as i64);
+                (
+                    self.vx[i] - 0.5 * (at(&pressure, x + 1, y) - at(&pressure, x - 1, y)),
+                    self.vy[i] - 0.5 * (at(&pressure, x, y + 1) - at(&pressure, x, y - 1)),
+                )
+            })
+            .unzip();
+        self.vx = vx;
+        self.vy = vy;
+    }
+
+    // Carry the velocity along itself, sampling where every cell's content came from
+    fn advect(&mut self) {
+        let w = self.width;
+        let (vx, vy): (Vec<f32>, Vec<f32>) = (0..w * self.height)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = ((i % w) as f64, (i / w) as f64);
+                let [vx, vy] = self.velocity_at(x - self.vx[i] as f64, y - self.vy[i] as f64);
+                (vx as f32, vy as f32)
+            })
+            .unzip();
+        self.vx = vx;
+        self.vy = vy;
+    }
+
+    // Velocity at a point in cells, interpolated bilinearly between cell centers
+    fn velocity_at(&self, x: f64, y: f64) -> [f64; 2] {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+        [&self.vx, &self.vy].map(|values| {
+            let at = |x: i64, y: i64| values[self.index(x, y)] as f64;
+            lerp(
+                lerp(at(x0, y0), at(x0 + 1, y0), tx),
+                lerp(at(x0, y0 + 1), at(x0 + 1, y0 + 1), tx),
+                ty,
+            )
+        })
+    }
+}
+
+// Fluid advanced along with the simulation, one solver step per simulation step. Only the seeded
+// jets are replayed when rewinding, not the mouse.
+pub struct Fluid {
+    config: FluidConfig,
+    seed: u32,
+    aspect: f64, // width over height of the canvas the grid spans
+    grid: RwLock<StableFluid>,
+}
+
+impl Fluid {
+    pub fn build(config: &Config, noise_seed: u32, extent: Vector2) -> Option<Arc<Self>> {
+        let config = FluidConfig::new(config)?;
+        let aspect = extent.x / extent.y;
+        Some(Arc::new(Fluid {
+            grid: RwLock::new(StableFluid::new(&config, noise_seed, aspect)),
+            config,
+            seed: noise_seed,
+            aspect,
+        }))
+    }
+
+    // Bring the fluid to `steps` simulation steps, starting over if it is past them, stirred at
+    // a normalized position within a radius normalized to the width. Called before the flows
+    // are stepped so they never wait on it.
+    pub fn advance(&self, steps: u64, stir: Option<([f64; 2], f64)>) {
+        let mut grid = self.grid.write().unwrap();
+        if grid.steps > steps {
+            *grid = StableFluid::new(&self.config, self.seed, self.aspect);
+        }
+        while grid.steps < steps {
+            grid.step(&self.config, stir);
+        }
+    }
+}
+
+// Field blended with the velocity of the fluid, which isn't normalized so the fluid takes over
+// where it moves fast and leaves the main field elsewhere
+pub struct FluidField {
+    pub field: Box<dyn VectorField>,
+    pub fluid: Arc<Fluid>,
+}
+
+impl VectorField for FluidField {
+    fn vector(&self, config: &Config, pos: Vector2, extent: Vector2, steps: u64) -> Vector2 {
+        let vector = self.field.vector(config, pos, extent, steps);
+        let vector = match vector.length() {
+            length if length > 0.0 => vector / length,
+            _ => vector,
+        };
+
+        let grid = self.fluid.grid.read().unwrap();
+        let (x, y) = (
+            pos.x / extent.x * grid.width as f64 - 0.5,
+            pos.y / extent.y * grid.height as f64 - 0.5,
+        );
+        let [vx, vy] = grid.velocity_at(x, y);
+
+        vector * config.field_weight + Vector2 { x: vx, y: vy } * config.fluid_weight
+    }
+}
@@ -0,0 +1,60 @@
+use crate::config::{self, Config};
+use crate::seed;
+
+// Cycles through presets on an unattended exhibition screen, `gallery` seconds each and with a
+// fresh seed every time. Over the last `gallery_fade` seconds of a preset its trails fade out, so
+// the next one starts on a clean canvas.
+pub struct Gallery {
+    presets: Vec<String>,
+    index: Option<usize>, // preset shown, none before the first
+    elapsed: f64,         // seconds it has been shown for
+    trail_fade: f32,      // of the preset shown, ramped up from while fading out
+}
+
+impl Gallery {
+    pub fn new(config: &Config) -> Self {
+        let presets = match config.gallery_presets.is_empty() {
+            true => config::presets(),
+            false => config.gallery_presets.clone(),
+        };
+        assert!(!presets.is_empty(), "No presets for the gallery to show");
+
+        Gallery {
+            presets,
+            index: None,
+            elapsed: 0.0,
+            trail_fade: 0.0,
+        }
+    }
+
+    // Advance by `dt` seconds, switching to the next preset or fading out the current one
+    pub fn apply(&mut self, config: &mut Config, dt: f64) {
+        let duration = config.gallery.unwrap_or(f64::INFINITY);
+        self.elapsed += dt;
+
+        if self.index.is_none() || self.elapsed >= duration {
+            let index = self
+                .index
+                .map_or(0, |index| (index + 1) % self.presets.len());
+            let name = &self.presets[index];
+            match Config::try_load_preset(name) {
+                Ok(preset) => {
+                    log::info!("Showing preset {}", name);
+                    *config = preset;
+                    config.seed = seed::random(&mut rand::thread_rng());
+                }
+                Err(err) => log::warn!("Skipping preset {}: {}", name, err),
+            }
+
+            self.index = Some(index);
+            self.elapsed = 0.0;
+            self.trail_fade = config.trail_fade;
+            return;
+        }
+
+        let fading = (self.elapsed - (duration - config.gallery_fade)) / config.gallery_fade;
+        if fading > 0.0 {
+            config.trail_fade = self.trail_fade.max(fading.min(1.0) as f32);
+        }
+    }
+}
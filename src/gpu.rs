@@ -0,0 +1,194 @@
+use crate::config::Config;
+use crate::particles::Simulation;
+use crate::seed::noise_seed;
+use nannou::prelude::*;
+use nannou::wgpu::util::DeviceExt;
+use rand::{prelude::StdRng, seq::SliceRandom, SeedableRng};
+
+const WORKGROUP_SIZE: u32 = 64; // matches `workgroup_size` in the shader
+const PARAMS_SIZE: u64 = 48; // size of `Params` in the shader, in bytes
+
+// Particles advected by a compute shader and drawn straight from the same buffer as points onto
+// the canvas, so positions never travel back to the CPU. The shader only implements its own Perlin
+// noise angle field, so the trails differ from the CPU path for the same seed.
+pub struct GpuSimulation {
+    count: u32,
+    particles: wgpu::Buffer, // one `vec2<f32>` position per particle
+    params: wgpu::Buffer,
+    compute_bind_group: wgpu::BindGroup,
+    render_bind_group: wgpu::BindGroup,
+    compute_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl GpuSimulation {
+    // Upload the flows of `simulation` and a permutation table shuffled from its seed
+    pub fn new(window: &Window, simulation: &Simulation) -> Self {
+        let device = window.device();
+        let module = device.create_shader_module(&wgpu::include_wgsl!("advect.wgsl"));
+
+        let mut rng = StdRng::seed_from_u64(noise_seed(simulation.seed) as u64);
+        let mut permutation: Vec<u32> = (0..256).collect();
+        permutation.shuffle(&mut rng);
+        permutation.extend_from_within(..);
+
+        let particles = device.create_buffer_init(&wgpu::BufferInitDescriptor {
+            label: Some("particles"),
+            contents: &positions(simulation),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_DST,
+        });
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("params"),
+            size: PARAMS_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let permutation = device.create_buffer_init(&wgpu::BufferInitDescriptor {
+            label: Some("permutation"),
+            contents: &words(&permutation),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        // Vertex shaders can't write storage buffers, so drawing only binds the first two
+        let layout = |stage, particles| {
+            let builder = wgpu::BindGroupLayoutBuilder::new()
+                .uniform_buffer(stage, false)
+                .storage_buffer(stage, false, true);
+            match particles {
+                true => builder.storage_buffer(stage, false, false),
+                false => builder,
+            }
+            .build(device)
+        };
+        let compute_layout = layout(wgpu::ShaderStages::COMPUTE, true);
+        let render_layout = layout(wgpu::ShaderStages::VERTEX, false);
+
+        let compute_bind_group = wgpu::BindGroupBuilder::new()
+            .buffer_bytes(&params, 0, None)
+            .buffer_bytes(&permutation, 0, None)
+            .buffer_bytes(&particles, 0, None)
+            .build(device, &compute_layout);
+        let render_bind_group = wgpu::BindGroupBuilder::new()
+            .buffer_bytes(&params, 0, None)
+            .buffer_bytes(&permutation, 0, None)
+            .build(device, &render_layout);
+
+        let compute_pipeline_layout = pipeline_layout(device, &compute_layout);
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("advect"),
+            layout: Some(&compute_pipeline_layout),
+            module: &module,
+            entry_point: "advect",
+        });
+
+        const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![0 => Float32x2];
+        let render_pipeline_layout = pipeline_layout(device, &render_layout);
+        let render_pipeline =
+            wgpu::RenderPipelineBuilder::from_layout(&render_pipeline_layout, &module)
+                .vertex_entry_point("vs_main")
+                .fragment_shader(&module)
+                .fragment_entry_point("fs_main")
+                .color_format(Frame::TEXTURE_FORMAT)
+                .add_vertex_buffer::<[f32; 2]>(&ATTRIBUTES)
+                .primitive_topology(wgpu::PrimitiveTopology::PointList)
+                .build(device);
+
+        GpuSimulation {
+            count: simulation.flow_field.len() as u32,
+            particles,
+            params,
+            compute_bind_group,
+            render_bind_group,
+            compute_pipeline,
+            render_pipeline,
+        }
+    }
+
+    // Replace the particle positions with the flows of `simulation`, e.g. after rewinding it
+    pub fn upload(&self, window: &Window, simulation: &Simulation) {
+        window
+            .queue()
+            .write_buffer(&self.particles, 0, &positions(simulation));
+    }
+
+    // Advance every particle by one step, then draw them on top of `texture`
+    pub fn step(
+        &self,
+        window: &Window,
+        config: &Config,
+        simulation: &Simulation,
+        texture: &wgpu::Texture,
+        alpha: f32,
+    ) {
+        let params = [
+            simulation.x_end as f32,
+            simulation.y_end as f32,
+            config.heading_noise_factor as f32,
+            config.heading_noise_multiplier as f32,
+            config.color_noise_factor as f32,
+            config.color_noise_multiplier as f32,
+            config.hue_min as f32,
+            config.hue_max as f32,
+            (config.velocity_multiplier * config.dt) as f32,
+            alpha,
+        ];
+        let mut params: Vec<u32> = params.iter().map(|value| value.to_bits()).collect();
+        params.extend([self.count, 0]);
+        window
+            .queue()
+            .write_buffer(&self.params, 0, &words(&params));
+
+        let device = window.device();
+        let descriptor = wgpu::CommandEncoderDescriptor {
+            label: Some("gpu simulation"),
+        };
+        let mut encoder = device.create_command_encoder(&descriptor);
+
+        {
+            let descriptor = wgpu::ComputePassDescriptor {
+                label: Some("advect"),
+            };
+            let mut pass = encoder.begin_compute_pass(&descriptor);
+            pass.set_pipeline(&self.compute_pipeline);
+            pass.set_bind_group(0, &self.compute_bind_group, &[]);
+            pass.dispatch(self.count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        let view = texture.view().build();
+        {
+            let mut pass = wgpu::RenderPassBuilder::new()
+                .color_attachment(&view, |color| color.load_op(wgpu::LoadOp::Load))
+                .begin(&mut encoder);
+            pass.set_pipeline(&self.render_pipeline);
+            pass.set_bind_group(0, &self.render_bind_group, &[]);
+            pass.set_vertex_buffer(0, self.particles.slice(..));
+            pass.draw(0..self.count, 0..1);
+        }
+
+        window.queue().submit(Some(encoder.finish()));
+    }
+}
+
+fn pipeline_layout(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> wgpu::PipelineLayout {
+    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    })
+}
+
+fn positions(simulation: &Simulation) -> Vec<u8> {
+    let positions: Vec<u32> = simulation
+        .flow_field
+        .iter()
+        .flat_map(|flow| [flow.pos.x as f32, flow.pos.y as f32])
+        .map(f32::to_bits)
+        .collect();
+    words(&positions)
+}
+
+fn words(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_ne_bytes()).collect()
+}
@@ -0,0 +1,99 @@
+use crate::cache::FieldCache;
+use crate::config::Config;
+use crate::field::VectorField;
+use crate::particles::NoiseParams;
+use crate::Vector2;
+use rayon::prelude::*;
+
+// The field sampled on a fixed lattice spanning the canvas, which particles interpolate
+// bilinearly instead of each evaluating the field. The lattice is only resampled when the field
+// parameters change, or every step while the field evolves or the reaction or the fluid runs.
+pub struct FlowGrid {
+    pub columns: usize, // lattice points along each axis, one more than the cells
+    pub rows: usize,
+    extent: Vector2,
+    vectors: FieldCache<(NoiseParams, u64), Vec<Vector2>>, // row-major, keyed by the step too
+}
+
+impl FlowGrid {
+    // Lattice of `grid_resolution` cells along the longer side of the canvas, if enabled
+    pub fn new(config: &Config, x_end: f64, y_end: f64) -> Option<Self> {
+        if config.grid_resolution == 0 {
+            return None;
+        }
+
+        let resolution = config.grid_resolution as f64;
+        let cells = |side: f64| ((resolution * side / x_end.max(y_end)).round() as usize).max(1);
+
+        Some(FlowGrid {
+            columns: cells(x_end) + 1,
+            rows: cells(y_end) + 1,
+            extent: Vector2 { x: x_end, y: y_end },
+            vectors: FieldCache::new(),
+        })
+    }
+
+    // Position of a lattice point
+    pub fn point(&self, column: usize, row: usize) -> Vector2 {
+        Vector2 {
+            x: self.extent.x * column as f64 / (self.columns - 1) as f64,
+            y: self.extent.y * row as f64 / (self.rows - 1) as f64,
+        }
+    }
+
+    // Resample the lattice if the field changed since it was last sampled
+    pub fn update(
+        &mut self,
+        config: &Config,
+        field: &dyn VectorField,
+        params: NoiseParams,
+        steps: u64,
+    ) {
+        let time = if config.evolution_speed == 0.0 && !config.reaction && !config.fluid {
+            0
+        } else {
+            steps
+        };
+        let key = (params, time);
+        if !self.vectors.is_dirty(&key) {
+            return;
+        }
+
+        let vectors = (0..self.columns * self.rows)
+            .into_par_iter()
+            .map(|i| {
+                let point = self.point(i % self.columns, i / self.columns);
+                field.vector(config, point, self.extent, steps)
+            })
+            .collect();
+        self.vectors.update(key, || vectors);
+    }
+
+    // Whether the lattice has been sampled yet, which the first step does
+    pub fn sampled(&self) -> bool {
+        self.vectors.get().is_some()
+    }
+
+    // Field at a position, interpolated between the four surrounding lattice points and clamped
+    // to the lattice outside of the canvas
+    pub fn sample(&self, pos: Vector2) -> Vector2 {
+        let vectors = self.vectors.get().expect("sampled before the first update");
+
+        let u = (pos.x / self.extent.x).clamp(0.0, 1.0) * (self.columns - 1) as f64;
+        let v = (pos.y / self.extent.y).clamp(0.0, 1.0) * (self.rows - 1) as f64;
+        let (column, row) = (
+            (u as usize).min(self.columns - 2),
+            (v as usize).min(self.rows - 2),
+        );
+        let (tx, ty) = (u - column as f64, v - row as f64);
+
+        let at = |column: usize, row: usize| vectors[row * self.columns + column];
+        let lerp = |a: Vector2, b: Vector2, t: f64| a * (1.0 - t) + b * t;
+
+        lerp(
+            lerp(at(column, row), at(column + 1, row), tx),
+            lerp(at(column, row + 1), at(column + 1, row + 1), tx),
+            ty,
+        )
+    }
+}
@@ -0,0 +1,357 @@
+use crate::background::BackgroundMode;
+use crate::config::Config;
+use crate::edge::EdgeMode;
+use crate::field::FieldKind;
+use crate::flock::Flock;
+use crate::integrator::Integrator;
+use crate::noise::NoiseKind;
+use crate::palette::{ColorMode, ColorSpace, PaletteKind, PaletteMapping};
+use crate::respawn::RespawnMode;
+use crate::ribbon::RenderMode;
+use crate::shape::{ParticleShape, SizeMode};
+use crate::stats::Stats;
+use clap::ValueEnum;
+use nannou_egui::egui;
+
+const WARP_STRENGTH: f64 = 2.0; // of warp iterations added on the panel
+
+// Buttons pressed on the panel this frame
+#[derive(Default)]
+pub struct PanelActions {
+    pub reseed: bool,
+    pub reset: bool,
+    pub randomize: bool,
+    pub save_preset: bool,
+}
+
+// Control panel with a slider for every tunable, editing `config` in place
+pub fn panel(ctx: &egui::CtxRef, config: &mut Config, seed: u64) -> PanelActions {
+    let mut actions = PanelActions::default();
+
+    egui::Window::new("Parameters").show(ctx, |ui| {
+        ui.label(format!("Seed: {}", seed));
+        ui.horizontal(|ui| {
+            actions.reseed = ui.button("Reseed").clicked();
+            actions.reset = ui.button("Reset").clicked();
+            actions.randomize = ui.button("Randomize").clicked();
+            actions.save_preset = ui.button("Save preset").clicked();
+        });
+
+        ui.separator();
+        ui.label("Particles");
+        ui.add(egui::Slider::new(&mut config.point_count, 1..=512).text("points"));
+        ui.add(egui::Slider::new(&mut config.point_delta, 0.0..=1.0).text("point delta"));
+        ui.add(egui::Slider::new(&mut config.velocity_multiplier, 0.01..=5.0).text("velocity"));
+        ui.add(egui::Slider::new(&mut config.heading_jitter, 0.0..=90.0).text("heading jitter"));
+        ui.add(egui::Slider::new(&mut config.speed_jitter, 0.0..=1.0).text("speed jitter"));
+        ui.add(egui::Slider::new(&mut config.inertia, 0.0..=0.99).text("inertia"));
+        ui.add(egui::Slider::new(&mut config.max_turn, 0.0..=90.0).text("max turn"));
+        egui::ComboBox::from_label("integrator")
+            .selected_text(name(config.integrator))
+            .show_ui(ui, |ui| {
+                for &integrator in Integrator::value_variants() {
+                    ui.selectable_value(&mut config.integrator, integrator, name(integrator));
+                }
+            });
+        ui.add(egui::Slider::new(&mut config.dt, 0.05..=4.0).text("dt"));
+        ui.add(
+            egui::Slider::new(&mut config.speed, 0.01..=10.0)
+                .logarithmic(true)
+                .text("speed"),
+        );
+        ui.add(egui::Slider::new(&mut config.point_size, 0.5..=20.0).text("point size"));
+        ui.add(egui::Slider::new(&mut config.spawn_rate, 0.0..=5000.0).text("spawn rate"));
+        egui::ComboBox::from_label("edge")
+            .selected_text(name(config.edge_mode))
+            .show_ui(ui, |ui| {
+                for &mode in EdgeMode::value_variants() {
+                    ui.selectable_value(&mut config.edge_mode, mode, name(mode));
+                }
+            });
+        ui.add(egui::Slider::new(&mut config.max_lifetime, 0..=5000).text("max lifetime"));
+        egui::ComboBox::from_label("respawn")
+            .selected_text(name(config.respawn))
+            .show_ui(ui, |ui| {
+                for &mode in RespawnMode::value_variants() {
+                    ui.selectable_value(&mut config.respawn, mode, name(mode));
+                }
+            });
+        ui.add(
+            egui::Slider::new(&mut config.trail_fade, 0.0..=1.0)
+                .logarithmic(true)
+                .text("trail fade"),
+        );
+        ui.horizontal(|ui| {
+            let mut custom = config.fade_color.is_some();
+            ui.checkbox(&mut custom, "fade color");
+            match (custom, &mut config.fade_color) {
+                (true, Some(color)) => {
+                    ui.color_edit_button_srgb(&mut color.0);
+                }
+                (true, fade_color) => *fade_color = Some(config.background),
+                (false, fade_color) => *fade_color = None,
+            }
+        });
+        ui.add(egui::Slider::new(&mut config.mouse_radius, 1.0..=500.0).text("mouse radius"));
+        ui.add(egui::Slider::new(&mut config.mouse_strength, 0.0..=5.0).text("mouse strength"));
+        ui.add(egui::Slider::new(&mut config.separation, 0.0..=5.0).text("separation"));
+        ui.add(egui::Slider::new(&mut config.alignment, 0.0..=5.0).text("alignment"));
+        ui.add(egui::Slider::new(&mut config.cohesion, 0.0..=5.0).text("cohesion"));
+        if Flock::enabled(config) {
+            ui.add(egui::Slider::new(&mut config.flock_radius, 1.0..=100.0).text("flock radius"));
+        }
+        egui::ComboBox::from_label("shape")
+            .selected_text(name(config.particle_shape))
+            .show_ui(ui, |ui| {
+                for &shape in ParticleShape::value_variants() {
+                    ui.selectable_value(&mut config.particle_shape, shape, name(shape));
+                }
+            });
+        ui.add(egui::Slider::new(&mut config.symmetry, 1..=12).text("symmetry"));
+        ui.checkbox(&mut config.mirror, "mirror");
+        egui::ComboBox::from_label("size mode")
+            .selected_text(name(config.size_mode))
+            .show_ui(ui, |ui| {
+                for &mode in SizeMode::value_variants() {
+                    ui.selectable_value(&mut config.size_mode, mode, name(mode));
+                }
+            });
+        if config.size_mode != SizeMode::Constant {
+            ui.add(egui::Slider::new(&mut config.size_min, 0.1..=20.0).text("size min"));
+            ui.add(egui::Slider::new(&mut config.size_max, 0.1..=20.0).text("size max"));
+        }
+        if config.size_mode == SizeMode::Noise {
+            ui.add(
+                egui::Slider::new(&mut config.size_noise_factor, 0.1..=100.0)
+                    .logarithmic(true)
+                    .text("size noise factor"),
+            );
+        }
+        egui::ComboBox::from_label("render mode")
+            .selected_text(name(config.render_mode))
+            .show_ui(ui, |ui| {
+                for &mode in RenderMode::value_variants() {
+                    ui.selectable_value(&mut config.render_mode, mode, name(mode));
+                }
+            });
+        if config.render_mode != RenderMode::Points {
+            ui.add(egui::Slider::new(&mut config.path_length, 2..=128).text("path length"));
+            ui.add(egui::Slider::new(&mut config.path_width, 0.5..=20.0).text("path width"));
+            ui.add(egui::Slider::new(&mut config.path_falloff, 0.0..=4.0).text("path falloff"));
+        }
+
+        ui.separator();
+        ui.label("Heading");
+        egui::ComboBox::from_label("noise")
+            .selected_text(name(config.noise_kind))
+            .show_ui(ui, |ui| {
+                for &kind in NoiseKind::value_variants() {
+                    ui.selectable_value(&mut config.noise_kind, kind, name(kind));
+                }
+            });
+        egui::ComboBox::from_label("field")
+            .selected_text(name(config.field_kind))
+            .show_ui(ui, |ui| {
+                for &kind in FieldKind::value_variants() {
+                    ui.selectable_value(&mut config.field_kind, kind, name(kind));
+                }
+            });
+        ui.add(
+            egui::Slider::new(&mut config.heading_noise_factor, 0.1..=100.0)
+                .logarithmic(true)
+                .text("noise factor"),
+        );
+        ui.add(
+            egui::Slider::new(&mut config.heading_noise_multiplier, 0.0..=5.0)
+                .text("noise multiplier"),
+        );
+        octaves(
+            ui,
+            config.noise_kind,
+            &mut config.octaves,
+            &mut config.lacunarity,
+            &mut config.persistence,
+        );
+        let mut iterations = config.domain_warp.len();
+        ui.add(egui::Slider::new(&mut iterations, 0..=3).text("warp iterations"));
+        config.domain_warp.resize(iterations, WARP_STRENGTH);
+        for (i, strength) in config.domain_warp.iter_mut().enumerate() {
+            ui.add(egui::Slider::new(strength, 0.0..=10.0).text(format!("warp {}", i + 1)));
+        }
+        ui.add(
+            egui::Slider::new(&mut config.evolution_speed, 0.0..=0.1)
+                .logarithmic(true)
+                .text("evolution speed"),
+        );
+        ui.add(egui::Slider::new(&mut config.grid_resolution, 0..=512).text("grid resolution"));
+        if config.guide_text.is_some() || config.guide_path.is_some() {
+            ui.add(egui::Slider::new(&mut config.guide_weight, 0.0..=10.0).text("guide weight"));
+            ui.add(egui::Slider::new(&mut config.guide_band, 0.01..=0.5).text("guide band"));
+        }
+
+        ui.separator();
+        ui.label("Color");
+        ui.add(
+            egui::Slider::new(&mut config.color_noise_factor, 0.1..=100.0)
+                .logarithmic(true)
+                .text("noise factor"),
+        );
+        ui.add(
+            egui::Slider::new(&mut config.color_noise_multiplier, 0.0..=5.0)
+                .text("noise multiplier"),
+        );
+        egui::ComboBox::from_label("color noise")
+            .selected_text(config.color_noise.map_or("same as heading".into(), name))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut config.color_noise, None, "same as heading");
+                for &kind in NoiseKind::value_variants() {
+                    ui.selectable_value(&mut config.color_noise, Some(kind), name(kind));
+                }
+            });
+        octaves(
+            ui,
+            config.color_noise.unwrap_or(config.noise_kind),
+            &mut config.color_octaves,
+            &mut config.color_lacunarity,
+            &mut config.color_persistence,
+        );
+        egui::ComboBox::from_label("color by")
+            .selected_text(name(config.color_mode))
+            .show_ui(ui, |ui| {
+                for &mode in ColorMode::value_variants() {
+                    ui.selectable_value(&mut config.color_mode, mode, name(mode));
+                }
+            });
+        egui::ComboBox::from_label("palette")
+            .selected_text(name(config.palette))
+            .show_ui(ui, |ui| {
+                for &kind in PaletteKind::value_variants() {
+                    ui.selectable_value(&mut config.palette, kind, name(kind));
+                }
+            });
+        egui::ComboBox::from_label("mapping")
+            .selected_text(name(config.palette_mapping))
+            .show_ui(ui, |ui| {
+                for &mapping in PaletteMapping::value_variants() {
+                    ui.selectable_value(&mut config.palette_mapping, mapping, name(mapping));
+                }
+            });
+        egui::ComboBox::from_label("color space")
+            .selected_text(name(config.color_space))
+            .show_ui(ui, |ui| {
+                for &space in ColorSpace::value_variants() {
+                    ui.selectable_value(&mut config.color_space, space, name(space));
+                }
+            });
+        match config.palette {
+            PaletteKind::Hue => {
+                ui.add(egui::Slider::new(&mut config.hue_min, 0.0..=1.0).text("hue min"));
+                ui.add(egui::Slider::new(&mut config.hue_max, 0.0..=1.0).text("hue max"));
+            }
+            PaletteKind::Custom => {
+                // only the endpoints, any stops in between are kept as they are
+                ui.horizontal(|ui| {
+                    let stops = &mut config.palette_stops;
+                    if let Some(start) = stops.first_mut() {
+                        ui.color_edit_button_srgb(&mut start.0);
+                    }
+                    if let (true, Some(end)) = (stops.len() > 1, stops.last_mut()) {
+                        ui.color_edit_button_srgb(&mut end.0);
+                    }
+                    ui.label("start and end");
+                });
+            }
+            _ => {}
+        }
+        ui.horizontal(|ui| {
+            ui.color_edit_button_srgb(&mut config.background.0);
+            ui.label("background");
+        });
+        egui::ComboBox::from_label("background mode")
+            .selected_text(name(config.background_mode))
+            .show_ui(ui, |ui| {
+                for &mode in BackgroundMode::value_variants() {
+                    // an image background needs an image to show
+                    if mode != BackgroundMode::Image || config.background_image.is_some() {
+                        ui.selectable_value(&mut config.background_mode, mode, name(mode));
+                    }
+                }
+            });
+        if config.background_mode == BackgroundMode::Gradient {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgb(&mut config.background_gradient.0);
+                ui.label("gradient end");
+            });
+            ui.add(
+                egui::Slider::new(&mut config.background_angle, 0.0..=360.0).text("gradient angle"),
+            );
+        }
+        ui.add(
+            egui::Slider::new(&mut config.background_wash_strength, 0.0..=1.0)
+                .text("wash strength"),
+        );
+        ui.checkbox(&mut config.additive_hue, "additive hue");
+        ui.add(
+            egui::Slider::new(&mut config.additive_hue_exposure, 0.01..=2.0)
+                .logarithmic(true)
+                .text("exposure"),
+        );
+    });
+
+    actions
+}
+
+// Performance overlay in the top right corner, toggled with F1
+pub fn hud(ctx: &egui::CtxRef, stats: &Stats, particles: usize) {
+    egui::Area::new("stats")
+        .anchor(egui::Align2::RIGHT_TOP, [-8.0, 8.0])
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.monospace(format!("FPS:       {:>8.1}", stats.fps()));
+                ui.monospace(format!("Update:    {:>6.2}ms", stats.update_ms()));
+                ui.monospace(format!("Draw:      {:>6.2}ms", stats.draw_ms()));
+                ui.monospace(format!("Particles: {:>8}", particles));
+            });
+        });
+}
+
+// Sliders over the octaves of a noise, showing the defaults of its kind until they are moved
+fn octaves(
+    ui: &mut egui::Ui,
+    kind: NoiseKind,
+    octaves: &mut Option<usize>,
+    lacunarity: &mut Option<f64>,
+    persistence: &mut Option<f64>,
+) {
+    let (default_octaves, default_lacunarity, default_persistence) = kind.octave_defaults();
+    optional(ui, octaves, default_octaves, 1..=8, "octaves");
+    optional(ui, lacunarity, default_lacunarity, 1.0..=4.0, "lacunarity");
+    optional(
+        ui,
+        persistence,
+        default_persistence,
+        0.0..=1.0,
+        "persistence",
+    );
+}
+
+fn optional<T: egui::emath::Numeric>(
+    ui: &mut egui::Ui,
+    value: &mut Option<T>,
+    default: T,
+    range: std::ops::RangeInclusive<T>,
+    text: &str,
+) {
+    let mut current = value.unwrap_or(default);
+    if ui
+        .add(egui::Slider::new(&mut current, range).text(text))
+        .changed()
+    {
+        *value = Some(current);
+    }
+}
+
+// Name of a value on the command line
+pub fn name<T: ValueEnum>(value: T) -> String {
+    value.to_possible_value().unwrap().get_name().to_string()
+}
@@ -0,0 +1,276 @@
+use crate::config::Config;
+use crate::field::VectorField;
+use crate::Vector2;
+use rusttype::{point, Scale, Segment};
+
+const FIT_SIZE: f64 = 1.6; // centered units the outline is fitted into, along its longer side
+const CURVE_SEGMENTS: usize = 12; // straight segments each curve is flattened into
+const TEXT_SCALE: f32 = 100.0; // pixel height glyphs are laid out at before fitting
+
+// Outline of a text or a path, flattened into straight segments in the centered units
+// expressions use (the shorter side of the canvas spans -1 to 1, y pointing up)
+pub struct Outline {
+    segments: Vec<([f64; 2], [f64; 2])>,
+}
+
+impl Outline {
+    // Outlines of every glyph of `text`, set in the font text is drawn with
+    pub fn text(text: &str) -> Self {
+        let font = nannou::text::font::default_notosans();
+        let glyphs = font.layout(text, Scale::uniform(TEXT_SCALE), point(0.0, 0.0));
+
+        let mut polylines = Vec::new();
+        for contour in glyphs.filter_map(|glyph| glyph.shape()).flatten() {
+            let mut polyline = Vec::new();
+            for segment in contour.segments {
+                match segment {
+                    Segment::Line(line) => {
+                        let [a, b] = line.p.map(|p| [p.x as f64, -p.y as f64]);
+                        polyline.extend([a, b]);
+                    }
+                    Segment::Curve(curve) => {
+                        let [a, b, c] = curve.p.map(|p| [p.x as f64, -p.y as f64]);
+                        if polyline.is_empty() {
+                            polyline.push(a);
+                        }
+                        polyline.extend(quadratic(a, b, c));
+                    }
+                }
+            }
+            polylines.push(polyline);
+        }
+
+        Outline::fit(polylines)
+    }
+
+    // Outline of SVG path data, supporting the `M`, `L`, `H`, `V`, `C`, `Q` and `Z` commands in
+    // absolute and relative form
+    pub fn svg_path(data: &str) -> Result<Self, String> {
+        let mut tokens = Tokens::new(data);
+        let mut polylines: Vec<Vec<[f64; 2]>> = Vec::new();
+        let (mut current, mut start) = ([0.0, 0.0], [0.0, 0.0]);
+        let mut command = None;
+
+        while let Some(token) = tokens.peek() {
+            if token.is_ascii_alphabetic() {
+                command = Some(token);
+                tokens.skip();
+            }
+            let letter = command.ok_or("path data must start with a command")?;
+            let relative = letter.is_ascii_lowercase();
+            let offset = |[x, y]: [f64; 2], current: [f64; 2]| match relative {
+                true => [current[0] + x, current[1] + y],
+                false => [x, y],
+            };
+
+            match letter.to_ascii_uppercase() {
+                'M' => {
+                    current = offset(tokens.point()?, current);
+                    start = current;
+                    polylines.push(vec![current]);
+
+                    // further points without a command are lines
+                    command = Some(if relative { 'l' } else { 'L' });
+                    continue;
+                }
+                'L' => current = offset(tokens.point()?, current),
+                'H' => {
+                    let x = tokens.number()?;
+                    current[0] = if relative { current[0] + x } else { x };
+                }
+                'V' => {
+                    let y = tokens.number()?;
+                    current[1] = if relative { current[1] + y } else { y };
+                }
+                'C' => {
+                    let (b, c) = (
+                        offset(tokens.point()?, current),
+                        offset(tokens.point()?, current),
+                    );
+                    let d = offset(tokens.point()?, current);
+                    polylines
+                        .last_mut()
+                        .ok_or("`C` before `M`")?
+                        .extend(cubic(current, b, c, d));
+                    current = d;
+                    continue;
+                }
+                'Q' => {
+                    let b = offset(tokens.point()?, current);
+                    let c = offset(tokens.point()?, current);
+                    polylines
+                        .last_mut()
+                        .ok_or("`Q` before `M`")?
+                        .extend(quadratic(current, b, c));
+                    current = c;
+                    continue;
+                }
+                'Z' => current = start,
+                other => return Err(format!("unsupported path command `{}`", other)),
+            }
+
+            match polylines.last_mut() {
+                Some(polyline) => polyline.push(current),
+                None => return Err("path data must start with `M`".to_string()),
+            }
+        }
+
+        // SVG's y points down
+        for point in polylines.iter_mut().flatten() {
+            point[1] = -point[1];
+        }
+        Ok(Outline::fit(polylines))
+    }
+
+    // Scale and center polylines so their bounding box fits into `FIT_SIZE`
+    fn fit(polylines: Vec<Vec<[f64; 2]>>) -> Self {
+        let points = || polylines.iter().flatten();
+        let min = |axis: usize| points().map(|p| p[axis]).fold(f64::INFINITY, f64::min);
+        let max = |axis: usize| points().map(|p| p[axis]).fold(f64::NEG_INFINITY, f64::max);
+        let (min_x, min_y, max_x, max_y) = (min(0), min(1), max(0), max(1));
+
+        let scale = FIT_SIZE / (max_x - min_x).max(max_y - min_y).max(f64::EPSILON);
+        let center = [(min_x + max_x) / 2.0, (min_y + max_y) / 2.0];
+        let map = |[x, y]: [f64; 2]| [(x - center[0]) * scale, (y - center[1]) * scale];
+
+        let segments = polylines
+            .iter()
+            .flat_map(|polyline| polyline.windows(2).map(|pair| (map(pair[0]), map(pair[1]))))
+            .collect();
+        Outline { segments }
+    }
+
+    // Distance to the nearest point of the outline, and that point
+    fn nearest(&self, [x, y]: [f64; 2]) -> (f64, [f64; 2]) {
+        self.segments
+            .iter()
+            .map(|&([ax, ay], [bx, by])| {
+                let (ex, ey) = (bx - ax, by - ay);
+                let length = ex * ex + ey * ey;
+                let t = match length > 0.0 {
+                    true => (((x - ax) * ex + (y - ay) * ey) / length).clamp(0.0, 1.0),
+                    false => 0.0,
+                };
+                let nearest = [ax + ex * t, ay + ey * t];
+                ((x - nearest[0]).hypot(y - nearest[1]), nearest)
+            })
+            .fold(
+                (f64::INFINITY, [x, y]),
+                |a, b| if b.0 < a.0 { b } else { a },
+            )
+    }
+}
+
+fn quadratic(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> impl Iterator<Item = [f64; 2]> {
+    (1..=CURVE_SEGMENTS).map(move |i| {
+        let t = i as f64 / CURVE_SEGMENTS as f64;
+        let s = 1.0 - t;
+        [0, 1].map(|axis| s * s * a[axis] + 2.0 * s * t * b[axis] + t * t * c[axis])
+    })
+}
+
+fn cubic(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> impl Iterator<Item = [f64; 2]> {
+    (1..=CURVE_SEGMENTS).map(move |i| {
+        let t = i as f64 / CURVE_SEGMENTS as f64;
+        let s = 1.0 - t;
+        [0, 1].map(|axis| {
+            s * s * s * a[axis]
+                + 3.0 * s * s * t * b[axis]
+                + 3.0 * s * t * t * c[axis]
+                + t * t * t * d[axis]
+        })
+    })
+}
+
+// Numbers and command letters of path data, separated by whitespace or commas
+struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(data: &'a str) -> Self {
+        Tokens { rest: data }
+    }
+
+    fn trim(&mut self) {
+        self.rest = self
+            .rest
+            .trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.trim();
+        self.rest.chars().next()
+    }
+
+    fn skip(&mut self) {
+        self.rest = &self.rest[1..];
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.trim();
+        let end = self
+            .rest
+            .char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && c == '-')))
+            .map_or(self.rest.len(), |(i, _)| i);
+        let (number, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        number
+            .parse()
+            .map_err(|_| format!("expected a number at `{}`", number))
+    }
+
+    fn point(&mut self) -> Result<[f64; 2], String> {
+        Ok([self.number()?, self.number()?])
+    }
+}
+
+// Field bent towards an outline near it, so particles trace it and the flow visibly forms the
+// letters or the shape
+pub struct GuidedField {
+    pub field: Box<dyn VectorField>,
+    pub outline: Outline,
+}
+
+impl VectorField for GuidedField {
+    fn vector(&self, config: &Config, pos: Vector2, extent: Vector2, steps: u64) -> Vector2 {
+        let vector = self.field.vector(config, pos, extent, steps);
+        let length = vector.length();
+        let vector = match length > 0.0 {
+            true => vector / length,
+            false => vector,
+        };
+
+        // centered and scaled so the shorter side of the canvas spans -1 to 1
+        let scale = extent.x.min(extent.y);
+        let (x, y) = (
+            (pos.x - extent.x / 2.0) * 2.0 / scale,
+            (pos.y - extent.y / 2.0) * 2.0 / scale,
+        );
+
+        let (distance, nearest) = self.outline.nearest([x, y]);
+        let band = config.guide_band;
+        if distance >= band || distance == 0.0 {
+            return vector;
+        }
+
+        // Slide along the outline in whichever direction the field already goes, pulled onto it
+        // harder the further away, fading out towards the edge of the band
+        let away = Vector2 {
+            x: (x - nearest[0]) / distance,
+            y: (y - nearest[1]) / distance,
+        };
+        let mut along = Vector2 {
+            x: -away.y,
+            y: away.x,
+        };
+        if along.dot(vector) < 0.0 {
+            along = along * -1.0;
+        }
+        let pull = distance / band;
+        let guide = along * (1.0 - pull) + away * -pull;
+
+        vector + guide * (config.guide_weight * (1.0 - pull))
+    }
+}
@@ -0,0 +1,232 @@
+use crate::background::Background;
+use crate::config::Config;
+use crate::metadata::Metadata;
+use crate::particles::Simulation;
+use crate::render::{
+    accumulation, draw_simulation, draw_streamlines, splat_accumulation, upload_accumulation,
+};
+use crate::replay::{self, Command, FrameInput, Playback, Played, Replay};
+use crate::streamline;
+use crate::target::{downscale, Target};
+use crate::wash;
+use nannou::image::{self, RgbaImage};
+use nannou::prelude::*;
+use std::io::Write;
+use std::path::Path;
+
+pub const STEP_DT: f64 = 1.0 / 60.0; // simulated seconds per step, for spawning
+
+// Render the sketch to an offscreen texture at an arbitrary resolution without opening a window,
+// running `render_steps` steps and saving the result to `path`
+pub fn render(config: &Config, path: &Path) {
+    // A replayed session is rendered with the parameters it was recorded with
+    let replay = config.replay_input.as_deref().map(Replay::load);
+    let config = &match &replay {
+        Some(replay) => replay.config(config),
+        None => config.clone(),
+    };
+    let replay = replay.as_ref();
+
+    let seed = crate::seed::resolve(config);
+    let path = crate::seed::expand(path, seed);
+    let metadata = Metadata::new(config, seed);
+    if config.sidecar {
+        metadata.save_sidecar(&path);
+    }
+
+    if let Some(tile) = config.render_tile {
+        render_tiled(config, seed, &path, tile, &metadata, replay);
+        log::info!("Saved {}", path.display());
+        return;
+    }
+
+    let (device, queue) = device();
+    let size = [config.render_width, config.render_height];
+    let image = render_region(config, seed, replay, &device, &queue, size, vec2(0.0, 0.0));
+    metadata.save_png(&image, &path);
+    log::info!("Saved {}", path.display());
+}
+
+// Render one tile at a time and stream the rows of tiles into the PNG as they complete, so the
+// image can be larger than a texture. Every tile replays the same simulation from the seed.
+fn render_tiled(
+    config: &Config,
+    seed: u64,
+    path: &Path,
+    tile: u32,
+    metadata: &Metadata,
+    replay: Option<&Replay>,
+) {
+    assert!(
+        !config.additive_hue,
+        "The additive buffer spans the whole image and can't be rendered in tiles"
+    );
+
+    let (width, height) = (config.render_width, config.render_height);
+    let tile = tile.max(1);
+    let (device, queue) = device();
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory).unwrap();
+    }
+    let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    metadata.write_chunks(&mut writer);
+    let mut writer = writer.into_stream_writer();
+
+    let (columns, rows) = (width.div_ceil(tile), height.div_ceil(tile));
+    for row in 0..rows {
+        let y = row * tile;
+        let tile_height = tile.min(height - y);
+        let mut strip = RgbaImage::new(width, tile_height);
+
+        for column in 0..columns {
+            let x = column * tile;
+            let tile_width = tile.min(width - x);
+            log::info!("Tile {}/{}", row * columns + column + 1, rows * columns);
+
+            // center of the tile relative to the center of the image, y pointing up
+            let center = vec2(
+                x as f32 + tile_width as f32 / 2.0 - width as f32 / 2.0,
+                height as f32 / 2.0 - y as f32 - tile_height as f32 / 2.0,
+            );
+            let size = [tile_width, tile_height];
+            let tile = render_region(config, seed, replay, &device, &queue, size, center);
+            image::imageops::replace(&mut strip, &tile, x, 0);
+        }
+
+        writer.write_all(strip.as_raw()).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+// Run the simulation over the whole `render_width` by `render_height` image, drawing the `size`
+// pixels of it around `center`. A replayed session is run frame by frame, with the mouse scaled
+// from its window to the image. With `supersample`, the region is drawn that many times larger
+// and averaged back down.
+fn render_region(
+    config: &Config,
+    seed: u64,
+    replay: Option<&Replay>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    size: [u32; 2],
+    center: Vec2,
+) -> RgbaImage {
+    let (width, height) = (config.render_width, config.render_height);
+    let src = (device, queue);
+    let simulation = Simulation::new(config, seed, width as f64 / 2.0, height as f64 / 2.0);
+
+    let factor = config.supersample.max(1);
+    let mut target = Target::new(device, size.map(|side| side * factor), config.msaa.max(1));
+    let finish = |target: &Target| downscale(&read(device, queue, &target.texture), factor);
+
+    let canvas = Draw::new();
+    let draw = canvas.scale(factor as f32).translate(-center.extend(0.0)); // offset to the region
+    let background = Background::new(src, config);
+    background.draw(&draw, config, simulation.size());
+
+    let wash = match config.background_wash_strength > 0.0 {
+        true => Some(wash::texture(src, &simulation, config)),
+        false => None,
+    };
+    if let Some(wash) = &wash {
+        draw.texture(wash).wh(simulation.size());
+    }
+
+    // Streamlines are traced to completion and drawn in one go instead of step by step
+    if config.streamlines {
+        let streamlines = streamline::trace(config, &simulation);
+        log::info!("Traced {} streamlines", streamlines.len());
+        draw_streamlines(&draw, config, &simulation, &streamlines);
+        target.render(device, queue, &canvas);
+        return finish(&target);
+    }
+
+    let mut accumulation = accumulation(device, config, simulation.x_end, simulation.y_end);
+
+    let steps = [FrameInput {
+        steps: config.render_steps as usize,
+        dt: STEP_DT,
+        ..FrameInput::default()
+    }];
+    let frames = replay.map_or(&steps[..], Replay::frames);
+    let total: usize = frames.iter().map(|frame| frame.steps).sum();
+    let mut step = 0;
+
+    let window = replay.map(|replay| replay.size);
+    let mut playback = Playback::new(simulation, config, window, |played, simulation, current| {
+        match (played, &mut accumulation) {
+            (Played::Command(Command::Clear | Command::Reset), None) => {
+                background.draw(&draw, current, simulation.size());
+            }
+            (Played::Command(_), _) => {}
+            (Played::Step, Some((buffer, _))) => splat_accumulation(buffer, current, simulation),
+            (Played::Step, None) => {
+                draw_simulation(&draw, current, simulation, None, true);
+                target.render(device, queue, &canvas);
+            }
+        }
+
+        if played == Played::Step {
+            step += 1;
+            crate::logging::progress("Step", step, total);
+        }
+    });
+    for frame in frames {
+        replay::play(&mut playback, frame);
+    }
+    let simulation = playback.finish();
+
+    // The additive buffer is only tone mapped and drawn once, at the end
+    if let Some((buffer, buffer_texture)) = &accumulation {
+        upload_accumulation(src, buffer, buffer_texture, config.additive_hue_exposure);
+        draw_simulation(&draw, config, &simulation, Some(buffer_texture), true);
+    }
+    target.render(device, queue, &canvas);
+
+    finish(&target)
+}
+
+pub fn device() -> (wgpu::Device, wgpu::Queue) {
+    let instance = wgpu::Instance::new(wgpu::Backends::PRIMARY);
+    let adapter = futures::executor::block_on(
+        instance.request_adapter(&wgpu::RequestAdapterOptions::default()),
+    )
+    .expect("no GPU adapter available for headless rendering");
+
+    futures::executor::block_on(adapter.request_device(&wgpu::default_device_descriptor(), None))
+        .unwrap()
+}
+
+// Read the texture back from the GPU and write it to disk
+pub fn save(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, path: &Path) {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory).unwrap();
+    }
+    read(device, queue, texture).save(path).unwrap();
+}
+
+// Read the texture back from the GPU as 8-bit sRGB
+pub fn read(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> RgbaImage {
+    let descriptor = wgpu::CommandEncoderDescriptor {
+        label: Some("capture"),
+    };
+    let mut encoder = device.create_command_encoder(&descriptor);
+    let capturer = wgpu::TextureCapturer::default();
+    let snapshot = capturer.capture(device, &mut encoder, texture);
+    queue.submit(Some(encoder.finish()));
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    snapshot
+        .read(move |result| {
+            let image = result.expect("failed to map texture memory").to_owned();
+            sender.send(image).unwrap();
+        })
+        .unwrap();
+    capturer.await_active_snapshots(device).unwrap();
+    receiver.recv().unwrap()
+}
@@ -0,0 +1,58 @@
+use crate::particles::Flow;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// State of a simulation after a step, everything stepping changes
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub flow_field: Vec<Flow>,
+    pub rng: ChaCha12Rng,
+    pub spawn_accumulator: f64,
+    pub steps: u64,
+    #[serde(default)]
+    pub spawned: u64,
+}
+
+// Ring buffer of the most recent steps, one snapshot per simulation each (the main one first,
+// then every species), so the sketch can step back through them
+pub struct History {
+    capacity: usize,
+    states: VecDeque<Vec<Snapshot>>, // oldest first, the last is the current state
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        History {
+            capacity,
+            states: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, snapshots: Vec<Snapshot>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(snapshots);
+    }
+
+    // Drop the current state, returning whether an earlier one remains to go back to
+    pub fn rewind(&mut self) -> bool {
+        if self.states.len() < 2 {
+            return false;
+        }
+        self.states.pop_back();
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.states.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[Snapshot]> {
+        self.states.iter().map(Vec::as_slice)
+    }
+}
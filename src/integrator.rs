@@ -0,0 +1,29 @@
+use crate::Vector2;
+use serde::{Deserialize, Serialize};
+
+// How particles are advanced along the velocity field over a step
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Integrator {
+    Euler,    // straight along the velocity at the start of the step, cutting corners at speed
+    Midpoint, // along the velocity half way through the step
+    Rk4,      // fourth order Runge-Kutta, weighing four samples across the step
+}
+
+impl Integrator {
+    // Velocity a particle at `pos` moves with over a step of length `h`, sampling `velocity`
+    // once, twice or four times
+    pub fn velocity(self, pos: Vector2, h: f64, velocity: impl Fn(Vector2) -> Vector2) -> Vector2 {
+        match self {
+            Integrator::Euler => velocity(pos),
+            Integrator::Midpoint => velocity(pos + velocity(pos) * (h / 2.0)),
+            Integrator::Rk4 => {
+                let k1 = velocity(pos);
+                let k2 = velocity(pos + k1 * (h / 2.0));
+                let k3 = velocity(pos + k2 * (h / 2.0));
+                let k4 = velocity(pos + k3 * h);
+                (k1 + k2 * 2.0 + k3 * 2.0 + k4) / 6.0
+            }
+        }
+    }
+}
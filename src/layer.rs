@@ -0,0 +1,35 @@
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// How a layer is composited onto the canvas beneath it
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendMode {
+    Add,      // sum the colors, brightening towards white
+    Multiply, // multiply the colors, darkening like stacked filters
+    Screen,   // invert, multiply and invert again, brightening without clipping
+}
+
+impl BlendMode {
+    pub fn component(self) -> wgpu::BlendComponent {
+        let (src_factor, dst_factor) = match self {
+            BlendMode::Add => (wgpu::BlendFactor::One, wgpu::BlendFactor::One),
+            BlendMode::Multiply => (wgpu::BlendFactor::Dst, wgpu::BlendFactor::Zero),
+            BlendMode::Screen => (wgpu::BlendFactor::One, wgpu::BlendFactor::OneMinusSrc),
+        };
+
+        wgpu::BlendComponent {
+            src_factor,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        }
+    }
+
+    // Color that leaves the canvas beneath untouched, which the layer starts from and fades to
+    pub fn neutral(self) -> LinSrgb {
+        match self {
+            BlendMode::Add | BlendMode::Screen => lin_srgb(0.0, 0.0, 0.0),
+            BlendMode::Multiply => lin_srgb(1.0, 1.0, 1.0),
+        }
+    }
+}
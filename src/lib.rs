@@ -0,0 +1,80 @@
+#![allow(ambiguous_glob_imports)] // nannou's noise crate glob-exports two `Perlin` structs
+
+// Generative sketches sharing the config, the fields, the particles and the rendering. Every sketch
+// implements `Sketch` and is picked by name on the command line, the flow field by default.
+
+pub mod accumulation;
+pub mod animation;
+pub mod audio;
+pub mod background;
+pub mod bench;
+pub mod cache;
+pub mod camera;
+pub mod canvas;
+pub mod collision;
+pub mod color_source;
+pub mod config;
+pub mod coverage;
+pub mod density;
+pub mod edge;
+pub mod emitter;
+pub mod export;
+pub mod expression;
+pub mod field;
+pub mod field_file;
+pub mod field_view;
+pub mod flock;
+pub mod flow_field;
+pub mod fluid;
+pub mod gallery;
+pub mod gpu;
+pub mod grid;
+pub mod gui;
+pub mod guide;
+pub mod headless;
+pub mod history;
+pub mod integrator;
+pub mod layer;
+pub mod logging;
+pub mod metadata;
+pub mod midi;
+pub mod modulation;
+pub mod ndi;
+pub mod noise;
+pub mod obstacle;
+pub mod osc;
+pub mod output;
+pub mod pacing;
+pub mod palette;
+pub mod particles;
+pub mod physarum;
+pub mod plot;
+pub mod radial;
+pub mod randomize;
+pub mod reaction;
+pub mod render;
+pub mod replay;
+pub mod respawn;
+pub mod ribbon;
+pub mod script;
+pub mod search;
+pub mod seed;
+pub mod session;
+pub mod shape;
+pub mod sketch;
+pub mod species;
+pub mod stack;
+pub mod state;
+pub mod stats;
+pub mod steering;
+pub mod stream;
+pub mod streamline;
+pub mod symmetry;
+pub mod target;
+pub mod timeline;
+pub mod vector;
+pub mod volume;
+pub mod wash;
+pub mod web;
+
+pub use vector::{Vector2, Vector3};
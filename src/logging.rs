@@ -0,0 +1,97 @@
+use crate::config::Config;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static LOGGER: Logger = Logger {
+    json: AtomicBool::new(false),
+};
+
+// Writes log records to stderr one line each, as plain text or as JSON objects with `log_json`
+struct Logger {
+    json: AtomicBool,
+}
+
+// Log at the level `quiet` and `verbose` ask for. Only the first call installs the logger, later
+// ones just change the level and format.
+pub fn init(config: &Config) {
+    LOGGER.json.store(config.log_json, Ordering::Relaxed);
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(match (config.quiet, config.verbose) {
+        (true, _) => LevelFilter::Warn,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    });
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        match (self.json.load(Ordering::Relaxed), record.level()) {
+            (true, level) => write(&serde_json::json!({
+                "level": name(level),
+                "target": record.target(),
+                "message": message,
+            })),
+            (false, Level::Info) => write(&message),
+            (false, level) => write(&format!("{}: {}", name(level), message)),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// Timings of a frame in milliseconds, logged at the debug level
+pub fn frame(step: u64, particles: usize, update: f64, draw: f64, frame: f64) {
+    if !log::log_enabled!(target: "frame", Level::Debug) {
+        return;
+    }
+    match LOGGER.json.load(Ordering::Relaxed) {
+        true => write(&serde_json::json!({
+            "level": name(Level::Debug),
+            "target": "frame",
+            "step": step,
+            "particles": particles,
+            "update_ms": update,
+            "draw_ms": draw,
+            "frame_ms": frame,
+        })),
+        false => log::debug!(
+            target: "frame",
+            "step {}, {} particles, update {:.2} ms, draw {:.2} ms, frame {:.2} ms",
+            step,
+            particles,
+            update,
+            draw,
+            frame
+        ),
+    }
+}
+
+// Progress `done` of `total`, logged every tenth of the way and at the trace level in between,
+// instead of rewriting a line in place that other output would break up
+pub fn progress(label: &str, done: usize, total: usize) {
+    let tenth = |count: usize| count * 10 / total.max(1);
+    let level = match done >= total || tenth(done) != tenth(done.saturating_sub(1)) {
+        true => Level::Info,
+        false => Level::Trace,
+    };
+    log::log!(level, "{} {}/{}", label, done, total);
+}
+
+fn name(level: Level) -> String {
+    level.as_str().to_lowercase()
+}
+
+// Whole lines at once, so records from several threads don't interleave
+fn write(line: &dyn std::fmt::Display) {
+    let _ = writeln!(std::io::stderr().lock(), "{}", line);
+}
@@ -0,0 +1,83 @@
+use crate::config::Config;
+use nannou::image::RgbaImage;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+pub const GIT_COMMIT: &str = env!("GIT_COMMIT"); // set by the build script
+
+// Everything needed to reproduce an output: the version and commit it was made with, and the
+// full config with the seed it resolved to
+#[derive(Serialize)]
+pub struct Metadata {
+    pub software: String,
+    pub commit: &'static str,
+    pub seed: u64,
+    pub config: Config,
+}
+
+impl Metadata {
+    pub fn new(config: &Config, seed: u64) -> Self {
+        Metadata {
+            software: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            commit: GIT_COMMIT,
+            seed,
+            config: Config {
+                seed,
+                ..config.clone()
+            },
+        }
+    }
+
+    // Keyword and text of every PNG `tEXt` chunk, the config as JSON
+    fn text(&self) -> [(&str, String); 4] {
+        [
+            ("Software", self.software.clone()),
+            ("Commit", self.commit.to_string()),
+            ("Seed", self.seed.to_string()),
+            ("Config", serde_json::to_string(&self.config).unwrap()),
+        ]
+    }
+
+    // Write the `tEXt` chunks, before the image data
+    pub fn write_chunks<W: std::io::Write>(&self, writer: &mut png::Writer<W>) {
+        for (keyword, text) in self.text() {
+            // the text is Latin-1, escaping everything beyond like JSON does
+            let mut data: Vec<u8> = keyword.bytes().chain([0]).collect();
+            for c in text.chars() {
+                match c as u32 {
+                    0..=0xff => data.push(c as u8),
+                    _ => {
+                        for unit in c.encode_utf16(&mut [0; 2]) {
+                            data.extend(format!("\\u{:04x}", unit).bytes());
+                        }
+                    }
+                }
+            }
+            writer.write_chunk(*b"tEXt", &data).unwrap();
+        }
+    }
+
+    // Save an image as PNG with the metadata embedded
+    pub fn save_png(&self, image: &RgbaImage, path: &Path) {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).unwrap();
+        }
+        let file = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+        let mut encoder = png::Encoder::new(file, image.width(), image.height());
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        self.write_chunks(&mut writer);
+        writer.write_image_data(image.as_raw()).unwrap();
+    }
+
+    // Save the metadata as JSON next to an output, for outputs it can't be embedded in
+    pub fn save_sidecar(&self, path: &Path) -> PathBuf {
+        let path = path.with_extension("json");
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).unwrap();
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self).unwrap()).unwrap();
+        path
+    }
+}
@@ -0,0 +1,82 @@
+use crate::config::Config;
+use crate::modulation::Binding;
+use serde::{Deserialize, Serialize};
+
+// Control change knob bound to a parameter, given as `[[midi]]` tables in the config file
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct MidiBinding {
+    pub cc: u8,
+    pub channel: Option<u8>, // 1 to 16, any channel if unset
+    #[serde(flatten)]
+    pub binding: Binding,
+}
+
+// Control changes received from a MIDI input port on midir's thread, applied once per frame
+#[cfg(feature = "midi")]
+pub struct Midi {
+    _connection: midir::MidiInputConnection<()>, // closes the port when dropped
+    receiver: std::sync::mpsc::Receiver<(u8, u8, u8)>, // channel from 1, controller and value
+}
+
+#[cfg(feature = "midi")]
+impl Midi {
+    // Listen to the first input port whose name contains `name`
+    pub fn connect(name: &str) -> Self {
+        let input = midir::MidiInput::new(env!("CARGO_PKG_NAME")).unwrap();
+        let ports = input.ports();
+        let port = ports
+            .iter()
+            .find(|port| input.port_name(port).is_ok_and(|port| port.contains(name)))
+            .unwrap_or_else(|| panic!("No MIDI input port matches `{}`", name));
+        log::info!("Listening to MIDI port {}", input.port_name(port).unwrap());
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let connection = input
+            .connect(
+                port,
+                "controls",
+                move |_, message, _| {
+                    // control change status bytes are 0xB0 plus the channel
+                    if let &[status, controller, value] = message {
+                        if status & 0xf0 == 0xb0 {
+                            let _ = sender.send(((status & 0x0f) + 1, controller, value));
+                        }
+                    }
+                },
+                (),
+            )
+            .unwrap_or_else(|err| panic!("Could not connect to MIDI port: {}", err));
+
+        Midi {
+            _connection: connection,
+            receiver,
+        }
+    }
+
+    // Set the parameters bound to every control change received since the last call
+    pub fn apply(&self, config: &mut Config) {
+        for (channel, controller, value) in self.receiver.try_iter() {
+            let bindings = config.midi.iter().filter(|midi| {
+                midi.cc == controller && midi.channel.is_none_or(|bound| bound == channel)
+            });
+            let bindings: Vec<Binding> = bindings.map(|midi| midi.binding.clone()).collect();
+            for binding in bindings {
+                binding.apply(config, value as f64 / 127.0);
+            }
+        }
+    }
+}
+
+// Stand-in without the `midi` feature, which needs the ALSA development files on Linux
+#[cfg(not(feature = "midi"))]
+pub struct Midi;
+
+#[cfg(not(feature = "midi"))]
+impl Midi {
+    pub fn connect(_name: &str) -> Self {
+        log::warn!("Built without MIDI support, rebuild with `--features midi` to use --midi-port");
+        Midi
+    }
+
+    pub fn apply(&self, _config: &mut Config) {}
+}
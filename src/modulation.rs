@@ -0,0 +1,79 @@
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+
+// Parameter external controllers can drive
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Parameter {
+    NoiseFactor,     // `heading_noise_factor`
+    NoiseMultiplier, // `heading_noise_multiplier`
+    Velocity,        // `velocity_multiplier`
+    HueOffset,       // `hue_min`, moving `hue_max` along with it
+    PointSize,
+    TrailFade,
+    EvolutionSpeed,
+    ColorNoiseFactor,
+}
+
+impl Parameter {
+    pub fn get(self, config: &Config) -> f64 {
+        match self {
+            Parameter::NoiseFactor => config.heading_noise_factor,
+            Parameter::NoiseMultiplier => config.heading_noise_multiplier,
+            Parameter::Velocity => config.velocity_multiplier,
+            Parameter::HueOffset => config.hue_min,
+            Parameter::PointSize => config.point_size,
+            Parameter::TrailFade => config.trail_fade as f64,
+            Parameter::EvolutionSpeed => config.evolution_speed,
+            Parameter::ColorNoiseFactor => config.color_noise_factor,
+        }
+    }
+
+    // Bounds giving sensible pieces, which randomizing picks from
+    pub fn range(self) -> (f64, f64) {
+        match self {
+            Parameter::NoiseFactor => (1.0, 40.0),
+            Parameter::NoiseMultiplier => (0.2, 3.0),
+            Parameter::Velocity => (0.05, 2.0),
+            Parameter::HueOffset => (0.0, 360.0),
+            Parameter::PointSize => (0.5, 4.0),
+            Parameter::TrailFade => (0.0, 0.05),
+            Parameter::EvolutionSpeed => (0.0, 0.01),
+            Parameter::ColorNoiseFactor => (0.2, 10.0),
+        }
+    }
+
+    pub fn set(self, config: &mut Config, value: f64) {
+        match self {
+            Parameter::NoiseFactor => config.heading_noise_factor = value,
+            Parameter::NoiseMultiplier => config.heading_noise_multiplier = value,
+            Parameter::Velocity => config.velocity_multiplier = value,
+            Parameter::HueOffset => {
+                let span = config.hue_max - config.hue_min;
+                config.hue_min = value;
+                config.hue_max = value + span;
+            }
+            Parameter::PointSize => config.point_size = value,
+            Parameter::TrailFade => config.trail_fade = value as f32,
+            Parameter::EvolutionSpeed => config.evolution_speed = value,
+            Parameter::ColorNoiseFactor => config.color_noise_factor = value,
+        }
+    }
+}
+
+// A controller input bound to a parameter, setting it to `min` at the lowest input and to `max`
+// at the highest
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Binding {
+    pub parameter: Parameter,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Binding {
+    // Set the parameter from an input normalized to 0 to 1
+    pub fn apply(&self, config: &mut Config, input: f64) {
+        let value = self.min + (self.max - self.min) * input.clamp(0.0, 1.0);
+        self.parameter.set(config, value);
+    }
+}
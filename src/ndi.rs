@@ -0,0 +1,169 @@
+use nannou::prelude::*;
+
+// Live output of the canvas as an NDI source, which OBS, Resolume and TouchDesigner pick up over
+// the network like a camera. The NDI runtime is loaded when the sender is created rather than
+// linked, so the crate builds without the SDK and only needs the runtime installed to send.
+#[cfg(feature = "ndi")]
+pub struct Ndi {
+    _library: libloading::Library, // kept open for the functions below
+    sender: *mut std::ffi::c_void,
+    send_video: unsafe extern "C" fn(*mut std::ffi::c_void, *const VideoFrame),
+    send_destroy: unsafe extern "C" fn(*mut std::ffi::c_void),
+    destroy: unsafe extern "C" fn(),
+    frame_rate: [i32; 2], // numerator and denominator, advertised to receivers
+}
+
+// `NDIlib_send_create_t`
+#[cfg(feature = "ndi")]
+#[repr(C)]
+struct SendCreate {
+    ndi_name: *const std::ffi::c_char,
+    groups: *const std::ffi::c_char,
+    clock_video: bool,
+    clock_audio: bool,
+}
+
+// `NDIlib_video_frame_v2_t`
+#[cfg(feature = "ndi")]
+#[repr(C)]
+struct VideoFrame {
+    xres: i32,
+    yres: i32,
+    four_cc: u32,
+    frame_rate_n: i32,
+    frame_rate_d: i32,
+    picture_aspect_ratio: f32,
+    frame_format_type: u32,
+    timecode: i64,
+    data: *const u8,
+    line_stride_in_bytes: i32,
+    metadata: *const std::ffi::c_char,
+    timestamp: i64,
+}
+
+#[cfg(feature = "ndi")]
+const FOURCC_RGBA: u32 = u32::from_le_bytes(*b"RGBA");
+#[cfg(feature = "ndi")]
+const FRAME_FORMAT_PROGRESSIVE: u32 = 1;
+#[cfg(feature = "ndi")]
+const TIMECODE_SYNTHESIZE: i64 = i64::MAX; // lets the runtime time the frames
+
+#[cfg(feature = "ndi")]
+impl Ndi {
+    // Announce a source named `name`, loading the runtime from `NDI_RUNTIME_DIR_V5` if it is set.
+    // Fails rather than panicking, since the sketch runs fine without the source.
+    pub fn open(name: &str, fps: f64) -> Result<Self, String> {
+        let name = std::ffi::CString::new(name)
+            .map_err(|_| format!("NDI source names can't contain NUL: {:?}", name))?;
+        let file = match std::env::consts::OS {
+            "windows" => "Processing.NDI.Lib.x64.dll",
+            "macos" => "libndi.dylib",
+            _ => "libndi.so.5",
+        };
+        let path = match std::env::var_os("NDI_RUNTIME_DIR_V5") {
+            Some(directory) => std::path::Path::new(&directory).join(file),
+            None => file.into(),
+        };
+        // SAFETY: the NDI runtime has no initializers with requirements on the loading thread
+        let library = unsafe { libloading::Library::new(&path) }
+            .map_err(|err| format!("Could not load the NDI runtime {}: {}", path.display(), err))?;
+
+        // every function the sender needs, so a runtime missing some fails here and not
+        // mid-session. They stay `unsafe` to call, their signatures are only what the headers
+        // promise.
+        fn get<T: Copy>(library: &libloading::Library, symbol: &str) -> Result<T, String> {
+            // SAFETY: `T` is the function pointer type the NDI headers declare for `symbol`, and
+            // the copies are kept next to the library so they don't outlive it
+            match unsafe { library.get::<T>(symbol.as_bytes()) } {
+                Ok(function) => Ok(*function),
+                Err(err) => Err(format!("The NDI runtime has no {}: {}", symbol, err)),
+            }
+        }
+        let initialize: unsafe extern "C" fn() -> bool = get(&library, "NDIlib_initialize")?;
+        let create: unsafe extern "C" fn(*const SendCreate) -> *mut std::ffi::c_void =
+            get(&library, "NDIlib_send_create")?;
+        let send_video: unsafe extern "C" fn(*mut std::ffi::c_void, *const VideoFrame) =
+            get(&library, "NDIlib_send_send_video_v2")?;
+        let send_destroy: unsafe extern "C" fn(*mut std::ffi::c_void) =
+            get(&library, "NDIlib_send_destroy")?;
+        let destroy: unsafe extern "C" fn() = get(&library, "NDIlib_destroy")?;
+        // SAFETY: `NDIlib_initialize` takes no arguments and may be called before anything else
+        if !unsafe { initialize() } {
+            return Err("The NDI runtime doesn't support this CPU".to_string());
+        }
+
+        // SAFETY: the settings and the name they point to outlive the call, which copies them
+        let sender = unsafe {
+            create(&SendCreate {
+                ndi_name: name.as_ptr(),
+                groups: std::ptr::null(),
+                clock_video: false, // frames are paced by the sketch
+                clock_audio: false,
+            })
+        };
+        if sender.is_null() {
+            // SAFETY: the runtime was initialized above and nothing else uses it
+            unsafe { destroy() };
+            return Err("Could not create the NDI sender".to_string());
+        }
+        log::info!("Sending NDI source {}", name.to_string_lossy());
+
+        Ok(Ndi {
+            _library: library,
+            sender,
+            send_video,
+            send_destroy,
+            destroy,
+            frame_rate: [(fps * 1000.0).round() as i32, 1000],
+        })
+    }
+
+    // Read the texture back and send it as the next frame of the source
+    pub fn send(&mut self, window: &Window, texture: &wgpu::Texture) {
+        let image = crate::headless::read(window.device(), window.queue(), texture);
+        let (width, height) = image.dimensions();
+        let frame = VideoFrame {
+            xres: width as i32,
+            yres: height as i32,
+            four_cc: FOURCC_RGBA,
+            frame_rate_n: self.frame_rate[0],
+            frame_rate_d: self.frame_rate[1],
+            picture_aspect_ratio: width as f32 / height as f32,
+            frame_format_type: FRAME_FORMAT_PROGRESSIVE,
+            timecode: TIMECODE_SYNTHESIZE,
+            data: image.as_raw().as_ptr(),
+            line_stride_in_bytes: width as i32 * 4,
+            metadata: std::ptr::null(),
+            timestamp: 0,
+        };
+
+        // SAFETY: the sender is live until `drop`, and the send is synchronous, so the runtime is
+        // done with `image` and `frame` once it returns
+        unsafe { (self.send_video)(self.sender, &frame) };
+    }
+}
+
+#[cfg(feature = "ndi")]
+impl Drop for Ndi {
+    fn drop(&mut self) {
+        // SAFETY: the sender is live and never used again, and it was the last thing using the
+        // runtime, which `open` initialized
+        unsafe {
+            (self.send_destroy)(self.sender);
+            (self.destroy)();
+        }
+    }
+}
+
+// Stand-in without the `ndi` feature
+#[cfg(not(feature = "ndi"))]
+pub struct Ndi;
+
+#[cfg(not(feature = "ndi"))]
+impl Ndi {
+    pub fn open(_name: &str, _fps: f64) -> Result<Self, String> {
+        Err("Built without NDI support, rebuild with `--features ndi` to use --ndi".to_string())
+    }
+
+    pub fn send(&mut self, _window: &Window, _texture: &wgpu::Texture) {}
+}
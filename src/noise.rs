@@ -0,0 +1,175 @@
+use crate::config::Config;
+use nannou::noise::{
+    Billow, Fbm, MultiFractal, NoiseFn, OpenSimplex, Perlin, RidgedMulti, Seedable, Value, Worley,
+};
+use serde::{Deserialize, Serialize};
+
+const LACUNARITY: f64 = 2.0; // frequency ratio between octaves of plain noise, by default
+const PERSISTENCE: f64 = 0.5; // amplitude ratio between octaves of plain noise, by default
+
+// Noise function the field is sampled from, in two dimensions, with time as a third or with time
+// going around a circle as a third and fourth
+pub trait Noise: Send + Sync {
+    fn sample(&self, point: [f64; 2]) -> f64;
+    fn sample_3d(&self, point: [f64; 3]) -> f64;
+    fn sample_4d(&self, point: [f64; 4]) -> f64;
+}
+
+impl<T: NoiseFn<[f64; 2]> + NoiseFn<[f64; 3]> + NoiseFn<[f64; 4]> + Send + Sync> Noise for T {
+    fn sample(&self, point: [f64; 2]) -> f64 {
+        NoiseFn::<[f64; 2]>::get(self, point)
+    }
+
+    fn sample_3d(&self, point: [f64; 3]) -> f64 {
+        NoiseFn::<[f64; 3]>::get(self, point)
+    }
+
+    fn sample_4d(&self, point: [f64; 4]) -> f64 {
+        NoiseFn::<[f64; 4]>::get(self, point)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NoiseKind {
+    Perlin,
+    OpenSimplex,
+    Worley, // cellular, constant inside each cell
+    Value,
+    Fbm, // fractal sum of Perlin octaves
+    RidgedMulti,
+    Billow,
+}
+
+impl NoiseKind {
+    pub fn build(self, seed: u32) -> Box<dyn Noise> {
+        match self {
+            NoiseKind::Perlin => Box::new(Perlin::new().set_seed(seed)),
+            NoiseKind::OpenSimplex => Box::new(OpenSimplex::new().set_seed(seed)),
+            NoiseKind::Worley => Box::new(Worley::new().set_seed(seed)),
+            NoiseKind::Value => Box::new(Value::new().set_seed(seed)),
+            NoiseKind::Fbm => Box::new(Fbm::new().set_seed(seed)),
+            NoiseKind::RidgedMulti => Box::new(RidgedMulti::new().set_seed(seed)),
+            NoiseKind::Billow => Box::new(Billow::new().set_seed(seed)),
+        }
+    }
+
+    // Octaves, lacunarity and persistence the kind is summed over when they are left unset
+    pub fn octave_defaults(self) -> (usize, f64, f64) {
+        match self {
+            NoiseKind::Fbm => (
+                Fbm::DEFAULT_OCTAVE_COUNT,
+                Fbm::DEFAULT_LACUNARITY,
+                Fbm::DEFAULT_PERSISTENCE,
+            ),
+            NoiseKind::RidgedMulti => (
+                RidgedMulti::DEFAULT_OCTAVE_COUNT,
+                RidgedMulti::DEFAULT_LACUNARITY,
+                RidgedMulti::DEFAULT_PERSISTENCE,
+            ),
+            NoiseKind::Billow => (
+                Billow::DEFAULT_OCTAVE_COUNT,
+                Billow::DEFAULT_LACUNARITY,
+                Billow::DEFAULT_PERSISTENCE,
+            ),
+            _ => (1, LACUNARITY, PERSISTENCE),
+        }
+    }
+}
+
+// Noise function along with the octaves it is summed over. Unset parameters keep the defaults of
+// the kind: a single octave of plain noise, or the fractal kinds' own octaves.
+#[derive(Clone, PartialEq)]
+pub struct NoiseConfig {
+    pub kind: NoiseKind,
+    pub octaves: Option<usize>,
+    pub lacunarity: Option<f64>,
+    pub persistence: Option<f64>,
+}
+
+impl NoiseConfig {
+    pub fn heading(config: &Config) -> Self {
+        NoiseConfig {
+            kind: config.noise_kind,
+            octaves: config.octaves,
+            lacunarity: config.lacunarity,
+            persistence: config.persistence,
+        }
+    }
+
+    pub fn color(config: &Config) -> Self {
+        NoiseConfig {
+            kind: config.color_noise.unwrap_or(config.noise_kind),
+            octaves: config.color_octaves,
+            lacunarity: config.color_lacunarity,
+            persistence: config.color_persistence,
+        }
+    }
+
+    pub fn build(&self, seed: u32) -> Box<dyn Noise> {
+        match self.kind {
+            NoiseKind::Fbm => Box::new(self.fractal(Fbm::new().set_seed(seed))),
+            NoiseKind::RidgedMulti => Box::new(self.fractal(RidgedMulti::new().set_seed(seed))),
+            NoiseKind::Billow => Box::new(self.fractal(Billow::new().set_seed(seed))),
+            kind => match self.octaves.unwrap_or(1) {
+                0 | 1 => kind.build(seed),
+                octaves => Box::new(Octaves {
+                    // seeded apart so the octaves don't line up at the origin
+                    octaves: (0..octaves as u32)
+                        .map(|i| kind.build(seed.wrapping_add(i)))
+                        .collect(),
+                    lacunarity: self.lacunarity.unwrap_or(LACUNARITY),
+                    persistence: self.persistence.unwrap_or(PERSISTENCE),
+                }),
+            },
+        }
+    }
+
+    fn fractal<T: MultiFractal>(&self, mut noise: T) -> T {
+        if let Some(octaves) = self.octaves {
+            noise = noise.set_octaves(octaves.max(1));
+        }
+        if let Some(lacunarity) = self.lacunarity {
+            noise = noise.set_lacunarity(lacunarity);
+        }
+        if let Some(persistence) = self.persistence {
+            noise = noise.set_persistence(persistence);
+        }
+        noise
+    }
+}
+
+// Octaves of plain noise summed at rising frequencies and falling amplitudes, normalized back to
+// the range of a single octave
+struct Octaves {
+    octaves: Vec<Box<dyn Noise>>,
+    lacunarity: f64,
+    persistence: f64,
+}
+
+impl Octaves {
+    fn sum(&self, sample: impl Fn(&dyn Noise, f64) -> f64) -> f64 {
+        let (mut sum, mut total, mut frequency, mut amplitude) = (0.0, 0.0, 1.0, 1.0);
+        for noise in &self.octaves {
+            sum += sample(noise.as_ref(), frequency) * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+        sum / total
+    }
+}
+
+impl Noise for Octaves {
+    fn sample(&self, point: [f64; 2]) -> f64 {
+        self.sum(|noise, frequency| noise.sample(point.map(|c| c * frequency)))
+    }
+
+    fn sample_3d(&self, point: [f64; 3]) -> f64 {
+        self.sum(|noise, frequency| noise.sample_3d(point.map(|c| c * frequency)))
+    }
+
+    fn sample_4d(&self, point: [f64; 4]) -> f64 {
+        self.sum(|noise, frequency| noise.sample_4d(point.map(|c| c * frequency)))
+    }
+}
@@ -0,0 +1,224 @@
+use crate::Vector2;
+use nannou::image::{self, GrayImage};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const NORMAL_EPSILON: f64 = 1e-4; // step of the finite differences normals are computed with
+const MASK_THRESHOLD: u8 = 128; // luminance above which mask pixels are inside the obstacle
+
+// Shape particles flow around, given as `[[obstacles]]` tables in the config file. Positions are
+// centered and scaled like expressions, so the shorter side of the canvas spans -1 to 1.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "shape", rename_all = "kebab-case")]
+pub enum Obstacle {
+    Circle { center: [f64; 2], radius: f64 },
+    Rectangle { center: [f64; 2], size: [f64; 2] },
+    Polygon { points: Vec<[f64; 2]> },
+    Mask { image: PathBuf }, // bright pixels of an image stretched over the canvas
+}
+
+// What obstacles do to particles
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ObstacleMode {
+    Deflect, // steer along the boundary, wrapping the flow around the obstacles
+    Absorb,  // respawn particles that hit one, leaving a wake behind it
+}
+
+// Obstacles loaded into signed distance functions, negative inside
+pub struct Obstacles(Vec<Sdf>);
+
+enum Sdf {
+    Circle([f64; 2], f64),
+    Rectangle([f64; 2], [f64; 2]),
+    Polygon(Vec<[f64; 2]>),
+    Mask(DistanceMask),
+}
+
+impl Obstacles {
+    pub fn build(obstacles: &[Obstacle]) -> Option<Self> {
+        if obstacles.is_empty() {
+            return None;
+        }
+
+        let sdfs = obstacles.iter().map(|obstacle| match obstacle {
+            Obstacle::Circle { center, radius } => Sdf::Circle(*center, *radius),
+            Obstacle::Rectangle { center, size } => Sdf::Rectangle(*center, *size),
+            Obstacle::Polygon { points } => Sdf::Polygon(points.clone()),
+            Obstacle::Mask { image } => {
+                let mask = image::open(image).unwrap_or_else(|err| {
+                    panic!("Could not load mask {}: {}", image.display(), err)
+                });
+                Sdf::Mask(DistanceMask::new(&mask.to_luma8()))
+            }
+        });
+        Some(Obstacles(sdfs.collect()))
+    }
+
+    // Distance from a position in the canvas to the nearest obstacle, and the direction away from
+    // it. Distances are in the centered units obstacles are defined in.
+    pub fn sample(&self, pos: Vector2, extent: Vector2) -> (f64, Vector2) {
+        let scale = extent.x.min(extent.y);
+        let (x, y) = (
+            (pos.x - extent.x / 2.0) * 2.0 / scale,
+            (pos.y - extent.y / 2.0) * 2.0 / scale,
+        );
+        let aspect = [extent.x / scale, extent.y / scale]; // half extent in centered units
+
+        let distance = |x: f64, y: f64| {
+            self.0
+                .iter()
+                .map(|sdf| sdf.distance([x, y], aspect))
+                .fold(f64::INFINITY, f64::min)
+        };
+
+        let e = NORMAL_EPSILON;
+        let (gradient_x, gradient_y) = (
+            distance(x + e, y) - distance(x - e, y),
+            distance(x, y + e) - distance(x, y - e),
+        );
+        let length = gradient_x.hypot(gradient_y);
+        let normal = match length {
+            length if length > 0.0 => Vector2 {
+                x: gradient_x / length,
+                y: gradient_y / length,
+            },
+            _ => Vector2 { x: 0.0, y: 0.0 },
+        };
+
+        (distance(x, y), normal)
+    }
+}
+
+impl Sdf {
+    // Signed distance at a centered position, from https://iquilezles.org/articles/distfunctions2d/
+    fn distance(&self, [x, y]: [f64; 2], aspect: [f64; 2]) -> f64 {
+        match self {
+            Sdf::Circle([cx, cy], radius) => (x - cx).hypot(y - cy) - radius,
+            Sdf::Rectangle([cx, cy], [width, height]) => {
+                let dx = (x - cx).abs() - width / 2.0;
+                let dy = (y - cy).abs() - height / 2.0;
+                dx.max(0.0).hypot(dy.max(0.0)) + dx.max(dy).min(0.0)
+            }
+            Sdf::Polygon(points) => polygon([x, y], points),
+            Sdf::Mask(mask) => {
+                let (u, v) = ((x / aspect[0] + 1.0) / 2.0, (y / aspect[1] + 1.0) / 2.0);
+                mask.sample(u, v)
+            }
+        }
+    }
+}
+
+fn polygon([x, y]: [f64; 2], points: &[[f64; 2]]) -> f64 {
+    let Some(&[x0, y0]) = points.first() else {
+        return f64::INFINITY;
+    };
+
+    let mut distance = (x - x0).powi(2) + (y - y0).powi(2);
+    let mut sign = 1.0;
+    for (i, &[ax, ay]) in points.iter().enumerate() {
+        let [bx, by] = points[(i + points.len() - 1) % points.len()];
+        let (ex, ey) = (bx - ax, by - ay);
+        let (wx, wy) = (x - ax, y - ay);
+
+        // squared distance to the edge
+        let t = ((wx * ex + wy * ey) / (ex * ex + ey * ey)).clamp(0.0, 1.0);
+        distance = distance.min((wx - ex * t).powi(2) + (wy - ey * t).powi(2));
+
+        // winding, flipping the sign every time a ray to the right crosses an edge
+        let crosses = [y >= ay, y < by, ex * wy > ey * wx];
+        if crosses.iter().all(|&c| c) || crosses.iter().all(|&c| !c) {
+            sign = -sign;
+        }
+    }
+
+    sign * distance.sqrt()
+}
+
+// Signed distance to the bright pixels of a mask, precomputed with a chamfer distance transform
+// and scaled so the shorter side of the image spans 2 like the centered units
+struct DistanceMask {
+    width: usize,
+    height: usize,
+    distance: Vec<f64>, // row-major from the top left
+}
+
+impl DistanceMask {
+    fn new(image: &GrayImage) -> Self {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let inside: Vec<bool> = image
+            .pixels()
+            .map(|pixel| pixel[0] >= MASK_THRESHOLD)
+            .collect();
+
+        // Distance to the nearest pixel of the other kind, from the outside and from the inside
+        let outside = chamfer(width, height, |i| inside[i]);
+        let within = chamfer(width, height, |i| !inside[i]);
+        let scale = 2.0 / width.min(height) as f64;
+
+        let distance = outside
+            .iter()
+            .zip(&within)
+            .map(|(outside, within)| (outside - within) * scale)
+            .collect();
+        DistanceMask {
+            width,
+            height,
+            distance,
+        }
+    }
+
+    // Bilinear sample at a normalized position (0 to 1, y pointing up), clamped to the image
+    fn sample(&self, u: f64, v: f64) -> f64 {
+        let x = (u * self.width as f64 - 0.5).clamp(0.0, (self.width - 1) as f64);
+        let y = ((1.0 - v) * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (x - x0 as f64, y - y0 as f64);
+
+        let at = |x: usize, y: usize| self.distance[y * self.width + x];
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        lerp(
+            lerp(at(x0, y0), at(x1, y0), tx),
+            lerp(at(x0, y1), at(x1, y1), tx),
+            ty,
+        )
+    }
+}
+
+// Two pass chamfer transform: distance in pixels from every pixel to the nearest one `target`
+// holds for along straight and diagonal steps, 0 on those
+fn chamfer(width: usize, height: usize, target: impl Fn(usize) -> bool) -> Vec<f64> {
+    let mut distance: Vec<f64> = (0..width * height)
+        .map(|i| if target(i) { 0.0 } else { f64::INFINITY })
+        .collect();
+    let (straight, diagonal) = (1.0, std::f64::consts::SQRT_2);
+
+    let relax = |distance: &mut [f64], x: usize, y: usize, dx: isize, dy: isize, cost| {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        if nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height {
+            let candidate = distance[ny as usize * width + nx as usize] + cost;
+            let current = &mut distance[y * width + x];
+            *current = current.min(candidate);
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            relax(&mut distance, x, y, -1, 0, straight);
+            relax(&mut distance, x, y, 0, -1, straight);
+            relax(&mut distance, x, y, -1, -1, diagonal);
+            relax(&mut distance, x, y, 1, -1, diagonal);
+        }
+    }
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            relax(&mut distance, x, y, 1, 0, straight);
+            relax(&mut distance, x, y, 0, 1, straight);
+            relax(&mut distance, x, y, 1, 1, diagonal);
+            relax(&mut distance, x, y, -1, 1, diagonal);
+        }
+    }
+
+    distance
+}
@@ -0,0 +1,72 @@
+use crate::config::Config;
+use crate::modulation::Parameter;
+use crate::palette::PaletteKind;
+use clap::ValueEnum;
+use nannou_osc::{Packet, Type};
+
+const PREFIX: &str = "/flow/"; // every address the sketch answers to starts with this
+
+// Messages received on a UDP port, applied once per frame. `/flow/seed` restarts with an integer
+// seed, `/flow/palette` switches to a palette by name and `/flow/<parameter>` sets any parameter
+// MIDI and audio can drive, like `/flow/velocity` or `/flow/hue-offset`.
+pub struct Osc {
+    receiver: nannou_osc::Receiver,
+}
+
+impl Osc {
+    pub fn listen(port: u16) -> Self {
+        let receiver = nannou_osc::receiver(port)
+            .unwrap_or_else(|err| panic!("Could not listen for OSC on port {}: {}", port, err));
+        log::info!("Listening for OSC on port {}", port);
+        Osc { receiver }
+    }
+
+    // Apply every message received since the last call, skipping malformed ones with a warning
+    pub fn apply(&self, config: &mut Config) {
+        let messages = self
+            .receiver
+            .try_iter()
+            .flat_map(|(packet, _): (Packet, _)| packet.into_msgs());
+        for message in messages {
+            let arg = message.args.and_then(|args| args.into_iter().next());
+            if let Err(err) = apply(config, &message.addr, arg) {
+                log::warn!("Ignoring OSC message to {}: {}", message.addr, err);
+            }
+        }
+    }
+}
+
+fn apply(config: &mut Config, addr: &str, arg: Option<Type>) -> Result<(), String> {
+    let name = addr
+        .strip_prefix(PREFIX)
+        .ok_or(format!("address outside of `{}`", PREFIX))?;
+    let number = |arg: Option<Type>| match arg {
+        Some(Type::Float(value)) => Ok(value as f64),
+        Some(Type::Double(value)) => Ok(value),
+        Some(Type::Int(value)) => Ok(value as f64),
+        Some(Type::Long(value)) => Ok(value as f64),
+        _ => Err("expected a number".to_string()),
+    };
+
+    match name {
+        "seed" => {
+            config.seed = match arg {
+                Some(Type::Int(seed)) => seed as u64,
+                Some(Type::Long(seed)) => seed as u64,
+                _ => return Err("expected an integer".to_string()),
+            }
+        }
+        "palette" => {
+            config.palette = match arg {
+                Some(Type::String(palette)) => PaletteKind::from_str(&palette, true)?,
+                _ => return Err("expected a palette name".to_string()),
+            }
+        }
+        _ => {
+            let parameter = Parameter::from_str(name, true)
+                .map_err(|_| format!("no parameter named `{}`", name))?;
+            parameter.set(config, number(arg)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use crate::config::Config;
+use crate::flow_field::Model;
+use crate::layer::BlendMode;
+use crate::pacing;
+use nannou::prelude::*;
+use nannou::window::Fullscreen;
+use serde::{Deserialize, Serialize};
+
+// Extra window showing part of the shared canvas, given as `[[outputs]]` tables in the config
+// file, so a piece can span the monitors of a video wall
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Output {
+    #[serde(default)]
+    pub monitor: Option<usize>, // index of the monitor it fills, windowed if unset
+    #[serde(default = "whole")]
+    pub viewport: [f32; 4], // left, top, width and height of the part shown, as fractions of the canvas
+}
+
+fn whole() -> [f32; 4] {
+    [0.0, 0.0, 1.0, 1.0]
+}
+
+impl Output {
+    // Part of the canvas shown, in texture coordinates from the bottom left
+    fn area(&self) -> Rect {
+        let [left, top, width, height] = self.viewport;
+        Rect::from_corners(
+            vec2(left, 1.0 - top - height),
+            vec2(left + width, 1.0 - top),
+        )
+    }
+}
+
+// Open a window for every output, borderless fullscreen on its monitor if it has one
+pub fn open(app: &App, config: &Config) -> Vec<(window::Id, Output)> {
+    let monitors = app.available_monitors();
+    config
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(i, output)| {
+            let mut builder = app
+                .new_window()
+                .title(format!("{} output {}", env!("CARGO_PKG_NAME"), i + 1))
+                .surface_conf_builder(pacing::surface(config))
+                .view(view);
+            if let Some(monitor) = output.monitor {
+                let handle = monitors.get(monitor).cloned().unwrap_or_else(|| {
+                    panic!("No monitor {}, there are {}", monitor, monitors.len())
+                });
+                builder = builder
+                    .decorations(false)
+                    .fullscreen_with(Some(Fullscreen::Borderless(Some(handle))));
+            }
+            (builder.build().unwrap(), output.clone())
+        })
+        .collect()
+}
+
+// The canvas, the reaction and the species layers as the main window composites them, cropped to
+// the output's viewport and stretched over its window. The panel and the field view stay in the
+// main window.
+fn view(app: &App, model: &Model, frame: Frame) {
+    let output = match model
+        .outputs
+        .iter()
+        .find(|(id, _)| *id == frame.window_id())
+    {
+        Some((_, output)) => output,
+        None => return,
+    };
+    let (area, size) = (output.area(), frame.rect().wh());
+
+    let draw = app.draw();
+    draw.background().color(model.config.background.linear());
+    draw.texture(&model.canvas.target.texture)
+        .area(area)
+        .wh(size);
+    if let (Some(reaction), true) = (model.reaction.get(), model.config.reaction_strength > 0.0) {
+        draw.blend(BlendMode::Add.component())
+            .texture(reaction)
+            .area(area)
+            .wh(size);
+    }
+    for (blend, layer) in model
+        .species
+        .iter()
+        .filter_map(|species| species.layer.as_ref())
+    {
+        draw.blend(blend.component())
+            .texture(&layer.target.texture)
+            .area(area)
+            .wh(size);
+    }
+    draw.to_frame(app, &frame).unwrap();
+}
@@ -0,0 +1,63 @@
+use crate::config::Config;
+use crate::web::Instant;
+use nannou::prelude::*;
+use nannou::window::SurfaceConfigurationBuilder;
+use std::time::Duration;
+
+// Holds the window's frames to `max_fps` at most, to spare the CPU of a laptop. nannou's rate
+// loop mode doesn't wait between updates itself yet, so what is left of every frame is slept off
+// here instead.
+pub struct Pacer {
+    last: Instant, // when the previous frame started
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Pacer::new()
+    }
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Pacer {
+            last: Instant::now(),
+        }
+    }
+
+    // Sleep until a frame interval has passed since the previous frame started
+    pub fn wait(&mut self, config: &Config) {
+        if let Some(interval) = interval(config) {
+            let elapsed = self.last.elapsed();
+            // the browser paces frames itself, and can't sleep
+            if elapsed < interval && cfg!(not(target_arch = "wasm32")) {
+                std::thread::sleep(interval - elapsed);
+            }
+        }
+        self.last = Instant::now();
+    }
+}
+
+// Loop mode for the config, set once it is loaded and whenever `max_fps` changes
+pub fn apply(app: &App, config: &Config) {
+    app.set_loop_mode(match interval(config) {
+        Some(interval) => LoopMode::Rate {
+            update_interval: interval,
+        },
+        None => LoopMode::RefreshSync,
+    });
+}
+
+// Surface presenting frames as soon as they are drawn with `no_vsync`, rather than waiting for
+// the display to refresh. Only read when the window opens.
+pub fn surface(config: &Config) -> SurfaceConfigurationBuilder {
+    let present_mode = match config.no_vsync {
+        true => wgpu::PresentMode::Immediate,
+        false => wgpu::PresentMode::Fifo,
+    };
+    SurfaceConfigurationBuilder::new().present_mode(present_mode)
+}
+
+fn interval(config: &Config) -> Option<Duration> {
+    let fps = config.max_fps.filter(|&fps| fps > 0.0)?;
+    Some(Duration::from_secs_f64(1.0 / fps))
+}
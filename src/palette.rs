@@ -0,0 +1,268 @@
+use crate::config::Config;
+use nannou::color::{hsl, LabHue, Lch, LinSrgb, Mix, Srgb};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// Stops of the matplotlib colormaps, evenly spaced
+const VIRIDIS: [[u8; 3]; 9] = [
+    [0x44, 0x01, 0x54],
+    [0x47, 0x2d, 0x7b],
+    [0x3b, 0x52, 0x8b],
+    [0x2c, 0x72, 0x8e],
+    [0x21, 0x91, 0x8c],
+    [0x28, 0xae, 0x80],
+    [0x5e, 0xc9, 0x62],
+    [0xad, 0xdc, 0x30],
+    [0xfd, 0xe7, 0x25],
+];
+const MAGMA: [[u8; 3]; 9] = [
+    [0x00, 0x00, 0x04],
+    [0x1c, 0x10, 0x44],
+    [0x4f, 0x12, 0x7b],
+    [0x81, 0x25, 0x81],
+    [0xb5, 0x36, 0x7a],
+    [0xe5, 0x50, 0x64],
+    [0xfb, 0x87, 0x61],
+    [0xfe, 0xc2, 0x87],
+    [0xfc, 0xfd, 0xbf],
+];
+
+const OKLCH_LIGHTNESS: f32 = 0.75; // of hues swept in OKLCh
+const OKLCH_CHROMA: f32 = 0.13;
+const LCH_LIGHTNESS: f32 = 65.0; // of hues swept in CIELCh
+const LCH_CHROMA: f32 = 55.0;
+
+// 8-bit sRGB color, written as `#rrggbb` on the command line and in config files
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HexColor(pub [u8; 3]);
+
+impl HexColor {
+    pub fn linear(self) -> LinSrgb {
+        let [r, g, b] = self.0;
+        Srgb::new(r, g, b).into_format::<f32>().into_linear()
+    }
+}
+
+impl FromStr for HexColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let digits = s.strip_prefix('#').unwrap_or(s);
+        let channel = |i: usize| {
+            digits
+                .get(i * 2..i * 2 + 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        };
+
+        match (digits.len(), channel(0), channel(1), channel(2)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(HexColor([r, g, b])),
+            _ => Err(format!("invalid color `{}`, expected `#rrggbb`", s)),
+        }
+    }
+}
+
+impl fmt::Display for HexColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse()
+    }
+}
+
+impl From<HexColor> for String {
+    fn from(color: HexColor) -> String {
+        color.to_string()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PaletteKind {
+    Hue, // fully saturated hues between `hue_min` and `hue_max`
+    Viridis,
+    Magma,
+    Custom, // gradient through `palette_stops`
+}
+
+// Space palettes are interpolated and hues are swept in
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorSpace {
+    Srgb,  // HSL hues and sRGB gradients, perceptually uneven
+    Oklab, // perceptually even, sweeping hues in its polar form OKLCh
+    Lch,   // CIELCh
+}
+
+// What particles are colored by
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorMode {
+    PositionNoise, // color noise at the particle's position
+    Heading,       // direction of travel, once around the palette per turn
+    Speed,         // distance per step, half way through the palette at the field's speed
+    Age,           // steps since the particle was placed, over its lifetime
+    Constant,      // random for every particle, kept for its whole life
+    Image,         // the pixel of `color_image` under the particle, bypassing the palette
+    Script,        // the script's `color` at the particle's position
+}
+
+// How values outside of 0 to 1 index into the palette
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PaletteMapping {
+    Repeat, // wrap around, jumping from the end back to the start
+    Mirror, // go back and forth, without jumps
+    Clamp,  // saturate at either end
+}
+
+impl PaletteMapping {
+    pub fn apply(self, value: f64) -> f64 {
+        match self {
+            PaletteMapping::Repeat => value.rem_euclid(1.0),
+            PaletteMapping::Mirror => 1.0 - (value.rem_euclid(2.0) - 1.0).abs(),
+            PaletteMapping::Clamp => value.clamp(0.0, 1.0),
+        }
+    }
+}
+
+// Color of the palette at `t`, from 0 at its start to 1 at its end
+pub fn color(config: &Config, t: f64) -> LinSrgb {
+    let space = config.color_space;
+    match config.palette {
+        PaletteKind::Hue => hue_color(space, hue(config, t) as f32),
+        PaletteKind::Viridis => gradient(space, &VIRIDIS.map(HexColor), t),
+        PaletteKind::Magma => gradient(space, &MAGMA.map(HexColor), t),
+        PaletteKind::Custom => gradient(space, &config.palette_stops, t),
+    }
+}
+
+// Hue in turns, at a lightness and chroma the whole hue circle roughly fits the sRGB gamut at
+fn hue_color(space: ColorSpace, hue: f32) -> LinSrgb {
+    let color = match space {
+        ColorSpace::Srgb => return LinSrgb::from(hsl(hue, 1.0, 0.5)),
+        ColorSpace::Oklab => {
+            let (sin, cos) = (hue * std::f32::consts::TAU).sin_cos();
+            from_oklab([OKLCH_LIGHTNESS, OKLCH_CHROMA * cos, OKLCH_CHROMA * sin])
+        }
+        ColorSpace::Lch => LinSrgb::from(Lch::new(
+            LCH_LIGHTNESS,
+            LCH_CHROMA,
+            LabHue::from_degrees(hue * 360.0),
+        )),
+    };
+
+    clamp(color)
+}
+
+// Remap `t` into the configured hue slice
+fn hue(config: &Config, t: f64) -> f64 {
+    config.hue_min + t * (config.hue_max - config.hue_min)
+}
+
+// Interpolate between evenly spaced stops
+fn gradient(space: ColorSpace, stops: &[HexColor], t: f64) -> LinSrgb {
+    let last = match stops.len() {
+        0 => return LinSrgb::new(0.0, 0.0, 0.0),
+        len => len - 1,
+    };
+
+    let position = t.clamp(0.0, 1.0) * last as f64;
+    let i = (position.floor() as usize).min(last.saturating_sub(1));
+    let (a, b) = (stops[i], stops[(i + 1).min(last)]);
+    let fraction = (position - i as f64) as f32;
+    let mix = |a: f32, b: f32| a + (b - a) * fraction;
+
+    match space {
+        ColorSpace::Srgb => {
+            let (a, b) = (a.0.map(|c| c as f32 / 255.0), b.0.map(|c| c as f32 / 255.0));
+            Srgb::new(mix(a[0], b[0]), mix(a[1], b[1]), mix(a[2], b[2])).into_linear()
+        }
+        ColorSpace::Oklab => {
+            let (a, b) = (to_oklab(a.linear()), to_oklab(b.linear()));
+            clamp(from_oklab([
+                mix(a[0], b[0]),
+                mix(a[1], b[1]),
+                mix(a[2], b[2]),
+            ]))
+        }
+        ColorSpace::Lch => {
+            let (a, b) = (Lch::from(a.linear()), Lch::from(b.linear()));
+            clamp(LinSrgb::from(a.mix(&b, fraction)))
+        }
+    }
+}
+
+// Conversions between linear sRGB and OKLab, with the matrices as published at
+// https://bottosson.github.io/posts/oklab/
+#[allow(clippy::excessive_precision)]
+fn to_oklab(color: LinSrgb) -> [f32; 3] {
+    let (r, g, b) = (color.red, color.green, color.blue);
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+#[allow(clippy::excessive_precision)]
+fn from_oklab([lightness, a, b]: [f32; 3]) -> LinSrgb {
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+
+    LinSrgb::new(
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    )
+}
+
+// Clip colors outside of the sRGB gamut
+fn clamp(color: LinSrgb) -> LinSrgb {
+    LinSrgb::new(
+        color.red.clamp(0.0, 1.0),
+        color.green.clamp(0.0, 1.0),
+        color.blue.clamp(0.0, 1.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hues_stay_within_slice() {
+        let config = Config {
+            hue_min: 0.5,
+            hue_max: 0.75,
+            color_noise_multiplier: 3.0, // pushes values well outside of 0 to 1 before mapping
+            ..Config::default()
+        };
+
+        for mapping in [
+            PaletteMapping::Repeat,
+            PaletteMapping::Mirror,
+            PaletteMapping::Clamp,
+        ] {
+            for i in 0..=1000 {
+                let noise = i as f64 / 500.0 - 1.0;
+                let value = (noise * config.color_noise_multiplier + 1.0) / 2.0;
+                let hue = hue(&config, mapping.apply(value));
+                assert!((config.hue_min..=config.hue_max).contains(&hue));
+            }
+        }
+    }
+}
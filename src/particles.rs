@@ -0,0 +1,729 @@
+use crate::collision::DrawnPaths;
+use crate::color_source::{self, ColorSource};
+use crate::config::Config;
+use crate::coverage::CoverageMap;
+use crate::edge::{Crossing, EdgeMode};
+use crate::emitter::Emitter;
+use crate::field::{FieldKind, VectorField};
+use crate::flock::Flock;
+use crate::fluid::{Fluid, FluidConfig};
+use crate::grid::FlowGrid;
+use crate::history::Snapshot;
+use crate::noise::NoiseConfig;
+use crate::obstacle::{ObstacleMode, Obstacles};
+use crate::palette::ColorMode;
+use crate::radial::Radial;
+use crate::reaction::{Reaction, ReactionConfig};
+use crate::respawn::RespawnMode;
+use crate::ribbon::RenderMode;
+use crate::script::Script;
+use crate::seed::noise_seed;
+use crate::shape::SizeMode;
+use crate::stack::FieldLayer;
+use crate::Vector2;
+use crate::{field, noise, palette, seed, steering};
+use nannou::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub const AGE_SCALE: f64 = 1000.0; // steps the age color mode spans the palette over without a lifetime
+const SIZE_NOISE_OFFSET: (f64, f64) = (31.7, 47.3); // into the color noise, decorrelating size from color
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Flow {
+    pub pos: Vector2,
+    pub vel: Vector2,
+    pub prev: Vector2, // position before the last step, which drawing interpolates from
+    pub origin: Vector2, // where the flow was first placed, respawned at in grid mode
+    pub age: u64,      // steps since the flow was placed or last respawned
+    pub path: Vec<Vector2>, // every position so far, only recorded for plotter export
+    pub tail: VecDeque<Vector2>, // the most recent positions, only recorded for lines and ribbons
+    #[serde(default)]
+    pub rng: u64, // state of the flow's own random stream, for jitter
+}
+
+impl Flow {
+    // The `index`th flow placed since seeding with `seed`, its random stream numbered after it so
+    // it doesn't depend on the order flows are stepped in
+    pub fn new(pos: Vector2, seed: u64, index: u64) -> Self {
+        Flow {
+            rng: seed::splitmix64(seed ^ seed::splitmix64(index)),
+            pos,
+            vel: Vector2 { x: 0.0, y: 0.0 },
+            prev: pos,
+            origin: pos,
+            age: 0,
+            path: Vec::new(),
+            tail: VecDeque::new(),
+        }
+    }
+}
+
+// Parameters the heading field is computed from
+#[derive(Clone, PartialEq)]
+pub struct NoiseParams {
+    pub seed: u64,
+    pub noise: NoiseConfig,
+    pub field_kind: FieldKind,
+    pub image: Option<PathBuf>,
+    pub expression: Option<String>,
+    pub field_file: Option<PathBuf>,
+    pub radial: Option<Radial>,
+    pub radial_center: Vec<f64>,
+    pub field_weight: f64,
+    pub fields: Vec<FieldLayer>,
+    pub guide_text: Option<String>,
+    pub guide_path: Option<String>,
+    pub guide_weight: f64,
+    pub guide_band: f64,
+    pub heading_noise_factor: f64,
+    pub heading_noise_multiplier: f64,
+    pub domain_warp: Vec<f64>,
+}
+
+impl NoiseParams {
+    pub fn new(config: &Config, seed: u64) -> Self {
+        NoiseParams {
+            seed,
+            noise: NoiseConfig::heading(config),
+            field_kind: config.field_kind,
+            image: config.image.clone(),
+            expression: config.expression.clone(),
+            field_file: config.field_file.clone(),
+            radial: config.radial,
+            radial_center: config.radial_center.clone(),
+            field_weight: config.field_weight,
+            fields: config.fields.clone(),
+            guide_text: config.guide_text.clone(),
+            guide_path: config.guide_path.clone(),
+            guide_weight: config.guide_weight,
+            guide_band: config.guide_band,
+            heading_noise_factor: config.heading_noise_factor,
+            heading_noise_multiplier: config.heading_noise_multiplier,
+            domain_warp: config.domain_warp.clone(),
+        }
+    }
+}
+
+// Point particles are pulled towards (or pushed away from with a negative strength)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Attractor {
+    pub pos: Vector2,
+    pub radius: f64,
+    pub strength: f64,
+}
+
+pub struct Simulation {
+    pub flow_field: Vec<Flow>, // starts as a row-major grid of `point_count` by `point_count` flows
+    pub field: Box<dyn VectorField>, // heading is derived from this
+    pub reaction: Option<Arc<Reaction>>, // advanced every step, blended into `field`
+    pub fluid: Option<Arc<Fluid>>, // advanced every step, blended into `field`
+    pub script: Option<Arc<Script>>, // defines `field` and the script color mode, if given
+    pub grid: Option<FlowGrid>, // lattice the field is sampled on, if not sampled per flow
+    pub obstacles: Option<Obstacles>,
+    pub drawn: Option<DrawnPaths>, // where flows were drawn, kept clear of if `path_spacing` is set
+    pub emitter: Emitter,
+    pub noise: Box<dyn noise::Noise>,         // color noise
+    pub colors: Option<Box<dyn ColorSource>>, // colors taken from the position, for the image mode
+    pub seed: u64,
+    pub rng: ChaCha12Rng,       // what `StdRng` currently is, but serializable
+    pub spawn_accumulator: f64, // fractional particles carried over to the next spawn
+    pub spawned: u64,           // flows placed since seeding, numbering their random streams
+    pub steps: u64,             // number of steps taken since the simulation was seeded
+    pub record_paths: bool,     // whether flows record their path history
+    pub attractor: Option<Attractor>, // held mouse button, if any
+    pub paths: Vec<Vec<Vector2>>, // path history of flows that were removed
+    pub interpolation: f64, // fraction of the last step flows are drawn at, 1.0 for their position
+    pub x_end: f64,
+    pub y_end: f64,
+}
+
+impl Simulation {
+    pub fn new(config: &Config, seed: u64, x_end: f64, y_end: f64) -> Self {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let extent = Vector2 { x: x_end, y: y_end };
+        let reaction = Reaction::build(config, noise_seed(seed), extent);
+        let fluid = Fluid::build(config, noise_seed(seed), extent);
+        let script = Script::build(config, noise_seed(seed));
+        let field = field::build(
+            config,
+            noise_seed(seed),
+            reaction.clone(),
+            fluid.clone(),
+            script.clone(),
+        );
+        let noise = NoiseConfig::color(config).build(seed::color_noise_seed(config, seed));
+        let emitter = Emitter::new(config);
+        let mut flow_field: Vec<Flow> = emitter
+            .positions(config, &mut rng, x_end, y_end)
+            .into_iter()
+            .enumerate()
+            .map(|(i, pos)| Flow::new(pos, seed, i as u64))
+            .collect();
+
+        // Stagger the starting ages so the flows don't all respawn on the same step
+        if config.max_lifetime > 0 {
+            for flow in flow_field.iter_mut() {
+                flow.age = rng.gen_range(0..config.max_lifetime);
+            }
+        }
+
+        Simulation {
+            spawned: flow_field.len() as u64,
+            flow_field,
+            field,
+            reaction,
+            fluid,
+            script,
+            grid: FlowGrid::new(config, x_end, y_end),
+            obstacles: Obstacles::build(&config.obstacles),
+            drawn: DrawnPaths::build(config),
+            emitter,
+            noise,
+            colors: color_source::build(config),
+            seed,
+            rng,
+            spawn_accumulator: 0.0,
+            steps: 0,
+            record_paths: false,
+            attractor: None,
+            paths: Vec::new(),
+            interpolation: 1.0,
+            x_end,
+            y_end,
+        }
+    }
+
+    // Load the script again after it changed, keeping the one running if it doesn't parse
+    pub fn reload_script(&mut self, config: &Config) {
+        let path = match &config.script {
+            Some(path) => path,
+            None => return,
+        };
+        let noise_seed = noise_seed(self.seed);
+        match Script::load(config, path, noise_seed) {
+            Ok(script) => {
+                log::info!("Reloaded {}", path.display());
+                self.script = Some(Arc::new(script));
+                let (reaction, fluid) = (self.reaction.clone(), self.fluid.clone());
+                self.field = field::build(config, noise_seed, reaction, fluid, self.script.clone());
+                self.grid = FlowGrid::new(config, self.x_end, self.y_end);
+            }
+            Err(err) => log::warn!("{}", err),
+        }
+    }
+
+    // Follow a change from `old` to `config` that keeps the flows where they are, rebuilding the
+    // field and the color noise from the seed if needed
+    pub fn reconfigure(&mut self, old: &Config, config: &Config) {
+        if NoiseConfig::heading(old) != NoiseConfig::heading(config)
+            || NoiseConfig::color(old) != NoiseConfig::color(config)
+            || old.color_seed != config.color_seed
+            || old.image != config.image
+            || old.expression != config.expression
+            || old.script != config.script
+            || old.field_file != config.field_file
+            || old.radial != config.radial
+            || old.radial_center != config.radial_center
+            || old.fields != config.fields
+            || old.guide_text != config.guide_text
+            || old.guide_path != config.guide_path
+            || ReactionConfig::new(old) != ReactionConfig::new(config)
+            || FluidConfig::new(old) != FluidConfig::new(config)
+        {
+            let noise_seed = noise_seed(self.seed);
+            if ReactionConfig::new(old) != ReactionConfig::new(config) {
+                self.reaction = Reaction::build(config, noise_seed, self.extent());
+            }
+            if FluidConfig::new(old) != FluidConfig::new(config) {
+                self.fluid = Fluid::build(config, noise_seed, self.extent());
+            }
+            if old.script != config.script {
+                self.script = Script::build(config, noise_seed);
+            }
+            let (reaction, fluid) = (self.reaction.clone(), self.fluid.clone());
+            self.field = field::build(config, noise_seed, reaction, fluid, self.script.clone());
+            self.noise =
+                NoiseConfig::color(config).build(seed::color_noise_seed(config, self.seed));
+            self.grid = FlowGrid::new(config, self.x_end, self.y_end); // resampled from the new field
+        } else if old.grid_resolution != config.grid_resolution {
+            self.grid = FlowGrid::new(config, self.x_end, self.y_end);
+        }
+
+        if old.obstacles != config.obstacles {
+            self.obstacles = Obstacles::build(&config.obstacles);
+        }
+        if old.color_image != config.color_image {
+            self.colors = color_source::build(config);
+        }
+        if old.path_spacing != config.path_spacing {
+            self.drawn = DrawnPaths::build(config);
+        }
+        if (
+            old.emitter,
+            &old.emitter_image,
+            &old.emitter_center,
+            old.emitter_radius,
+        ) != (
+            config.emitter,
+            &config.emitter_image,
+            &config.emitter_center,
+            config.emitter_radius,
+        ) || old.emitter_line != config.emitter_line
+        {
+            self.emitter = Emitter::new(config);
+        }
+    }
+
+    // Follow the window to a new size, keeping every flow at the same position relative to it
+    pub fn resize(&mut self, config: &Config, x_end: f64, y_end: f64) {
+        let (sx, sy) = (x_end / self.x_end, y_end / self.y_end);
+        let scale = |pos: &mut Vector2| {
+            pos.x *= sx;
+            pos.y *= sy;
+        };
+
+        for flow in &mut self.flow_field {
+            for pos in [&mut flow.pos, &mut flow.prev, &mut flow.origin] {
+                scale(pos);
+            }
+            flow.path.iter_mut().chain(&mut flow.tail).for_each(scale);
+        }
+        self.paths.iter_mut().flatten().for_each(scale);
+
+        self.x_end = x_end;
+        self.y_end = y_end;
+        self.grid = FlowGrid::new(config, x_end, y_end); // resampled over the new extent
+        if let Some(drawn) = &mut self.drawn {
+            drawn.clear(); // the spacing doesn't scale with the window
+        }
+    }
+
+    // Point on the canvas of a position, centered like the window
+    pub fn canvas_point(&self, pos: Vector2) -> Vec2 {
+        vec2(
+            (pos.x * 2.0 - self.x_end) as f32,
+            (pos.y * 2.0 - self.y_end) as f32,
+        )
+    }
+
+    // Position of a point on the canvas, halving like the positions
+    pub fn position(&self, point: Vec2) -> Vector2 {
+        Vector2 {
+            x: (point.x as f64 + self.x_end) / 2.0,
+            y: (point.y as f64 + self.y_end) / 2.0,
+        }
+    }
+
+    pub fn extent(&self) -> Vector2 {
+        Vector2 {
+            x: self.x_end,
+            y: self.y_end,
+        }
+    }
+
+    // Where a flow is drawn, `interpolation` of the way from its previous position to its current one
+    pub fn drawn_pos(&self, flow: &Flow) -> Vector2 {
+        let t = self.interpolation;
+        flow.prev * (1.0 - t) + flow.pos * t
+    }
+
+    // Size of the canvas the simulation is drawn to, in points
+    pub fn size(&self) -> Vec2 {
+        vec2(self.x_end as f32, self.y_end as f32) * 2.0
+    }
+
+    // Vector of the field at a position, interpolated from the grid if there is one
+    pub fn field_vector(&self, config: &Config, pos: Vector2) -> Vector2 {
+        let extent = Vector2 {
+            x: self.x_end,
+            y: self.y_end,
+        };
+        match &self.grid {
+            Some(grid) if grid.sampled() => grid.sample(pos),
+            _ => self.field.vector(config, pos, extent, self.steps),
+        }
+    }
+
+    // Heading of the field at a position, in radians
+    pub fn heading(&self, config: &Config, pos: Vector2) -> f64 {
+        let vector = self.field_vector(config, pos);
+
+        // measured like `step()` does
+        vector.x.atan2(vector.y)
+    }
+
+    // Velocity of a flow at a position: along the field, pulled towards the attractor or pushed
+    // away from it, fading out towards its radius
+    pub fn velocity(&self, config: &Config, pos: Vector2) -> Vector2 {
+        let heading = self.heading(config, pos);
+        let mut vel = Vector2 {
+            x: heading.sin(),
+            y: heading.cos(),
+        };
+
+        if let Some(attractor) = &self.attractor {
+            let offset = attractor.pos - pos;
+            let distance = offset.length();
+
+            if distance > 0.0 && distance < attractor.radius {
+                let weight = attractor.strength * (1.0 - distance / attractor.radius);
+                vel += offset / distance * weight;
+            }
+        }
+
+        // Cancel the part of the velocity heading into an obstacle, fully at its boundary and
+        // fading out towards the margin, so the flow slides along it. Flows that ended up inside
+        // are pushed back out.
+        if let (Some(obstacles), ObstacleMode::Deflect) = (&self.obstacles, config.obstacle_mode) {
+            let (distance, normal) = obstacles.sample(pos, self.extent());
+            let margin = config.obstacle_margin;
+
+            if distance < margin {
+                let weight = match margin > 0.0 {
+                    true => (1.0 - distance / margin).min(1.0),
+                    false => 1.0,
+                };
+                let into = vel.dot(normal);
+                if into < 0.0 {
+                    vel += normal * (-into * weight);
+                }
+                if distance < 0.0 {
+                    vel += normal;
+                }
+            }
+        }
+
+        vel
+    }
+
+    // Normalized color noise at a position, based on screen position, mapped into the palette
+    pub fn color_value(&self, config: &Config, pos: Vector2) -> f64 {
+        let x: f64 = pos.x * 2.0 - self.x_end; // map to window
+        let y: f64 = pos.y * 2.0 - self.y_end;
+        let x2: f64 = x / self.x_end * config.color_noise_factor; // 0 to 1, times color_noise_factor
+        let y2: f64 = y / self.y_end * config.color_noise_factor;
+
+        let value = (self.noise.sample([x2, y2]) * config.color_noise_multiplier + 1.0) / 2.0;
+        config.palette_mapping.apply(value)
+    }
+
+    // Color of a flow, indexing into the palette by `color_mode`
+    pub fn color(&self, config: &Config, flow: &Flow) -> LinSrgb {
+        let value = match config.color_mode {
+            ColorMode::PositionNoise => {
+                return palette::color(config, self.color_value(config, flow.pos))
+            }
+            // the color noise without an image
+            ColorMode::Image => {
+                return match &self.colors {
+                    Some(colors) => colors.color(flow.pos.x / self.x_end, flow.pos.y / self.y_end),
+                    None => palette::color(config, self.color_value(config, flow.pos)),
+                }
+            }
+            // the color noise without a color in the script
+            ColorMode::Script => {
+                let time = self.steps as f64 * config.evolution_speed;
+                let script = self.script.as_ref();
+                match script.and_then(|script| script.color(flow.pos, self.extent(), time)) {
+                    Some(value) => value,
+                    None => return palette::color(config, self.color_value(config, flow.pos)),
+                }
+            }
+            ColorMode::Heading => flow.vel.x.atan2(flow.vel.y) / std::f64::consts::TAU + 0.5,
+            ColorMode::Speed => flow.vel.length() / 2.0,
+            ColorMode::Age => match config.max_lifetime {
+                0 => flow.age as f64 / AGE_SCALE,
+                lifetime => flow.age as f64 / lifetime as f64,
+            },
+            ColorMode::Constant => {
+                // hashed from where the flow started, so respawning keeps it
+                let hash = seed::splitmix64(
+                    flow.origin.x.to_bits() ^ flow.origin.y.to_bits().rotate_left(32),
+                );
+                hash as f64 / u64::MAX as f64
+            }
+        };
+
+        palette::color(config, config.palette_mapping.apply(value))
+    }
+
+    // Size a flow is drawn at by `size_mode`, unless it is constant
+    pub fn stroke_size(&self, config: &Config, flow: &Flow) -> Option<f64> {
+        let value = match config.size_mode {
+            SizeMode::Constant => return None,
+            SizeMode::Noise => {
+                let x = flow.pos.x / self.x_end * config.size_noise_factor + SIZE_NOISE_OFFSET.0;
+                let y = flow.pos.y / self.y_end * config.size_noise_factor + SIZE_NOISE_OFFSET.1;
+                (self.noise.sample([x, y]) + 1.0) / 2.0
+            }
+            SizeMode::Speed => flow.vel.length() / 2.0, // like the speed color mode
+        };
+        Some(config.size_min + (config.size_max - config.size_min) * value.clamp(0.0, 1.0))
+    }
+
+    pub fn step(&mut self, config: &Config) {
+        if let Some(reaction) = &self.reaction {
+            reaction.advance(self.steps);
+        }
+        if let Some(fluid) = &self.fluid {
+            let stir = self.attractor.as_ref().map(|attractor| {
+                let pos = [attractor.pos.x / self.x_end, attractor.pos.y / self.y_end];
+                (pos, attractor.radius / self.x_end)
+            });
+            fluid.advance(self.steps, stir);
+        }
+        if let Some(grid) = &mut self.grid {
+            let params = NoiseParams::new(config, self.seed);
+            grid.update(config, &*self.field, params, self.steps);
+        }
+
+        // Flows only depend on the field and on where their neighbors were at the start of the
+        // step, so they are stepped in parallel
+        let mut flow_field = std::mem::take(&mut self.flow_field);
+        let simulation = &*self;
+        let flock = Flock::enabled(config).then(|| {
+            Flock::new(
+                config,
+                flow_field.iter().map(|flow| (flow.pos, flow.vel)).collect(),
+            )
+        });
+
+        let h = config.velocity_multiplier * config.dt;
+
+        flow_field.par_iter_mut().enumerate().for_each(|(i, flow)| {
+            if simulation.record_paths {
+                flow.path.push(flow.pos);
+            }
+            if config.render_mode != RenderMode::Points {
+                flow.tail.push_back(flow.pos);
+                while flow.tail.len() > config.path_length {
+                    flow.tail.pop_front();
+                }
+            }
+            flow.prev = flow.pos;
+
+            // Change the velocity based on the field, keeping to the previous one with inertia
+            let mut target = config
+                .integrator
+                .velocity(flow.pos, h, |pos| simulation.velocity(config, pos));
+            if let Some(flock) = &flock {
+                target += flock.steering(config, i);
+            }
+            flow.vel = steering::steer(config, flow.vel, target, flow.age);
+
+            // Turn and speed up or slow down by a random amount from the flow's own stream
+            if config.heading_jitter > 0.0 || config.speed_jitter > 0.0 {
+                let turn = (seed::next_unit(&mut flow.rng) * 2.0 - 1.0) * config.heading_jitter;
+                let speed =
+                    1.0 + (seed::next_unit(&mut flow.rng) * 2.0 - 1.0) * config.speed_jitter;
+                flow.vel = flow.vel.rotate(turn.to_radians()) * speed;
+            }
+
+            flow.pos += flow.vel * h;
+            flow.age += 1;
+        });
+
+        // Keep flows within the window and respawn the ones that left it or outlived their
+        // lifetime, in order so the random numbers are too
+        let absorbing = match (&self.obstacles, config.obstacle_mode) {
+            (Some(obstacles), ObstacleMode::Absorb) => Some(obstacles),
+            _ => None,
+        };
+        if config.max_lifetime > 0
+            || config.edge_mode != EdgeMode::Exit
+            || absorbing.is_some()
+            || self.drawn.is_some()
+        {
+            let (x_end, y_end) = (self.x_end, self.y_end);
+            let extent = self.extent();
+            let mut coverage = (config.respawn == RespawnMode::Coverage).then(|| {
+                let positions = flow_field.iter().map(|flow| flow.pos);
+                CoverageMap::new(positions, config.coverage_cells, x_end, y_end)
+            });
+
+            for (i, flow) in flow_field.iter_mut().enumerate() {
+                if let Some(coverage) = &mut coverage {
+                    coverage.remove(flow.pos);
+                }
+                let crossing = config
+                    .edge_mode
+                    .apply(&mut flow.pos, &mut flow.vel, x_end, y_end);
+                let expired = config.max_lifetime > 0 && flow.age >= config.max_lifetime;
+                let absorbed = absorbing.is_some_and(|obstacles| {
+                    let (distance, _) = obstacles.sample(flow.pos, extent);
+                    distance < 0.0
+                });
+                // Flows stop before coming too close to a drawn path, other than the bit of their
+                // own they just drew
+                let owner = (i, self.steps.wrapping_sub(flow.age));
+                let collided = self.drawn.as_ref().is_some_and(|drawn| {
+                    let recent = (config.path_spacing / (flow.vel.length() * h)).ceil();
+                    drawn.blocked(
+                        flow.pos,
+                        owner,
+                        flow.age,
+                        recent.min(u64::MAX as f64) as u64,
+                    )
+                });
+                let respawn = expired || absorbed || collided || crossing == Crossing::Respawn;
+
+                if (respawn || crossing == Crossing::Jumped) && !flow.path.is_empty() {
+                    self.paths.push(std::mem::take(&mut flow.path));
+                }
+                if respawn {
+                    flow.pos = config.respawn.position(
+                        &mut self.rng,
+                        flow.origin,
+                        &self.emitter,
+                        coverage.as_ref(),
+                        x_end,
+                        y_end,
+                    );
+                    flow.age = 0;
+                }
+                if respawn || crossing == Crossing::Jumped {
+                    flow.prev = flow.pos; // not drawn across the jump
+                    flow.tail.clear();
+                }
+                if let Some(coverage) = &mut coverage {
+                    coverage.add(flow.pos);
+                }
+                if let Some(drawn) = &mut self.drawn {
+                    let owner = (i, self.steps.wrapping_sub(flow.age));
+                    drawn.add(flow.pos, owner, flow.age);
+                }
+            }
+        }
+
+        self.flow_field = flow_field;
+        self.steps += 1;
+    }
+
+    // Spawn `spawn_rate` particles per second at random positions and remove the ones that left
+    // the window. Fractional spawns accumulate across calls, keeping the rate independent of `dt`.
+    // Returns the number of particles spawned.
+    pub fn spawn(&mut self, config: &Config, dt: f64) -> usize {
+        if config.spawn_rate <= 0.0 {
+            return 0;
+        }
+
+        let (x_end, y_end) = (self.x_end, self.y_end);
+        let paths = &mut self.paths;
+        self.flow_field.retain_mut(|flow| {
+            let inside = (0.0..=x_end).contains(&flow.pos.x) && (0.0..=y_end).contains(&flow.pos.y);
+            if !inside && !flow.path.is_empty() {
+                paths.push(std::mem::take(&mut flow.path));
+            }
+            inside
+        });
+
+        self.spawn_accumulator += config.spawn_rate * dt;
+        let count = self.spawn_accumulator.floor();
+        self.spawn_accumulator -= count;
+
+        for _ in 0..count as usize {
+            let pos = self.emitter.position(&mut self.rng, x_end, y_end);
+            self.flow_field
+                .push(Flow::new(pos, self.seed, self.spawned));
+            self.spawned += 1;
+        }
+
+        count as usize
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            flow_field: self.flow_field.clone(),
+            rng: self.rng.clone(),
+            spawn_accumulator: self.spawn_accumulator,
+            steps: self.steps,
+            spawned: self.spawned,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.flow_field = snapshot.flow_field.clone();
+        self.rng = snapshot.rng.clone();
+        self.spawn_accumulator = snapshot.spawn_accumulator;
+        self.steps = snapshot.steps;
+        self.spawned = snapshot.spawned;
+        if let Some(drawn) = &mut self.drawn {
+            drawn.clear();
+        }
+    }
+
+    // Compute the exact state `steps` steps after seeding, as a pure function of the config, the
+    // seed and `steps`. Rewinding reseeds from scratch, going forward continues stepping. Every
+    // step spawns `STEP_DT` seconds' worth like headless renders do, so with a spawn rate the
+    // state only matches an interactive session running at 60 fps.
+    pub fn run_to(&mut self, config: &Config, steps: u64) -> &[Flow] {
+        if steps < self.steps {
+            let record_paths = self.record_paths;
+            *self = Simulation::new(config, self.seed, self.x_end, self.y_end);
+            self.record_paths = record_paths;
+        }
+
+        while self.steps < steps {
+            self.spawn(config, crate::headless::STEP_DT);
+            self.step(config);
+        }
+
+        &self.flow_field
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_to_matches_stepping() {
+        let state = |flows: &[Flow]| serde_json::to_string(flows).unwrap();
+
+        // with and without particles spawning along the way
+        for spawn_rate in [0.0, 30.0] {
+            let config = Config {
+                point_count: 8,
+                spawn_rate,
+                ..Config::default()
+            };
+
+            let mut stepped = Simulation::new(&config, 3, 200.0, 150.0);
+            for _ in 0..50 {
+                stepped.spawn(&config, crate::headless::STEP_DT);
+                stepped.step(&config);
+            }
+
+            let mut ran = Simulation::new(&config, 3, 200.0, 150.0);
+            assert_eq!(state(ran.run_to(&config, 50)), state(&stepped.flow_field));
+
+            // rewinding reseeds and steps forward again to the same state
+            ran.run_to(&config, 80);
+            assert_eq!(state(ran.run_to(&config, 50)), state(&stepped.flow_field));
+        }
+    }
+
+    #[test]
+    fn spawn_rate_over_a_second() {
+        let config = Config {
+            spawn_rate: 25.0,
+            ..Config::default()
+        };
+
+        for fps in [30, 60, 144] {
+            let mut simulation = Simulation::new(&config, 3, 200.0, 150.0);
+            let spawned: usize = (0..fps)
+                .map(|_| simulation.spawn(&config, 1.0 / fps as f64))
+                .sum();
+
+            // the last fraction of a spawn can be lost to rounding
+            assert!((24..=25).contains(&spawned), "{} at {} fps", spawned, fps);
+        }
+    }
+}
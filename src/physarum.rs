@@ -0,0 +1,288 @@
+use crate::config::Config;
+use crate::metadata::Metadata;
+use crate::pacing::{self, Pacer};
+use crate::palette;
+use crate::seed;
+use crate::sketch::Sketch;
+use nannou::color::{LinSrgb, Srgb};
+use nannou::image::RgbaImage;
+use nannou::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use rayon::prelude::*;
+use std::path::Path;
+
+// Agent of the slime mold, moving one `agent_speed` per step
+struct Agent {
+    x: f64,
+    y: f64,
+    heading: f64, // in radians
+}
+
+// The classic Physarum model: agents sense the trail map ahead, to the left and to the right of
+// them, turn towards the strongest trail and deposit onto it, while the map diffuses and decays
+// every step. Networks of veins emerge from nothing but that feedback.
+pub struct Physarum {
+    agents: Vec<Agent>,
+    trail: Vec<f32>, // row-major, `width` by `height`, y pointing down
+    width: usize,
+    height: usize,
+    rng: ChaCha12Rng,
+    pub seed: u64,
+    pub steps: u64,
+}
+
+impl Physarum {
+    // `point_count` squared agents at random positions and headings
+    pub fn new(config: &Config, seed: u64, width: usize, height: usize) -> Self {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let count = (config.point_count * config.point_count) as usize;
+        let agents = (0..count)
+            .map(|_| Agent {
+                x: rng.gen_range(0.0..width as f64),
+                y: rng.gen_range(0.0..height as f64),
+                heading: rng.gen_range(0.0..std::f64::consts::TAU),
+            })
+            .collect();
+
+        Physarum {
+            agents,
+            trail: vec![0.0; width * height],
+            width,
+            height,
+            rng,
+            seed,
+            steps: 0,
+        }
+    }
+
+    // Trail at a point, wrapping around the edges
+    fn sense(&self, x: f64, y: f64) -> f32 {
+        let column = (x.floor() as i64).rem_euclid(self.width as i64) as usize;
+        let row = (y.floor() as i64).rem_euclid(self.height as i64) as usize;
+        self.trail[row * self.width + column]
+    }
+
+    pub fn step(&mut self, config: &Config) {
+        let (angle, turn) = (
+            config.sensor_angle.to_radians(),
+            config.turn_angle.to_radians(),
+        );
+        let distance = config.sensor_distance;
+        let (width, height) = (self.width as f64, self.height as f64);
+
+        // Agents only read the trail map while turning, so they are moved in order, drawing turns
+        // from the seeded generator for reproducibility
+        let mut agents = std::mem::take(&mut self.agents);
+        for agent in agents.iter_mut() {
+            let sensor = |offset: f64| {
+                let heading = agent.heading + offset;
+                self.sense(
+                    agent.x + heading.cos() * distance,
+                    agent.y + heading.sin() * distance,
+                )
+            };
+            let (left, ahead, right) = (sensor(-angle), sensor(0.0), sensor(angle));
+
+            if ahead >= left && ahead >= right {
+                // keep going
+            } else if ahead < left && ahead < right {
+                agent.heading += if self.rng.gen() { turn } else { -turn };
+            } else if left > right {
+                agent.heading -= turn;
+            } else {
+                agent.heading += turn;
+            }
+
+            agent.x = (agent.x + agent.heading.cos() * config.agent_speed).rem_euclid(width);
+            agent.y = (agent.y + agent.heading.sin() * config.agent_speed).rem_euclid(height);
+        }
+        for agent in &agents {
+            let index = (agent.y as usize).min(self.height - 1) * self.width
+                + (agent.x as usize).min(self.width - 1);
+            self.trail[index] += config.deposit;
+        }
+        self.agents = agents;
+
+        // 3x3 box blur mixed in by `diffusion`, then decay
+        let (w, h) = (self.width, self.height);
+        let trail = &self.trail;
+        let keep = 1.0 - config.trail_decay;
+        let diffusion = config.diffusion;
+        self.trail = (0..w * h)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = ((i % w) as i64, (i / w) as i64);
+                let mut sum = 0.0;
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let column = (x + dx).rem_euclid(w as i64) as usize;
+                        let row = (y + dy).rem_euclid(h as i64) as usize;
+                        sum += trail[row * w + column];
+                    }
+                }
+                let blurred = trail[i] * (1.0 - diffusion) + sum / 9.0 * diffusion;
+                blurred * keep
+            })
+            .collect();
+
+        self.steps += 1;
+    }
+
+    // The trail map colored by the palette over the background, fading in with an exponential
+    // curve (higher exposure saturates sooner), as sRGB encoded RGBA bytes
+    pub fn pixels(&self, config: &Config) -> Vec<u8> {
+        let background = config.background.linear();
+        self.trail
+            .par_iter()
+            .flat_map_iter(|&value| {
+                let t = 1.0 - (-value * config.additive_hue_exposure).exp();
+                let color = palette::color(config, t as f64);
+                let mix = |a: f32, b: f32| a * (1.0 - t) + b * t;
+                let linear = LinSrgb::new(
+                    mix(background.red, color.red),
+                    mix(background.green, color.green),
+                    mix(background.blue, color.blue),
+                );
+                let color: Srgb<u8> = Srgb::from_linear(linear).into_format();
+                [color.red, color.green, color.blue, 255]
+            })
+            .collect()
+    }
+}
+
+// Run `render_steps` steps at the render resolution and save the trail map, without a window
+fn render(config: &Config, path: &Path) {
+    let seed = crate::seed::resolve(config);
+    let path = crate::seed::expand(path, seed);
+    let (width, height) = (config.render_width, config.render_height);
+
+    let mut physarum = Physarum::new(config, seed, width as usize, height as usize);
+    for _ in 0..config.render_steps {
+        physarum.step(config);
+    }
+
+    let metadata = Metadata::new(config, seed);
+    if config.sidecar {
+        metadata.save_sidecar(&path);
+    }
+    let image = RgbaImage::from_raw(width, height, physarum.pixels(config)).unwrap();
+    metadata.save_png(&image, &path);
+    log::info!("Saved {}", path.display());
+}
+
+pub struct Model {
+    config: Config,
+    physarum: Physarum,
+    texture: wgpu::Texture, // the trail map, uploaded every frame
+    paused: bool,
+    capture_next: bool,
+    pacer: Pacer, // holds frames to `max_fps`
+}
+
+impl Sketch for Model {
+    fn export(config: &Config) -> bool {
+        if let Some(path) = &config.render {
+            render(config, path);
+        }
+        config.render.is_some()
+    }
+
+    fn model(app: &App) -> Self {
+        model(app)
+    }
+
+    fn update(app: &App, model: &mut Self, frame: Update) {
+        update(app, model, frame)
+    }
+
+    fn exit(app: &App, model: Self) {
+        exit(app, model)
+    }
+}
+
+fn model(app: &App) -> Model {
+    let config = Config::load();
+    app.new_window()
+        .surface_conf_builder(pacing::surface(&config))
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    pacing::apply(app, &config);
+
+    let [width, height] = app.window_rect().wh().to_array().map(|side| side as u32);
+    let texture = wgpu::TextureBuilder::new()
+        .size([width, height])
+        .format(wgpu::TextureFormat::Rgba8UnormSrgb)
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .build(app.main_window().device());
+    let seed = crate::seed::resolve(&config);
+
+    Model {
+        physarum: Physarum::new(&config, seed, width as usize, height as usize),
+        config,
+        texture,
+        paused: false,
+        capture_next: false,
+        pacer: Pacer::new(),
+    }
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.pacer.wait(&model.config);
+
+    if std::mem::take(&mut model.capture_next) {
+        let path = crate::export::capture_path(model.physarum.seed);
+        log::info!("Saving {}", path.display());
+        Metadata::new(&model.config, model.physarum.seed).save_sidecar(&path);
+        app.main_window().capture_frame(path);
+    }
+
+    // `speed` steps per frame, at least one unless paused
+    if !model.paused {
+        for _ in 0..(model.config.speed.round() as usize).max(1) {
+            model.physarum.step(&model.config);
+        }
+    }
+
+    let window = app.main_window();
+    let (device, queue) = (window.device(), window.queue());
+    let descriptor = wgpu::CommandEncoderDescriptor {
+        label: Some("physarum"),
+    };
+    let mut encoder = device.create_command_encoder(&descriptor);
+    let pixels = model.physarum.pixels(&model.config);
+    model.texture.upload_data(device, &mut encoder, &pixels);
+    queue.submit(Some(encoder.finish()));
+}
+
+fn exit(app: &App, model: Model) {
+    let (config, physarum) = (&model.config, &model.physarum);
+    crate::session::summarize(app, config, physarum.seed, physarum.steps, None);
+    if config.save_on_exit {
+        let (width, height) = (physarum.width as u32, physarum.height as u32);
+        let image = RgbaImage::from_raw(width, height, physarum.pixels(config)).unwrap();
+        crate::session::save(config, physarum.seed, &image);
+    }
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    match key {
+        Key::S => model.capture_next = true,
+        Key::Space => model.paused = !model.paused,
+        Key::R => {
+            let [width, height] = app.window_rect().wh().to_array().map(|side| side as usize);
+            let seed = seed::random(&mut rand::thread_rng());
+            log::info!("Seed: {}", seed);
+            model.physarum = Physarum::new(&model.config, seed, width, height);
+        }
+        _ => {}
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let draw = app.draw();
+    draw.texture(&model.texture).wh(app.window_rect().wh());
+    draw.to_frame(app, &frame).unwrap();
+}
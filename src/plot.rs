@@ -0,0 +1,232 @@
+use crate::config::Config;
+use crate::palette;
+use crate::particles::Simulation;
+use crate::streamline;
+use crate::symmetry::Symmetry;
+use crate::Vector2;
+use nannou::color::Srgb;
+use std::fmt::Write;
+use std::path::Path;
+
+const MIN_SEGMENT: f64 = 1.0; // points closer than this many pixels to the previous one are dropped
+const PEN_UP: f64 = 2.0; // pen height when travelling, in millimeters
+const PEN_DOWN: f64 = 0.0; // pen height when drawing
+const FEED_RATE: f64 = 3000.0; // drawing speed, in millimeters per minute
+
+// A particle trail in canvas pixels, with the layer it is plotted on
+struct Polyline {
+    layer: usize,
+    points: Vec<(f64, f64)>,
+}
+
+// Run the simulation headlessly recording every particle path, or trace the streamlines, then
+// export the paths for pen plotting
+pub fn export(config: &Config) {
+    let (width, height) = (config.render_width, config.render_height);
+    let seed = crate::seed::resolve(config);
+
+    let mut simulation = Simulation::new(config, seed, width as f64 / 2.0, height as f64 / 2.0);
+    let paths = match config.streamlines {
+        true => {
+            let streamlines = streamline::trace(config, &simulation);
+            let values = streamline::values(config, &simulation, &streamlines);
+            let points = streamlines.into_iter().map(|streamline| streamline.points);
+            points.zip(values).collect()
+        }
+        false => {
+            simulation.record_paths = true;
+            for _ in 0..config.render_steps {
+                simulation.spawn(config, crate::headless::STEP_DT);
+                simulation.step(config);
+            }
+            recorded(config, &simulation)
+        }
+    };
+
+    let polylines = polylines(config, &simulation, paths);
+    log::info!("Exporting {} paths", polylines.len());
+
+    if let Some(path) = &config.svg {
+        write(
+            &crate::seed::expand(path, seed),
+            &svg(config, &polylines, width, height),
+        );
+    }
+    if let Some(path) = &config.gcode {
+        write(
+            &crate::seed::expand(path, seed),
+            &gcode(config, &polylines, width, height),
+        );
+    }
+}
+
+// Every recorded path, finished or still being drawn, with the color value at its start
+fn recorded(config: &Config, simulation: &Simulation) -> Vec<(Vec<Vector2>, f64)> {
+    let live = simulation.flow_field.iter().map(|flow| {
+        let mut path = flow.path.clone();
+        path.push(flow.pos);
+        path
+    });
+
+    simulation
+        .paths
+        .iter()
+        .cloned()
+        .chain(live)
+        .filter_map(|path| {
+            let value = simulation.color_value(config, *path.first()?);
+            Some((path, value))
+        })
+        .collect()
+}
+
+// Split every path into polylines inside the canvas, thinned to `MIN_SEGMENT`, once per copy of
+// the symmetry. Each path goes on the layer of its color value.
+fn polylines(
+    config: &Config,
+    simulation: &Simulation,
+    paths: Vec<(Vec<Vector2>, f64)>,
+) -> Vec<Polyline> {
+    let (width, height) = (simulation.x_end * 2.0, simulation.y_end * 2.0);
+    let layers = config.plot_layers.max(1);
+    let symmetry = Symmetry::new(config.symmetry, config.mirror);
+    let mut polylines = Vec::new();
+
+    for (path, value) in paths {
+        let layer = ((value * layers as f64) as usize).min(layers - 1);
+
+        for copy in 0..symmetry.count() {
+            let mut points: Vec<(f64, f64)> = Vec::new();
+            for pos in &path {
+                // map to canvas pixels, flipping y so up is up
+                let point = (pos.x * 2.0, (simulation.y_end - pos.y) * 2.0);
+                let [x, y] = symmetry.image(
+                    copy,
+                    [point.0 - simulation.x_end, point.1 - simulation.y_end],
+                );
+                let point = (x + simulation.x_end, y + simulation.y_end);
+                let inside = (0.0..=width).contains(&point.0) && (0.0..=height).contains(&point.1);
+
+                if !inside {
+                    if points.len() >= 2 {
+                        polylines.push(Polyline { layer, points });
+                    }
+                    points = Vec::new();
+                    continue;
+                }
+
+                match points.last() {
+                    Some(last) if distance(*last, point) < MIN_SEGMENT => {}
+                    _ => points.push(point),
+                }
+            }
+
+            if points.len() >= 2 {
+                polylines.push(Polyline { layer, points });
+            }
+        }
+    }
+
+    polylines
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    Vector2 {
+        x: a.0 - b.0,
+        y: a.1 - b.1,
+    }
+    .length()
+}
+
+// Color of a layer, at the center of its slice of the palette
+fn layer_color(config: &Config, layer: usize) -> String {
+    let layers = config.plot_layers.max(1);
+    let color = palette::color(config, (layer as f64 + 0.5) / layers as f64);
+    let color: Srgb<u8> = Srgb::from_linear(color).into_format();
+
+    format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue)
+}
+
+// One Inkscape layer per color, so each can be plotted with its own pen
+fn svg(config: &Config, polylines: &[Polyline], width: u32, height: u32) -> String {
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        width, height
+    )
+    .unwrap();
+
+    for layer in 0..config.plot_layers.max(1) {
+        writeln!(
+            svg,
+            r#"<g id="layer{0}" inkscape:groupmode="layer" inkscape:label="{0}" fill="none" stroke="{1}" stroke-width="{2}" stroke-linecap="round" stroke-linejoin="round">"#,
+            layer + 1,
+            layer_color(config, layer),
+            config.stroke_width
+        )
+        .unwrap();
+
+        for polyline in polylines.iter().filter(|polyline| polyline.layer == layer) {
+            let points: Vec<String> = polyline
+                .points
+                .iter()
+                .map(|(x, y)| format!("{:.2},{:.2}", x, y))
+                .collect();
+            writeln!(svg, r#"<polyline points="{}"/>"#, points.join(" ")).unwrap();
+        }
+
+        writeln!(svg, "</g>").unwrap();
+    }
+
+    writeln!(svg, "</svg>").unwrap();
+    svg
+}
+
+// Scaled to `plot_width` millimeters with the origin at the bottom left, pausing for a pen change
+// before every layer
+fn gcode(config: &Config, polylines: &[Polyline], width: u32, height: u32) -> String {
+    let scale = config.plot_width / width as f64;
+    let to_machine = |(x, y): (f64, f64)| (x * scale, (height as f64 - y) * scale);
+
+    let mut gcode = String::new();
+    writeln!(gcode, "G21 ; millimeters").unwrap();
+    writeln!(gcode, "G90 ; absolute positioning").unwrap();
+    writeln!(gcode, "G0 Z{:.2}", PEN_UP).unwrap();
+
+    for layer in 0..config.plot_layers.max(1) {
+        writeln!(
+            gcode,
+            "; layer {} ({})",
+            layer + 1,
+            layer_color(config, layer)
+        )
+        .unwrap();
+        writeln!(gcode, "M0 ; change pen").unwrap();
+
+        for polyline in polylines.iter().filter(|polyline| polyline.layer == layer) {
+            let (x, y) = to_machine(polyline.points[0]);
+            writeln!(gcode, "G0 X{:.3} Y{:.3}", x, y).unwrap();
+            writeln!(gcode, "G1 Z{:.2} F{:.0}", PEN_DOWN, FEED_RATE).unwrap();
+
+            for &point in &polyline.points[1..] {
+                let (x, y) = to_machine(point);
+                writeln!(gcode, "G1 X{:.3} Y{:.3}", x, y).unwrap();
+            }
+
+            writeln!(gcode, "G0 Z{:.2}", PEN_UP).unwrap();
+        }
+    }
+
+    writeln!(gcode, "G0 X0 Y0").unwrap();
+    gcode
+}
+
+fn write(path: &Path, contents: &str) {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory).unwrap();
+    }
+
+    std::fs::write(path, contents).unwrap();
+    log::info!("Saved {}", path.display());
+}
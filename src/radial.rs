@@ -0,0 +1,55 @@
+use crate::config::Config;
+use crate::field::VectorField;
+use crate::Vector2;
+use serde::{Deserialize, Serialize};
+
+// Analytic field around a center point
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Radial {
+    Vortex, // circling counterclockwise
+    Source, // flowing straight out
+    Sink,   // flowing straight in
+    Dipole, // looping out of one side and back into the other, like a bar magnet
+    Spiral, // circling while drawn in
+}
+
+impl Radial {
+    // Direction of the flow at an offset from the center, in centered units
+    pub fn vector(self, [x, y]: [f64; 2]) -> Vector2 {
+        let r = x.hypot(y);
+        if r == 0.0 {
+            return Vector2 { x: 0.0, y: 0.0 };
+        }
+        let (outward, around) = (Vector2 { x, y } / r, Vector2 { x: -y, y: x } / r);
+
+        match self {
+            Radial::Vortex => around,
+            Radial::Source => outward,
+            Radial::Sink => outward * -1.0,
+            // derivative of the complex potential `-1 / z`, the dipole pointing along x
+            Radial::Dipole => Vector2 {
+                x: (x * x - y * y) / r.powi(4),
+                y: 2.0 * x * y / r.powi(4),
+            },
+            Radial::Spiral => (around + outward * -1.0) / std::f64::consts::SQRT_2,
+        }
+    }
+}
+
+// A radial primitive as the main field, centered like expressions so the shorter side of the
+// canvas spans -1 to 1
+pub struct RadialField {
+    pub radial: Radial,
+    pub center: [f64; 2],
+}
+
+impl VectorField for RadialField {
+    fn vector(&self, _config: &Config, pos: Vector2, extent: Vector2, _steps: u64) -> Vector2 {
+        let scale = extent.x.min(extent.y);
+        self.radial.vector([
+            (pos.x - extent.x / 2.0) * 2.0 / scale - self.center[0],
+            (pos.y - extent.y / 2.0) * 2.0 / scale - self.center[1],
+        ])
+    }
+}
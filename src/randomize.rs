@@ -0,0 +1,53 @@
+use crate::config::Config;
+use crate::gui::name;
+use crate::modulation::Parameter;
+use crate::palette::PaletteKind;
+use crate::seed;
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+// A copy of the config with every parameter picked at random within its range, along with the
+// noise, the field and the palette, and a fresh seed. The picks are printed so a piece worth
+// keeping can be reproduced or saved as a preset.
+pub fn randomize(config: &Config) -> Config {
+    let mut rng = rand::thread_rng();
+    let mut config = config.clone();
+
+    for &parameter in Parameter::value_variants() {
+        let (min, max) = parameter.range();
+        parameter.set(&mut config, rng.gen_range(min..=max));
+    }
+    config.noise_kind = pick(&mut rng);
+    config.field_kind = pick(&mut rng);
+    config.palette_mapping = pick(&mut rng);
+    config.color_space = pick(&mut rng);
+    // custom gradients need stops to go through
+    config.palette = *[PaletteKind::Hue, PaletteKind::Viridis, PaletteKind::Magma]
+        .choose(&mut rng)
+        .unwrap();
+    config.seed = seed::random(&mut rng);
+
+    log::info!("Randomized: {}", describe(&config));
+    config
+}
+
+fn pick<T: ValueEnum + Copy>(rng: &mut impl Rng) -> T {
+    *T::value_variants().choose(rng).unwrap()
+}
+
+// The randomized parameters, e.g. `noise worley, field curl, ..., noise-factor 12.5000, ...`
+pub fn describe(config: &Config) -> String {
+    let mut picks = vec![
+        format!("seed {}", config.seed),
+        format!("noise {}", name(config.noise_kind)),
+        format!("field {}", name(config.field_kind)),
+        format!("palette {}", name(config.palette)),
+        format!("palette-mapping {}", name(config.palette_mapping)),
+        format!("color-space {}", name(config.color_space)),
+    ];
+    for &parameter in Parameter::value_variants() {
+        picks.push(format!("{} {:.4}", name(parameter), parameter.get(config)));
+    }
+    picks.join(", ")
+}
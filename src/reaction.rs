@@ -0,0 +1,220 @@
+use crate::config::Config;
+use crate::field::{FieldKind, VectorField};
+use crate::palette;
+use crate::Vector2;
+use nannou::image::{DynamicImage, RgbaImage};
+use nannou::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use rayon::prelude::*;
+use std::sync::{Arc, RwLock};
+
+const DIFFUSION_U: f32 = 1.0; // diffusion rate of the substrate
+const DIFFUSION_V: f32 = 0.5; // diffusion rate of the catalyst
+const SEED_SPOTS: usize = 24; // squares of catalyst the grid starts with
+const SPOT_RADIUS: i64 = 3; // in cells
+
+// Parameters the reaction is computed from, unless it is off
+#[derive(Clone, PartialEq)]
+pub struct ReactionConfig {
+    resolution: u32,
+    feed: f32,
+    kill: f32,
+    speed: u32,
+}
+
+impl ReactionConfig {
+    pub fn new(config: &Config) -> Option<Self> {
+        config.reaction.then(|| ReactionConfig {
+            resolution: config.reaction_resolution.max(2),
+            feed: config.reaction_feed,
+            kill: config.reaction_kill,
+            speed: config.reaction_speed,
+        })
+    }
+}
+
+// Gray-Scott grid of a substrate `u` fed in and a catalyst `v` killed off, reacting as
+// `u + 2v -> 3v` while both diffuse. Rows go up like positions do, wrapping around the edges.
+struct GrayScott {
+    width: usize,
+    height: usize,
+    u: Vec<f32>,
+    v: Vec<f32>,
+    iterations: u64,
+}
+
+impl GrayScott {
+    fn new(config: &ReactionConfig, seed: u32, aspect: f64) -> Self {
+        let resolution = config.resolution as f64;
+        let (width, height) = match aspect >= 1.0 {
+            true => (resolution, (resolution / aspect).round().max(2.0)),
+            false => ((resolution * aspect).round().max(2.0), resolution),
+        };
+        let (width, height) = (width as usize, height as usize);
+
+        let mut u = vec![1.0; width * height];
+        let mut v = vec![0.0; width * height];
+        let mut rng = ChaCha12Rng::seed_from_u64(seed as u64);
+        for _ in 0..SEED_SPOTS {
+            let (x, y) = (
+                rng.gen_range(0..width as i64),
+                rng.gen_range(0..height as i64),
+            );
+            for dy in -SPOT_RADIUS..=SPOT_RADIUS {
+                for dx in -SPOT_RADIUS..=SPOT_RADIUS {
+                    let column = (x + dx).rem_euclid(width as i64) as usize;
+                    let row = (y + dy).rem_euclid(height as i64) as usize;
+                    u[row * width + column] = 0.5;
+                    v[row * width + column] = 0.25;
+                }
+            }
+        }
+
+        GrayScott {
+            width,
+            height,
+            u,
+            v,
+            iterations: 0,
+        }
+    }
+
+    fn iterate(&mut self, config: &ReactionConfig) {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (u, v) = (&self.u, &self.v);
+        let (feed, kill) = (config.feed, config.kill);
+
+        // Laplacian by a 3x3 kernel weighting the edges by 0.2 and the corners by 0.05
+        let laplacian = |values: &[f32], x: i64, y: i64| {
+            let at = |dx: i64, dy: i64| {
+                values[((y + dy).rem_euclid(h) * w + (x + dx).rem_euclid(w)) as usize]
+            };
+            (at(-1, 0) + at(1, 0) + at(0, -1) + at(0, 1)) * 0.2
+                + (at(-1, -1) + at(1, -1) + at(-1, 1) + at(1, 1)) * 0.05
+                - at(0, 0)
+        };
+
+        let next: Vec<(f32, f32)> = (0..self.width * self.height)
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = (i as i64 % w, i as i64 / w);
+                let (a, b) = (u[i], v[i]);
+                let reaction = a * b * b;
+                (
+                    (a + DIFFUSION_U * laplacian(u, x, y) - reaction + feed * (1.0 - a))
+                        .clamp(0.0, 1.0),
+                    (b + DIFFUSION_V * laplacian(v, x, y) + reaction - (kill + feed) * b)
+                        .clamp(0.0, 1.0),
+                )
+            })
+            .collect();
+        (self.u, self.v) = next.into_iter().unzip();
+        self.iterations += 1;
+    }
+
+    // Catalyst at a normalized position, interpolated bilinearly between cell centers
+    fn catalyst(&self, s: f64, t: f64) -> f64 {
+        let x = s * self.width as f64 - 0.5;
+        let y = t * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+
+        let at = |x: f64, y: f64| {
+            let column = (x as i64).rem_euclid(self.width as i64) as usize;
+            let row = (y as i64).rem_euclid(self.height as i64) as usize;
+            self.v[row * self.width + column] as f64
+        };
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        lerp(
+            lerp(at(x0, y0), at(x0 + 1.0, y0), tx),
+            lerp(at(x0, y0 + 1.0), at(x0 + 1.0, y0 + 1.0), tx),
+            ty,
+        )
+    }
+}
+
+// Reaction advanced along with the simulation, `reaction_speed` iterations per step, so its
+// state is a function of the seed and the step like the rest of the simulation
+pub struct Reaction {
+    config: ReactionConfig,
+    seed: u32,
+    aspect: f64, // width over height of the canvas the grid spans
+    grid: RwLock<GrayScott>,
+}
+
+impl Reaction {
+    pub fn build(config: &Config, noise_seed: u32, extent: Vector2) -> Option<Arc<Self>> {
+        let config = ReactionConfig::new(config)?;
+        let aspect = extent.x / extent.y;
+        Some(Arc::new(Reaction {
+            grid: RwLock::new(GrayScott::new(&config, noise_seed, aspect)),
+            config,
+            seed: noise_seed,
+            aspect,
+        }))
+    }
+
+    // Bring the grid to `steps` simulation steps, starting over if it is past them. Called before
+    // the flows are stepped so they never wait on it.
+    pub fn advance(&self, steps: u64) {
+        let iterations = steps * self.config.speed as u64;
+        let mut grid = self.grid.write().unwrap();
+        if grid.iterations > iterations {
+            *grid = GrayScott::new(&self.config, self.seed, self.aspect);
+        }
+        while grid.iterations < iterations {
+            grid.iterate(&self.config);
+        }
+    }
+
+    // The catalyst colored by the palette with black where there is none, for compositing over
+    // the canvas
+    pub fn texture<T: wgpu::WithDeviceQueuePair>(&self, src: T, config: &Config) -> wgpu::Texture {
+        let grid = self.grid.read().unwrap();
+        let (width, height) = (grid.width as u32, grid.height as u32);
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            let value = grid.v[(height - 1 - y) as usize * grid.width + x as usize];
+            let t = (value * 2.0).clamp(0.0, 1.0);
+            let color = palette::color(config, t as f64) * t * config.reaction_strength;
+            let color: Srgb<u8> = Srgb::from_linear(color).into_format();
+            [color.red, color.green, color.blue, 255].into()
+        });
+        wgpu::Texture::from_image(src, &DynamicImage::ImageRgba8(image))
+    }
+}
+
+// Field blended with the gradient of the catalyst, flowing up it with the `angle` field kind or
+// along its contours with `curl`, both normalized and summed by weight
+pub struct ReactionField {
+    pub field: Box<dyn VectorField>,
+    pub reaction: Arc<Reaction>,
+}
+
+impl VectorField for ReactionField {
+    fn vector(&self, config: &Config, pos: Vector2, extent: Vector2, steps: u64) -> Vector2 {
+        let unit = |vector: Vector2| match vector.length() {
+            length if length > 0.0 => vector / length,
+            _ => vector,
+        };
+
+        let grid = self.reaction.grid.read().unwrap();
+        let (s, t) = (pos.x / extent.x, pos.y / extent.y);
+        let (es, et) = (1.0 / grid.width as f64, 1.0 / grid.height as f64); // one cell
+        let gradient = Vector2 {
+            x: (grid.catalyst(s + es, t) - grid.catalyst(s - es, t)) / (2.0 * es * extent.x),
+            y: (grid.catalyst(s, t + et) - grid.catalyst(s, t - et)) / (2.0 * et * extent.y),
+        };
+        drop(grid);
+
+        let reaction = match config.field_kind {
+            FieldKind::Angle => gradient,
+            FieldKind::Curl => Vector2 {
+                x: gradient.y,
+                y: -gradient.x,
+            },
+        };
+        unit(self.field.vector(config, pos, extent, steps)) * config.field_weight
+            + unit(reaction) * config.reaction_weight
+    }
+}
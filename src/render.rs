@@ -0,0 +1,307 @@
+use crate::accumulation::AccumulationBuffer;
+use crate::config::Config;
+use crate::layer::BlendMode;
+use crate::palette::{self, HexColor};
+use crate::particles::Simulation;
+use crate::ribbon::RenderMode;
+use crate::streamline::{self, Streamline};
+use crate::symmetry::Symmetry;
+use nannou::prelude::*;
+use rayon::prelude::*;
+
+// Global alpha of a loop of `frames` frames at `frame`, easing in from 0 over the first portion,
+// holding at 1 and easing back out to 0 over the last portion so the loop point is invisible.
+// Config files skip the flags' checks, so loops without frames or fades don't fade at all.
+pub fn envelope(frame: u64, frames: u64, fade_portion: f64) -> f64 {
+    if frames == 0 || fade_portion.is_nan() || fade_portion <= 0.0 {
+        return 1.0;
+    }
+
+    let t = frame as f64 / frames as f64;
+    let smoothstep = |x: f64| {
+        let x = x.clamp(0.0, 1.0);
+        x * x * (3.0 - 2.0 * x)
+    };
+
+    smoothstep(t.min(1.0 - t) / fade_portion)
+}
+
+pub fn accumulation(
+    device: &wgpu::Device,
+    config: &Config,
+    x_end: f64,
+    y_end: f64,
+) -> Option<(AccumulationBuffer, wgpu::Texture)> {
+    if !config.additive_hue {
+        return None;
+    }
+
+    let (width, height) = ((x_end * 2.0) as u32, (y_end * 2.0) as u32);
+    let texture = wgpu::TextureBuilder::new()
+        .size([width, height])
+        .format(wgpu::TextureFormat::Rgba8UnormSrgb)
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .build(device);
+
+    Some((
+        AccumulationBuffer::new(width as usize, height as usize),
+        texture,
+    ))
+}
+
+// Global alpha of the particles at the current step, fading them in and out when looping
+pub fn loop_alpha(config: &Config, simulation: &Simulation) -> f32 {
+    match config.loop_frames {
+        Some(frames) => envelope(simulation.steps, frames, config.loop_fade_portion) as f32,
+        None => 1.0,
+    }
+}
+
+// Draw one step of the simulation: the loop fade, then either the tone mapped additive buffer or
+// the particles themselves, unless they are drawn by the compute shader
+pub fn draw_simulation(
+    draw: &Draw,
+    config: &Config,
+    simulation: &Simulation,
+    accumulation: Option<&wgpu::Texture>,
+    particles: bool,
+) {
+    let alpha = loop_alpha(config, simulation);
+
+    let translucent = |color: HexColor, alpha: f32| {
+        let color = color.linear();
+        lin_srgba(color.red, color.green, color.blue, alpha)
+    };
+
+    // Fade out older trails so only the recent ones remain, the additive buffer fades itself
+    if config.trail_fade > 0.0 && accumulation.is_none() {
+        let color = config.fade_color.unwrap_or(config.background);
+        draw.rect()
+            .wh(simulation.size())
+            .color(translucent(color, config.trail_fade));
+    }
+
+    // Fade the accumulated trails along with the particles
+    if alpha < 1.0 {
+        draw.rect()
+            .wh(simulation.size())
+            .color(translucent(config.background, 1.0 - alpha));
+    }
+
+    match accumulation {
+        Some(texture) => {
+            draw.texture(texture).wh(simulation.size());
+        }
+        None if particles => draw_particles(draw, config, simulation, alpha),
+        None => {}
+    }
+}
+
+// Draw one step of a species onto its layer, fading its trails towards the layer's neutral color
+pub fn draw_layer(
+    draw: &Draw,
+    config: &Config,
+    simulation: &Simulation,
+    blend: BlendMode,
+    alpha: f32,
+    particles: bool,
+) {
+    if config.trail_fade > 0.0 {
+        let neutral = blend.neutral();
+        draw.rect().wh(simulation.size()).color(lin_srgba(
+            neutral.red,
+            neutral.green,
+            neutral.blue,
+            config.trail_fade,
+        ));
+    }
+
+    if particles {
+        draw_particles(draw, config, simulation, alpha);
+    }
+}
+
+// Add the current particle positions to the additive buffer, fading its older trails
+pub fn splat_accumulation(
+    buffer: &mut AccumulationBuffer,
+    config: &Config,
+    simulation: &Simulation,
+) {
+    buffer.fade(config.trail_fade);
+    splat_particles(buffer, config, simulation);
+}
+
+pub fn splat_particles(buffer: &mut AccumulationBuffer, config: &Config, simulation: &Simulation) {
+    let symmetry = Symmetry::new(config.symmetry, config.mirror);
+    let (x_end, y_end) = (simulation.x_end, simulation.y_end);
+
+    for flow in simulation.flow_field.iter() {
+        // map to buffer pixels, flipping y so up is up
+        let (x, y) = (flow.pos.x * 2.0, (y_end - flow.pos.y) * 2.0);
+        let color = simulation.color(config, flow);
+
+        for i in 0..symmetry.count() {
+            let [x, y] = symmetry.image(i, [x - x_end, y - y_end]); // around the center
+            buffer.splat(x_end + x, y_end + y, color);
+        }
+    }
+}
+
+// Tone map the additive buffer into its texture
+pub fn upload_accumulation<T: wgpu::WithDeviceQueuePair>(
+    src: T,
+    buffer: &AccumulationBuffer,
+    texture: &wgpu::Texture,
+    exposure: f32,
+) {
+    src.with_device_queue_pair(|device, queue| {
+        let descriptor = wgpu::CommandEncoderDescriptor {
+            label: Some("accumulation"),
+        };
+        let mut encoder = device.create_command_encoder(&descriptor);
+        texture.upload_data(device, &mut encoder, &buffer.tone_map(exposure));
+        queue.submit(Some(encoder.finish()));
+    });
+}
+
+pub fn draw_particles(draw: &Draw, config: &Config, simulation: &Simulation, alpha: f32) {
+    // Every particle is appended to a single mesh rather than drawn separately, built in parallel
+    let symmetry = Symmetry::new(config.symmetry, config.mirror);
+    let mesh: Vec<(Vec3, LinSrgba)> = simulation
+        .flow_field
+        .par_iter()
+        .flat_map_iter(|flow| {
+            let point = simulation.canvas_point(simulation.drawn_pos(flow));
+
+            // Choose a color based on screen position
+            let rgb = simulation.color(config, flow);
+            let color = lin_srgba(rgb.red, rgb.green, rgb.blue, alpha);
+
+            let (point_size, path_width) = match simulation.stroke_size(config, flow) {
+                Some(size) => (size, size),
+                None => (config.point_size, config.path_width),
+            };
+            let vertices = match config.render_mode {
+                RenderMode::Points => {
+                    let heading = flow.vel.y.atan2(flow.vel.x) as f32;
+                    let vertices =
+                        config
+                            .particle_shape
+                            .vertices(point, point_size as f32, heading);
+                    vertices.into_iter().map(|vertex| (vertex, 1.0)).collect()
+                }
+                mode => {
+                    let mut points: Vec<Vec2> = flow
+                        .tail
+                        .iter()
+                        .map(|&pos| simulation.canvas_point(pos))
+                        .collect();
+                    points.push(point);
+                    mode.vertices(&points, path_width as f32)
+                }
+            };
+
+            // fading towards the tail of the path
+            let falloff = config.path_falloff as f32;
+            symmetry
+                .copies(vertices)
+                .into_iter()
+                .map(move |(vertex, t)| {
+                    let color = match falloff > 0.0 {
+                        true => {
+                            lin_srgba(color.red, color.green, color.blue, alpha * t.powf(falloff))
+                        }
+                        false => color,
+                    };
+                    (vertex.extend(0.0), color)
+                })
+        })
+        .collect();
+
+    if !mesh.is_empty() {
+        draw.mesh().points_colored(mesh);
+    }
+}
+
+// Draw traced streamlines all at once as lines, or as ribbons tapering towards where they started
+pub fn draw_streamlines(
+    draw: &Draw,
+    config: &Config,
+    simulation: &Simulation,
+    streamlines: &[Streamline],
+) {
+    let symmetry = Symmetry::new(config.symmetry, config.mirror);
+    let mode = match config.render_mode {
+        RenderMode::Points => RenderMode::Lines,
+        mode => mode,
+    };
+    let values = streamline::values(config, simulation, streamlines);
+
+    let mesh: Vec<(Vec3, LinSrgba)> = streamlines
+        .par_iter()
+        .zip(values)
+        .flat_map_iter(|(streamline, value)| {
+            let rgb = palette::color(config, value);
+            let points: Vec<Vec2> = streamline
+                .points
+                .iter()
+                .map(|&pos| simulation.canvas_point(pos))
+                .collect();
+            let vertices = mode.vertices(&points, config.path_width as f32);
+
+            // fading towards the start of the streamline
+            let falloff = config.path_falloff as f32;
+            symmetry
+                .copies(vertices)
+                .into_iter()
+                .map(move |(vertex, t)| {
+                    let alpha = match falloff > 0.0 {
+                        true => t.powf(falloff),
+                        false => 1.0,
+                    };
+                    (
+                        vertex.extend(0.0),
+                        lin_srgba(rgb.red, rgb.green, rgb.blue, alpha),
+                    )
+                })
+        })
+        .collect();
+
+    if !mesh.is_empty() {
+        draw.mesh().points_colored(mesh);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loop_alpha_endpoints_and_midpoint() {
+        let config = Config {
+            loop_frames: Some(100),
+            loop_fade_portion: 0.2,
+            ..Config::default()
+        };
+        let mut simulation = Simulation::new(&config, 1, 100.0, 100.0);
+        let mut alpha_at = |steps: u64| {
+            simulation.steps = steps;
+            loop_alpha(&config, &simulation)
+        };
+
+        assert_eq!(alpha_at(0), 0.0);
+        assert_eq!(alpha_at(50), 1.0);
+        assert_eq!(alpha_at(100), 0.0);
+        assert_eq!(alpha_at(10), 0.5); // half way through the fade in
+
+        let unlooped = Config {
+            loop_frames: None,
+            ..config
+        };
+        assert_eq!(loop_alpha(&unlooped, &simulation), 1.0);
+
+        // config files can hold what the flags reject
+        assert_eq!(envelope(0, 0, 0.2), 1.0);
+        assert_eq!(envelope(0, 100, 0.0), 1.0);
+    }
+}
@@ -0,0 +1,369 @@
+use crate::config::Config;
+use crate::particles::{Attractor, Simulation};
+use crate::seed;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+// Keyboard commands recorded and replayed. Keys that change the config, like reseeding or the
+// speed, are replayed through the recorded config instead, and saving files isn't replayed.
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Command {
+    TogglePanel,
+    ToggleStats,
+    ToggleField,
+    TogglePause,
+    StepOnce,
+    Rewind,
+    ToggleFastForward,
+    ResetCamera,
+    Clear,
+    Reset, // the button on the panel
+}
+
+// Everything an interactive session started from, the first line of a replay file
+#[derive(Serialize, Deserialize)]
+struct Header {
+    seed: u64,
+    size: [f32; 2], // of the window, in points
+    config: Config,
+}
+
+// What happened during one frame that isn't a function of the seed and the config, a line of a
+// replay file each
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct FrameInput {
+    pub steps: usize, // taken this frame
+    pub dt: f64,      // simulated seconds per step, for spawning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<[f32; 2]>, // of the window, when resized
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<Command>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Box<Config>>, // when edited on the panel, by a controller or a key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attractor: Option<Attractor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<[f32; 3]>, // pan and zoom, when moved
+}
+
+// Writes a replay file one frame at a time, flushing every frame so a crash keeps the session
+pub struct Recorder {
+    writer: BufWriter<File>,
+    pub frame: FrameInput, // being recorded, commands are added to it as they happen
+    config: Config,        // last recorded
+    camera: [f32; 3],
+}
+
+impl Recorder {
+    pub fn create(path: &Path, config: &Config, seed: u64, size: [f32; 2]) -> Self {
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).unwrap();
+        }
+        let file = File::create(path)
+            .unwrap_or_else(|err| panic!("Could not record to {}: {}", path.display(), err));
+        let mut recorder = Recorder {
+            writer: BufWriter::new(file),
+            frame: FrameInput::default(),
+            config: Config {
+                seed,
+                ..config.clone()
+            },
+            camera: [0.0, 0.0, 1.0],
+        };
+
+        let header = Header {
+            seed,
+            size,
+            config: config.clone(),
+        };
+        recorder.write(&header);
+        log::info!("Recording input to {}", path.display());
+        recorder
+    }
+
+    // Finish the frame, recording the config and the camera only if they changed. The config is
+    // recorded with the seed the simulation resolved, so that replaying an edit to it doesn't
+    // resolve a default seed to a new random one.
+    pub fn record(&mut self, config: &Config, seed: u64, camera: [f32; 3]) {
+        let mut frame = std::mem::take(&mut self.frame);
+        let config = Config {
+            seed,
+            ..config.clone()
+        };
+        if config != self.config {
+            frame.config = Some(Box::new(config.clone()));
+            self.config = config;
+        }
+        if camera != self.camera {
+            frame.camera = Some(camera);
+            self.camera = camera;
+        }
+        self.write(&frame);
+    }
+
+    fn write<T: Serialize>(&mut self, line: &T) {
+        serde_json::to_writer(&mut self.writer, line).unwrap();
+        self.writer.write_all(b"\n").unwrap();
+        self.writer.flush().unwrap();
+    }
+}
+
+// A recorded session, played back frame by frame
+pub struct Replay {
+    pub seed: u64,
+    pub size: [f32; 2],
+    config: Config,
+    frames: Vec<FrameInput>,
+    next: usize, // frame played next
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Self {
+        let file = File::open(path)
+            .unwrap_or_else(|err| panic!("Could not read replay {}: {}", path.display(), err));
+        let mut lines = BufReader::new(file).lines().map(Result::unwrap);
+        let invalid =
+            |err: serde_json::Error| format!("Invalid replay {}: {}", path.display(), err);
+
+        let header: Header = serde_json::from_str(&lines.next().unwrap_or_default())
+            .unwrap_or_else(|err| panic!("{}", invalid(err)));
+        let frames = lines
+            .map(|line| {
+                serde_json::from_str(&line).unwrap_or_else(|err| panic!("{}", invalid(err)))
+            })
+            .collect();
+
+        Replay {
+            seed: header.seed,
+            size: header.size,
+            config: header.config,
+            frames,
+            next: 0,
+        }
+    }
+
+    // The recorded config with the seed it resolved to, writing outputs like the command line
+    // asks rather than like the session did
+    pub fn config(&self, command_line: &Config) -> Config {
+        Config {
+            seed: self.seed,
+            render: command_line.render.clone(),
+            render_width: command_line.render_width,
+            render_height: command_line.render_height,
+            render_tile: command_line.render_tile,
+            sidecar: command_line.sidecar,
+            record: command_line.record.clone(),
+            record_frames: command_line.record_frames,
+            record_input: None,
+            replay_input: command_line.replay_input.clone(),
+            randomize: false,
+            resume: None,
+            ..self.config.clone()
+        }
+    }
+
+    pub fn frames(&self) -> &[FrameInput] {
+        &self.frames
+    }
+
+    // Next frame of the session, none once it's over
+    pub fn next_frame(&mut self) -> Option<FrameInput> {
+        let frame = self.frames.get(self.next).cloned();
+        self.next += 1;
+        frame
+    }
+}
+
+// What playing back a frame of input does, called by `play` in the order a frame happens in
+pub trait Player {
+    fn resize(&mut self, size: [f32; 2]); // of the window, in points
+    fn configure(&mut self, config: &Config);
+    fn command(&mut self, command: Command);
+    // Take `steps` steps, spawning `dt` seconds' worth each, with the mouse pulling if it did
+    fn steps(&mut self, steps: usize, dt: f64, attractor: Option<Attractor>);
+}
+
+// Play a frame of input back, the same way for an interactive session and a headless render
+pub fn play(player: &mut impl Player, frame: &FrameInput) {
+    if let Some(size) = frame.size {
+        player.resize(size);
+    }
+    if let Some(config) = frame.config.as_deref() {
+        player.configure(config);
+    }
+    for &command in &frame.commands {
+        player.command(command);
+    }
+    player.steps(frame.steps, frame.dt, frame.attractor.clone());
+}
+
+// What a `Playback` played, for drawing it
+#[derive(Copy, Clone, PartialEq)]
+pub enum Played {
+    Command(Command),
+    Step,
+}
+
+// A simulation played back on its own, calling `played` after every command and step so headless
+// renders can draw it
+pub struct Playback<F: FnMut(Played, &Simulation, &Config)> {
+    pub simulation: Simulation,
+    pub config: Config,       // the simulation runs with now
+    window: Option<[f32; 2]>, // size the session had, in points
+    played: F,
+}
+
+impl<F: FnMut(Played, &Simulation, &Config)> Playback<F> {
+    pub fn new(
+        simulation: Simulation,
+        config: &Config,
+        window: Option<[f32; 2]>,
+        played: F,
+    ) -> Self {
+        Playback {
+            simulation,
+            config: config.clone(),
+            window,
+            played,
+        }
+    }
+
+    pub fn finish(self) -> Simulation {
+        self.simulation
+    }
+}
+
+impl<F: FnMut(Played, &Simulation, &Config)> Player for Playback<F> {
+    fn resize(&mut self, size: [f32; 2]) {
+        self.window = Some(size);
+    }
+
+    fn configure(&mut self, config: &Config) {
+        reconfigure(&mut self.simulation, &self.config, config);
+        self.config = config.clone();
+    }
+
+    // Only resets change the simulation, the other commands only what the session showed
+    fn command(&mut self, command: Command) {
+        if command == Command::Reset {
+            self.simulation.run_to(&self.config, 0);
+        }
+        (self.played)(Played::Command(command), &self.simulation, &self.config);
+    }
+
+    fn steps(&mut self, steps: usize, dt: f64, attractor: Option<Attractor>) {
+        // the attractor is where the mouse was in the window, scaled onto this simulation
+        let simulation = &mut self.simulation;
+        let (window, scale) = (self.window, self.config.domain_scale);
+        simulation.attractor = attractor.map(|mut attractor| {
+            let [width, height] = window.unwrap().map(|points| points as f64 / 2.0);
+            attractor.pos.x *= simulation.x_end / (width * scale);
+            attractor.pos.y *= simulation.y_end / (height * scale);
+            attractor
+        });
+
+        for _ in 0..steps {
+            self.simulation.spawn(&self.config, dt);
+            self.simulation.step(&self.config);
+            (self.played)(Played::Step, &self.simulation, &self.config);
+        }
+    }
+}
+
+// Carry a replayed simulation over from `current` to a recorded config, reseeding it if the session
+// did
+pub fn reconfigure(simulation: &mut Simulation, current: &Config, next: &Config) {
+    match current.reseeds(next) {
+        true => {
+            let seed = seed::resolve(next);
+            let (x_end, y_end) = (simulation.x_end, simulation.y_end);
+            *simulation = Simulation::new(next, seed, x_end, y_end);
+        }
+        false => simulation.reconfigure(current, next),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector2;
+
+    const SEED: u64 = 12345;
+
+    fn step(simulation: &mut Simulation, config: &Config, steps: usize, dt: f64) {
+        for _ in 0..steps {
+            simulation.spawn(config, dt);
+            simulation.step(config);
+        }
+    }
+
+    #[test]
+    fn recorded_session_replays_to_same_state() {
+        let path = std::env::temp_dir().join(format!("replay-{}.jsonl", std::process::id()));
+
+        // a session from the default seed, with an edit on the panel, the mouse pulling and a reset
+        let mut config = Config {
+            point_count: 8,
+            spawn_rate: 20.0,
+            ..Config::default()
+        };
+        let mut simulation = Simulation::new(&config, SEED, 200.0, 150.0);
+        let mut recorder = Recorder::create(&path, &config, SEED, [400.0, 300.0]);
+        for frame in 0..6 {
+            if frame == 2 {
+                let edited = Config {
+                    heading_noise_factor: config.heading_noise_factor * 2.0,
+                    ..config.clone()
+                };
+                simulation.reconfigure(&config, &edited);
+                config = edited;
+            }
+            if frame == 4 {
+                simulation.run_to(&config, 0);
+                recorder.frame.commands.push(Command::Reset);
+            }
+            simulation.attractor = (frame == 5).then_some(Attractor {
+                pos: Vector2 { x: 50.0, y: 100.0 },
+                radius: 100.0,
+                strength: 2.0,
+            });
+            step(&mut simulation, &config, 5, 0.1);
+            recorder.frame.attractor = simulation.attractor.clone();
+            recorder.frame.steps = 5;
+            recorder.frame.dt = 0.1;
+            recorder.record(&config, simulation.seed, [0.0, 0.0, 1.0]);
+        }
+        drop(recorder);
+
+        let replay = Replay::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.seed, SEED);
+        assert_eq!(replay.frames().len(), 6);
+
+        let config = replay.config(&Config::default());
+        let simulation_replayed = Simulation::new(&config, replay.seed, 200.0, 150.0);
+        let (mut commands, mut steps) = (Vec::new(), 0);
+        let mut playback = Playback::new(
+            simulation_replayed,
+            &config,
+            Some(replay.size),
+            |played, _: &Simulation, _: &Config| match played {
+                Played::Command(command) => commands.push(command),
+                Played::Step => steps += 1,
+            },
+        );
+        for frame in replay.frames() {
+            play(&mut playback, frame);
+        }
+        let replayed = playback.finish();
+
+        let state =
+            |simulation: &Simulation| serde_json::to_string(&simulation.flow_field).unwrap();
+        assert_eq!(replayed.seed, SEED);
+        assert_eq!(state(&replayed), state(&simulation));
+        assert!(commands == [Command::Reset] && steps == 30);
+    }
+}
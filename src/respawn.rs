@@ -0,0 +1,59 @@
+use crate::coverage::CoverageMap;
+use crate::emitter::Emitter;
+use crate::Vector2;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Where a particle reappears once it reaches the end of its lifetime
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RespawnMode {
+    Random,   // anywhere in the window
+    Edge,     // anywhere along the border of the window, as if flowing in from outside
+    Grid,     // back where the particle started
+    Emitter,  // wherever the emitter places new particles
+    Coverage, // in the cells with the fewest particles, keeping coverage even
+}
+
+impl RespawnMode {
+    pub fn position(
+        self,
+        rng: &mut impl Rng,
+        origin: Vector2,
+        emitter: &Emitter,
+        coverage: Option<&CoverageMap>,
+        x_end: f64,
+        y_end: f64,
+    ) -> Vector2 {
+        match self {
+            RespawnMode::Random => Vector2 {
+                x: rng.gen_range(0.0..x_end),
+                y: rng.gen_range(0.0..y_end),
+            },
+            RespawnMode::Edge => {
+                // pick a point along the perimeter so every part of the border is equally likely
+                let along = rng.gen_range(0.0..(x_end + y_end) * 2.0);
+                match along {
+                    d if d < x_end => Vector2 { x: d, y: 0.0 },
+                    d if d < x_end * 2.0 => Vector2 {
+                        x: d - x_end,
+                        y: y_end,
+                    },
+                    d if d < x_end * 2.0 + y_end => Vector2 {
+                        x: 0.0,
+                        y: d - x_end * 2.0,
+                    },
+                    d => Vector2 {
+                        x: x_end,
+                        y: d - x_end * 2.0 - y_end,
+                    },
+                }
+            }
+            RespawnMode::Grid => origin,
+            RespawnMode::Emitter => emitter.position(rng, x_end, y_end),
+            RespawnMode::Coverage => coverage
+                .expect("Coverage respawning needs a coverage map")
+                .position(rng),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// How particles are drawn
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderMode {
+    Points,  // a shape at the current position
+    Lines,   // a polyline through the recent positions
+    Ribbons, // a polyline tapering towards its tail
+}
+
+impl RenderMode {
+    // Triangle list of a strip through `points`, oldest first, `width` wide at the newest one.
+    // Every vertex comes with how far along the path it is, 0 at the tail and 1 at the head.
+    pub fn vertices(self, points: &[Vec2], width: f32) -> Vec<(Vec2, f32)> {
+        if points.len() < 2 {
+            return Vec::new();
+        }
+
+        // Left and right edge at every point, offset along the normal of the path through it
+        let last = points.len() - 1;
+        let edges: Vec<(Vec2, Vec2, f32)> = (0..=last)
+            .map(|i| {
+                let t = i as f32 / last as f32;
+                let direction = (points[(i + 1).min(last)] - points[i.saturating_sub(1)])
+                    .try_normalize()
+                    .unwrap_or(Vec2::X);
+                let half_width = match self {
+                    RenderMode::Ribbons => width * t / 2.0,
+                    _ => width / 2.0,
+                };
+                let normal = direction.perp() * half_width;
+                (points[i] + normal, points[i] - normal, t)
+            })
+            .collect();
+
+        edges
+            .windows(2)
+            .flat_map(|pair| {
+                let ((l0, r0, t0), (l1, r1, t1)) = (pair[0], pair[1]);
+                [(l0, t0), (r0, t0), (l1, t1), (r0, t0), (r1, t1), (l1, t1)]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,272 @@
+use crate::config::{self, Config};
+use crate::field::VectorField;
+use crate::Vector2;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+#[cfg(feature = "script")]
+const MAX_OPERATIONS: u64 = 100_000; // per call, so a runaway loop can't hang the sketch
+
+// Heading and color defined by the functions of a Rhai script, like
+//
+//     fn heading(x, y, t, noise) {
+//         let swirl = sin(sqrt(x * x + y * y) * 4.0 - t);
+//         atan(y, x) + PI() / 2.0 + noise.sample(x * 2.0, y * 2.0) * swirl
+//     }
+//
+//     fn color(x, y, t, noise) {
+//         noise.sample(x, y, t) / 2.0 + 0.5
+//     }
+//
+// `x` and `y` are centered like in `--expression`, `t` is the time and `noise.sample` the heading
+// noise at two or three coordinates. `heading` returns the flow angle in radians counterclockwise
+// from +x, `color` the position in the palette. Scripts run sandboxed, without modules, `eval` or
+// any access to files, and every call is cut off after `MAX_OPERATIONS` operations.
+#[cfg(feature = "script")]
+pub struct Script {
+    engine: rhai::Engine,
+    ast: rhai::AST,
+    heading: bool, // whether the script defines `heading`
+    color: bool,   // whether the script defines `color`
+    noise: ScriptNoise,
+    failed: std::sync::atomic::AtomicBool, // whether a call failed since loading, logged once
+}
+
+// The heading noise, handed to the script functions as `noise`
+#[cfg(feature = "script")]
+#[derive(Clone)]
+struct ScriptNoise(Arc<dyn crate::noise::Noise>);
+
+#[cfg(feature = "script")]
+impl Script {
+    // Script for `config`, if it has one. A broken script stops the sketch from starting, later
+    // edits only take effect once they compile.
+    pub fn build(config: &Config, noise_seed: u32) -> Option<Arc<Self>> {
+        let path = config.script.as_deref()?;
+        let script = Script::load(config, path, noise_seed).unwrap_or_else(|err| panic!("{}", err));
+        Some(Arc::new(script))
+    }
+
+    pub fn load(config: &Config, path: &Path, noise_seed: u32) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| format!("Could not read script {}: {}", path.display(), err))?;
+
+        let mut engine = rhai::Engine::new();
+        engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+        engine.disable_symbol("eval");
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| log::info!("{}", text));
+        engine.on_debug(|text, _, position| log::debug!("{}: {}", position, text));
+        engine
+            .register_type_with_name::<ScriptNoise>("Noise")
+            .register_fn("sample", |noise: &mut ScriptNoise, x: f64, y: f64| {
+                noise.0.sample([x, y])
+            })
+            .register_fn(
+                "sample",
+                |noise: &mut ScriptNoise, x: f64, y: f64, z: f64| noise.0.sample_3d([x, y, z]),
+            );
+
+        let ast = engine
+            .compile(source)
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        let defines = |name: &str| {
+            ast.iter_functions()
+                .any(|function| function.name == name && function.params.len() == 4)
+        };
+        let (heading, color) = (defines("heading"), defines("color"));
+        if !heading && !color {
+            return Err(format!(
+                "Script {} defines neither `fn heading(x, y, t, noise)` nor `fn color(x, y, t, noise)`",
+                path.display()
+            ));
+        }
+
+        let noise = crate::noise::NoiseConfig::heading(config).build(noise_seed);
+        Ok(Script {
+            engine,
+            ast,
+            heading,
+            color,
+            noise: ScriptNoise(Arc::from(noise)),
+            failed: std::sync::atomic::AtomicBool::new(false),
+        })
+    }
+
+    pub fn has_heading(&self) -> bool {
+        self.heading
+    }
+
+    // Value the function `name` returns at a position, None if it fails. Only the first failure
+    // is logged, since it happens for every particle.
+    fn call(&self, name: &str, pos: Vector2, extent: Vector2, time: f64) -> Option<f64> {
+        let [x, y] = crate::expression::centered(pos, extent);
+        let options = rhai::CallFnOptions::new().eval_ast(false);
+        let args = (x, y, time, self.noise.clone());
+        let result =
+            self.engine
+                .call_fn_with_options::<rhai::Dynamic>(
+                    options,
+                    &mut rhai::Scope::new(),
+                    &self.ast,
+                    name,
+                    args,
+                )
+                .map_err(|err| err.to_string())
+                .and_then(|value| match value.as_float() {
+                    Ok(value) => Ok(value),
+                    Err(_) => value.as_int().map(|value| value as f64).map_err(|kind| {
+                        format!("`{}` returned {} rather than a number", name, kind)
+                    }),
+                });
+
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                if !self.failed.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                    log::warn!("Script failed: {}", err);
+                }
+                None
+            }
+        }
+    }
+
+    // Flow angle at a position, if the script defines `heading`
+    pub fn heading(&self, pos: Vector2, extent: Vector2, time: f64) -> Option<f64> {
+        match self.heading {
+            true => self.call("heading", pos, extent, time),
+            false => None,
+        }
+    }
+
+    // Palette position at a position, if the script defines `color`
+    pub fn color(&self, pos: Vector2, extent: Vector2, time: f64) -> Option<f64> {
+        let value = match self.color {
+            true => self.call("color", pos, extent, time)?,
+            false => return None,
+        };
+        Some(if value.is_finite() { value } else { 0.0 })
+    }
+}
+
+// Stand-in without the `script` feature
+#[cfg(not(feature = "script"))]
+pub struct Script;
+
+#[cfg(not(feature = "script"))]
+impl Script {
+    pub fn build(config: &Config, _noise_seed: u32) -> Option<Arc<Self>> {
+        if config.script.is_some() {
+            log::warn!("Built without scripting, rebuild with `--features script` to use --script");
+        }
+        None
+    }
+
+    pub fn load(_config: &Config, _path: &Path, _noise_seed: u32) -> Result<Self, String> {
+        Err("Built without scripting, rebuild with `--features script`".to_string())
+    }
+
+    pub fn has_heading(&self) -> bool {
+        false
+    }
+
+    pub fn heading(&self, _pos: Vector2, _extent: Vector2, _time: f64) -> Option<f64> {
+        None
+    }
+
+    pub fn color(&self, _pos: Vector2, _extent: Vector2, _time: f64) -> Option<f64> {
+        None
+    }
+}
+
+// Field along the script's `heading`
+pub struct ScriptField(pub Arc<Script>);
+
+impl VectorField for ScriptField {
+    fn vector(&self, config: &Config, pos: Vector2, extent: Vector2, steps: u64) -> Vector2 {
+        let time = steps as f64 * config.evolution_speed;
+
+        // a failing call or a heading dividing by zero stalls particles there rather than sending
+        // them off to NaN
+        match self.0.heading(pos, extent, time) {
+            Some(heading) if heading.is_finite() => {
+                let (sin, cos) = heading.sin_cos();
+                Vector2 { x: cos, y: sin }
+            }
+            _ => Vector2::default(),
+        }
+    }
+}
+
+// Watches the script for changes by polling its modification time, like the config file
+pub struct ScriptWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ScriptWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = config::modified(&path);
+        ScriptWatcher { path, modified }
+    }
+
+    // Whether the script changed since the last poll
+    pub fn poll(&mut self) -> bool {
+        let modified = config::modified(&self.path);
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+}
+
+#[cfg(all(test, feature = "script"))]
+mod tests {
+    use super::*;
+
+    // Load `source` from a file of its own, since tests run in parallel
+    fn load(source: &str) -> Result<Script, String> {
+        static FILES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let file = FILES.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let name = format!("script-{}-{}.rhai", std::process::id(), file);
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, source).unwrap();
+        let script = Script::load(&Config::default(), &path, 0);
+        std::fs::remove_file(&path).unwrap();
+        script
+    }
+
+    #[test]
+    fn calls_heading_and_color() {
+        let extent = Vector2 { x: 2.0, y: 2.0 };
+        let swirl = load(include_str!("../scripts/swirl.rhai")).unwrap();
+        assert!(swirl.has_heading());
+        assert!(swirl
+            .heading(Vector2 { x: 0.5, y: 1.5 }, extent, 1.0)
+            .unwrap()
+            .is_finite());
+        let color = swirl
+            .color(Vector2 { x: 0.5, y: 1.5 }, extent, 1.0)
+            .unwrap();
+        assert!((0.0..=1.0).contains(&color));
+
+        // integers are numbers too, and a script may define only one of the functions
+        let constant = load("fn color(x, y, t, noise) { 1 }").unwrap();
+        assert!(!constant.has_heading());
+        assert_eq!(constant.heading(Vector2::default(), extent, 0.0), None);
+        assert_eq!(constant.color(Vector2::default(), extent, 0.0), Some(1.0));
+    }
+
+    #[test]
+    fn rejects_broken_scripts() {
+        assert!(load("fn heading(x, y, t, noise) {").is_err());
+        assert!(load("fn other(x, y, t, noise) { 0.0 }").is_err());
+
+        // runaway loops are cut off rather than hanging the sketch
+        let endless = load("fn heading(x, y, t, noise) { loop {} }").unwrap();
+        assert_eq!(
+            endless.heading(Vector2::default(), Vector2::default(), 0.0),
+            None
+        );
+    }
+}
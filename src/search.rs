@@ -0,0 +1,114 @@
+use crate::config::Config;
+use crate::density::DensityGrid;
+use crate::particles::Simulation;
+use rand::{prelude::StdRng, SeedableRng};
+
+const SEARCH_DIRECTORY: &str = "search"; // directory the best scoring images are saved to
+const SCORE_BLOCKS: usize = 16; // number of blocks per axis the canvas is split into when scoring
+
+// Score a rendered density grid (higher is better)
+pub type Scorer = fn(&DensityGrid) -> f64;
+
+// Coverage uniformity: the fraction of pixels visited, weighted by how evenly the hits are spread
+// across coarse blocks of the canvas. An empty grid scores 0.
+pub fn score(grid: &DensityGrid) -> f64 {
+    let mut blocks = vec![0.0; SCORE_BLOCKS * SCORE_BLOCKS];
+    let mut covered = 0;
+
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let count = grid.cells[y * grid.width + x];
+            if count > 0 {
+                covered += 1;
+                blocks[y * SCORE_BLOCKS / grid.height * SCORE_BLOCKS
+                    + x * SCORE_BLOCKS / grid.width] += 1.0;
+            }
+        }
+    }
+
+    let mean = blocks.iter().sum::<f64>() / blocks.len() as f64;
+    if mean == 0.0 {
+        return 0.0;
+    }
+
+    let variance = blocks
+        .iter()
+        .map(|block| (block - mean).powi(2))
+        .sum::<f64>()
+        / blocks.len() as f64;
+    let evenness = 1.0 / (1.0 + variance.sqrt() / mean);
+    let coverage = covered as f64 / grid.cells.len() as f64;
+
+    coverage * evenness
+}
+
+// Run the simulation headlessly and accumulate every particle position into a density grid
+fn render(config: &Config, seed: u64) -> DensityGrid {
+    let (width, height) = (config.search_width, config.search_height);
+    let mut simulation = Simulation::new(config, seed, width as f64 / 2.0, height as f64 / 2.0);
+    let mut grid = DensityGrid::new(width as usize, height as usize);
+
+    for _ in 0..config.search_steps {
+        simulation.step(config);
+
+        for flow in simulation.flow_field.iter() {
+            // map to canvas pixels, flipping y so up is up
+            grid.splat(flow.pos.x * 2.0, (simulation.y_end - flow.pos.y) * 2.0);
+        }
+    }
+
+    grid
+}
+
+// Render `count` seeds, score each of them and save the best `search_top_k` as images. Fails if
+// the images have nowhere to go, an image that can't be saved is skipped.
+pub fn search(config: &Config, count: u32, score: Scorer) -> Result<(), String> {
+    let mut rng = StdRng::seed_from_u64(crate::seed::resolve(config));
+    let mut results: Vec<(f64, u64, DensityGrid)> = Vec::new();
+
+    for i in 0..count {
+        let seed = crate::seed::random(&mut rng);
+        let grid = render(config, seed);
+        let score = score(&grid);
+
+        log::info!("Seed {}/{}: {} (score {:.4})", i + 1, count, seed, score);
+
+        results.push((score, seed, grid));
+        results.sort_by(|a, b| b.0.total_cmp(&a.0));
+        results.truncate(config.search_top_k);
+    }
+
+    std::fs::create_dir_all(SEARCH_DIRECTORY)
+        .map_err(|err| format!("Could not create {}: {}", SEARCH_DIRECTORY, err))?;
+
+    for (rank, (score, seed, grid)) in results.iter().enumerate() {
+        let path = format!("{}/{:02}_{}.png", SEARCH_DIRECTORY, rank + 1, seed);
+        match grid.to_image().save(&path) {
+            Ok(()) => log::info!("#{}: {} (score {:.4}) -> {}", rank + 1, seed, score, path),
+            Err(err) => log::warn!("Could not save {}: {}", path, err),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn populated_grid_scores_above_empty_grid() {
+        let empty = DensityGrid::new(64, 64);
+
+        // a hit in every other pixel, spread over the whole canvas
+        let mut populated = DensityGrid::new(64, 64);
+        for y in (0..64).step_by(2) {
+            for x in (0..64).step_by(2) {
+                populated.splat(x as f64, y as f64);
+            }
+        }
+
+        assert_eq!(score(&empty), 0.0);
+        assert!(score(&populated) > score(&empty));
+    }
+}
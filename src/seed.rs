@@ -0,0 +1,107 @@
+use crate::config::Config;
+use rand::Rng;
+use std::path::{Path, PathBuf};
+
+pub const SEED_PLACEHOLDER: &str = "{seed}"; // replaced with the seed in output paths
+pub const MAX_SEED: u64 = i64::MAX as u64; // the largest integer config files hold
+
+// Seed from the config, or a genuinely random one if it is 0. Either way it is printed, so any run
+// can be reproduced by passing it back with `--seed`.
+pub fn resolve(config: &Config) -> u64 {
+    let seed = match config.seed {
+        0 => random(&mut rand::thread_rng()),
+        seed => seed,
+    };
+
+    log::info!("Seed: {} (noise seed {})", seed, noise_seed(seed));
+    seed
+}
+
+// Random seed between 1 and `MAX_SEED`, so it can be passed back on the command line and saved
+// in config files
+pub fn random(rng: &mut impl Rng) -> u64 {
+    rng.gen_range(1..=MAX_SEED)
+}
+
+// splitmix64 finalizer, every bit of the input affects every bit of the output
+pub fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+// Next number of a splitmix64 stream with state `state`, uniform between 0 and 1
+pub fn next_unit(state: &mut u64) -> f64 {
+    let value = splitmix64(*state);
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    (value >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// Noise functions only take a 32-bit seed. Hashing the full seed rather than truncating it keeps
+// seeds that only differ in their high bits from sharing a noise field.
+pub fn noise_seed(seed: u64) -> u32 {
+    (splitmix64(seed) >> 32) as u32
+}
+
+// Noise seed of the color noise, from its own seed if it has one
+pub fn color_noise_seed(config: &Config, seed: u64) -> u32 {
+    noise_seed(config.color_seed.unwrap_or(seed))
+}
+
+// Substitute the seed into an output path, e.g. `out/{seed}.png`
+pub fn expand(path: &Path, seed: u64) -> PathBuf {
+    PathBuf::from(
+        path.to_string_lossy()
+            .replace(SEED_PLACEHOLDER, &seed.to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn noise_seed_deterministic() {
+        for seed in [1, 42, u64::MAX] {
+            assert_eq!(noise_seed(seed), noise_seed(seed));
+        }
+
+        // truncating would map these to the same seed
+        assert_ne!(noise_seed(7), noise_seed(7 + (1 << 32)));
+    }
+
+    #[test]
+    fn noise_seed_distributed() {
+        const SEEDS: u64 = 10_000;
+        const BUCKETS: usize = 16;
+
+        // consecutive seeds, the worst case for a weak hash
+        let noise_seeds: Vec<u32> = (1..=SEEDS).map(noise_seed).collect();
+        let distinct: HashSet<_> = noise_seeds.iter().collect();
+        assert_eq!(distinct.len(), SEEDS as usize);
+
+        let mut buckets = [0; BUCKETS];
+        for noise_seed in noise_seeds {
+            buckets[((noise_seed as u64 * BUCKETS as u64) >> 32) as usize] += 1;
+        }
+        let expected = SEEDS as f64 / BUCKETS as f64;
+        for count in buckets {
+            assert!((count as f64 - expected).abs() < expected * 0.1);
+        }
+    }
+
+    #[test]
+    fn random_seeds_fit_config_files() {
+        let mut rng = rand::thread_rng();
+        for seed in (0..100).map(|_| random(&mut rng)).chain([MAX_SEED]) {
+            assert!(seed != 0);
+            let config = Config {
+                seed,
+                ..Config::default()
+            };
+            assert!(toml::to_string(&config).is_ok());
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::config::Config;
+use crate::metadata::Metadata;
+use crate::stats::Stats;
+use nannou::image::RgbaImage;
+use nannou::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false); // set by Ctrl+C
+
+// Catch Ctrl+C so the sketch quits like it does when its window closes, summarizing the session
+// instead of dying mid-frame. Only on Unix, elsewhere Ctrl+C still ends the process outright.
+pub fn catch_interrupt() {
+    #[cfg(unix)]
+    unsafe {
+        let handler: extern "C" fn(libc::c_int) = interrupt;
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+// Whether Ctrl+C was pressed since the sketch started
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+// Log what the session did once it ends, so the seed and the parameters that made the image
+// aren't lost with the window. Timings are averaged over the whole session.
+pub fn summarize(app: &App, config: &Config, seed: u64, steps: u64, stats: Option<&Stats>) {
+    log::info!(
+        "Session ended after {:.1} s, {} frames and {} steps",
+        app.duration.since_start.as_secs_f64(),
+        app.elapsed_frames(),
+        steps
+    );
+    log::info!("Seed: {}", seed);
+    log::info!("Parameters: {}", changed(config));
+    if let Some(stats) = stats {
+        let [update, draw, frame] = stats.session_ms();
+        log::info!(
+            "Average update {:.2} ms, draw {:.2} ms, frame {:.2} ms",
+            update,
+            draw,
+            frame
+        );
+    }
+}
+
+// Parameters that differ from their defaults, as `name = value` pairs
+fn changed(config: &Config) -> String {
+    let value = serde_json::to_value(config).unwrap();
+    let default = serde_json::to_value(Config::default()).unwrap();
+    let changed: Vec<String> = value
+        .as_object()
+        .unwrap()
+        .iter()
+        .filter(|(name, value)| default.get(name.as_str()) != Some(*value))
+        .map(|(name, value)| format!("{} = {}", name, value))
+        .collect();
+
+    match changed.is_empty() {
+        true => "defaults".to_string(),
+        false => changed.join(", "),
+    }
+}
+
+// Save the final frame with `save_on_exit`, into the capture directory with a TOML config next
+// to it that `--config` reproduces it with
+pub fn save(config: &Config, seed: u64, image: &RgbaImage) {
+    let path = crate::export::capture_path(seed);
+    let metadata = Metadata::new(config, seed);
+    metadata.save_png(image, &path);
+    log::info!("Saved {}", path.display());
+
+    let path = path.with_extension("toml");
+    match metadata.config.save(&path) {
+        Ok(()) => log::info!("Saved {}", path.display()),
+        Err(err) => log::warn!("Could not save {}: {}", path.display(), err),
+    }
+}
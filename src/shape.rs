@@ -0,0 +1,102 @@
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const MIN_CIRCLE_SEGMENTS: usize = 4; // number of triangles tiny circles are approximated with
+const MAX_CIRCLE_SEGMENTS: usize = 32; // number of triangles large circles are approximated with
+
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParticleShape {
+    Circle,
+    Square,
+    Plus,
+    Hexagon,
+    Triangle, // points along the particle's heading
+}
+
+// What the size points and paths are drawn at follows
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SizeMode {
+    Constant, // `point_size` and `path_width`
+    Noise,    // noise over the window, between `size_min` and `size_max`
+    Speed,    // faster particles larger, between `size_min` and `size_max`
+}
+
+// Rotate a point around the origin so that +x points along `heading` (in radians)
+pub fn orient(point: Vec2, heading: f32) -> Vec2 {
+    let (sin, cos) = heading.sin_cos();
+    vec2(point.x * cos - point.y * sin, point.x * sin + point.y * cos)
+}
+
+impl ParticleShape {
+    // Triangle list of the shape centered on `center`, `size` wide and oriented along `heading`,
+    // so that every particle can be appended to the same mesh
+    pub fn vertices(self, center: Vec2, size: f32, heading: f32) -> Vec<Vec2> {
+        let r = size / 2.0;
+
+        let quad = |x0: f32, y0: f32, x1: f32, y1: f32| {
+            [
+                vec2(x0, y0),
+                vec2(x1, y0),
+                vec2(x1, y1),
+                vec2(x0, y0),
+                vec2(x1, y1),
+                vec2(x0, y1),
+            ]
+        };
+        let fan = |segments: usize, phase: f32| {
+            (0..segments).flat_map(move |i| {
+                let a0 = phase + i as f32 / segments as f32 * TAU;
+                let a1 = phase + (i + 1) as f32 / segments as f32 * TAU;
+                [
+                    Vec2::ZERO,
+                    vec2(a0.cos(), a0.sin()) * r,
+                    vec2(a1.cos(), a1.sin()) * r,
+                ]
+            })
+        };
+
+        let local: Vec<Vec2> = match self {
+            ParticleShape::Circle => {
+                // about one segment per pixel of circumference, a pixel wide circle is a diamond
+                let segments =
+                    ((size * 4.0).ceil() as usize).clamp(MIN_CIRCLE_SEGMENTS, MAX_CIRCLE_SEGMENTS);
+                fan(segments, 0.0).collect()
+            }
+            ParticleShape::Square => quad(-r, -r, r, r).to_vec(),
+            ParticleShape::Plus => {
+                let t = r / 3.0; // half thickness of each bar
+                [quad(-r, -t, r, t), quad(-t, -r, t, r)].concat()
+            }
+            ParticleShape::Hexagon => fan(6, 0.0).collect(),
+            ParticleShape::Triangle => vec![
+                orient(vec2(r, 0.0), heading),
+                orient(vec2(-r, r * 0.6), heading),
+                orient(vec2(-r, -r * 0.6), heading),
+            ],
+        };
+
+        local.into_iter().map(|point| center + point).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertex_counts() {
+        let count = |shape: ParticleShape, size: f32| shape.vertices(Vec2::ZERO, size, 0.0).len();
+
+        assert_eq!(count(ParticleShape::Square, 4.0), 6);
+        assert_eq!(count(ParticleShape::Plus, 4.0), 12);
+        assert_eq!(count(ParticleShape::Hexagon, 4.0), 18);
+        assert_eq!(count(ParticleShape::Triangle, 4.0), 3);
+
+        // circles get more segments the larger they are, within bounds
+        assert_eq!(count(ParticleShape::Circle, 0.5), MIN_CIRCLE_SEGMENTS * 3);
+        assert_eq!(count(ParticleShape::Circle, 4.0), 16 * 3);
+        assert_eq!(count(ParticleShape::Circle, 100.0), MAX_CIRCLE_SEGMENTS * 3);
+    }
+}
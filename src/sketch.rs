@@ -0,0 +1,59 @@
+use crate::config::Config;
+use crate::session;
+use crate::{flow_field, physarum, volume};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// Which sketch runs, sharing the config and the palettes
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SketchKind {
+    FlowField, // particles following a vector field
+    Physarum,  // agents following the trails they deposit, like slime mold
+    Volume,    // particles following a vector field in three dimensions, seen orbiting
+}
+
+impl SketchKind {
+    pub fn run(self, config: &Config) {
+        match self {
+            SketchKind::FlowField => run::<flow_field::Model>(config),
+            SketchKind::Physarum => run::<physarum::Model>(config),
+            SketchKind::Volume => run::<volume::Model>(config),
+        }
+    }
+}
+
+// A runnable sketch, as the nannou model it keeps between frames
+pub trait Sketch: Sized + 'static {
+    // Produce the outputs the config asks for without opening a window, returning whether there
+    // were any
+    fn export(config: &Config) -> bool;
+
+    // Open the window and build the model, loading the config itself
+    fn model(app: &App) -> Self;
+
+    fn update(app: &App, model: &mut Self, update: Update);
+
+    // Summarize the session once the window closes or Ctrl+C is pressed, saving what it asks to
+    fn exit(app: &App, model: Self);
+}
+
+// Export the sketch if the config asks to, else open it in a window
+pub fn run<S: Sketch>(config: &Config) {
+    if !S::export(config) {
+        session::catch_interrupt();
+        nannou::app(S::model)
+            .update(update::<S>)
+            .exit(S::exit)
+            .run();
+    }
+}
+
+// Quit on Ctrl+C, which closes the windows and exits the sketch
+fn update<S: Sketch>(app: &App, model: &mut S, update: Update) {
+    if session::interrupted() {
+        app.quit();
+        return;
+    }
+    S::update(app, model, update);
+}
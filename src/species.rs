@@ -0,0 +1,91 @@
+use crate::canvas::Canvas;
+use crate::config::Config;
+use crate::layer::BlendMode;
+use crate::palette::{HexColor, PaletteKind};
+use crate::particles::Simulation;
+use crate::shape::ParticleShape;
+use crate::target;
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// Overrides of the main parameters for an additional species, given as `[[species]]` tables in
+// the config file. Unset fields inherit the main parameters.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct SpeciesConfig {
+    pub point_count: Option<u32>,
+    pub heading_noise_factor: Option<f64>,
+    pub heading_noise_multiplier: Option<f64>,
+    pub velocity_multiplier: Option<f64>,
+    pub point_size: Option<f64>,
+    pub particle_shape: Option<ParticleShape>,
+    pub palette: Option<PaletteKind>,
+    pub palette_stops: Option<Vec<HexColor>>,
+    pub hue_min: Option<f64>,
+    pub hue_max: Option<f64>,
+    pub blend: Option<BlendMode>, // draw onto a layer of its own, composited with this mode
+}
+
+impl SpeciesConfig {
+    // The main parameters with the overrides applied
+    pub fn apply(&self, config: &Config) -> Config {
+        let mut species = config.clone();
+        species.species = Vec::new();
+
+        macro_rules! apply {
+            ($($field:ident),*) => {
+                $(if let Some(value) = &self.$field {
+                    species.$field = value.clone();
+                })*
+            };
+        }
+        apply!(
+            point_count,
+            heading_noise_factor,
+            heading_noise_multiplier,
+            velocity_multiplier,
+            point_size,
+            particle_shape,
+            palette,
+            palette_stops,
+            hue_min,
+            hue_max
+        );
+
+        species
+    }
+}
+
+// Particles advected alongside the main simulation with their own parameters
+pub struct Species {
+    pub config: Config,
+    pub simulation: Simulation,
+    pub layer: Option<(BlendMode, Canvas)>, // drawn onto instead of the main canvas
+}
+
+// One species per override in `config`, each seeded from `seed` and its index
+pub fn build(window: &Window, config: &Config, seed: u64, x_end: f64, y_end: f64) -> Vec<Species> {
+    config
+        .species
+        .iter()
+        .enumerate()
+        .map(|(i, overrides)| {
+            let config = overrides.apply(config);
+            let seed = crate::seed::splitmix64(seed.wrapping_add(i as u64 + 1));
+
+            let size = [(x_end * 2.0) as u32, (y_end * 2.0) as u32];
+            let layer = overrides.blend.map(|blend| {
+                (
+                    blend,
+                    Canvas::new(window, size, blend.neutral(), target::samples(&config)),
+                )
+            });
+
+            Species {
+                simulation: Simulation::new(&config, seed, x_end, y_end),
+                config,
+                layer,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,153 @@
+use crate::config::Config;
+use crate::field::{self, FieldKind, FieldSource, ImageSource, NoiseSource, VectorField};
+use crate::noise::NoiseConfig;
+use crate::radial::Radial;
+use crate::Vector2;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// Field blended on top of the main one, given as `[[fields]]` tables in the config file.
+// Positions are centered and scaled like expressions, so the shorter side of the canvas spans
+// -1 to 1.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct FieldLayer {
+    #[serde(flatten)]
+    pub source: LayerSource,
+    #[serde(default = "default_weight")]
+    pub weight: f64, // relative to the main field, which has `field_weight`
+    #[serde(default)]
+    pub falloff: Option<Falloff>, // where the layer fades out, everywhere at full weight if unset
+    #[serde(default)]
+    pub mask: Option<PathBuf>, // image stretched over the canvas whose luminance scales the weight
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "source", rename_all = "kebab-case")]
+pub enum LayerSource {
+    // More noise, at `scale` times the frequency of the heading noise and offset from its seed
+    Noise {
+        #[serde(default = "default_weight")]
+        scale: f64,
+        #[serde(default)]
+        seed: u32,
+    },
+    Curl {
+        #[serde(default = "default_weight")]
+        scale: f64,
+        #[serde(default)]
+        seed: u32,
+    },
+    // A radial primitive, reversed by a negative weight
+    Radial {
+        kind: Radial,
+        #[serde(default)]
+        center: [f64; 2],
+    },
+    Wind {
+        direction: [f64; 2],
+    },
+    Image {
+        image: PathBuf, // flowing up the gradient of its luminance, towards bright areas
+    },
+}
+
+// Weight fading from full at `center` to nothing at `radius` away from it
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Falloff {
+    pub center: [f64; 2],
+    pub radius: f64,
+}
+
+// The main field and the layers, each normalized and summed by weight
+pub struct FieldStack {
+    base: Box<dyn VectorField>,
+    layers: Vec<(FieldLayer, Source, Option<ImageSource>)>,
+}
+
+enum Source {
+    Noise(NoiseSource, FieldKind, f64),
+    Radial(Radial, [f64; 2]),
+    Wind(Vector2),
+    Image(ImageSource),
+}
+
+impl FieldStack {
+    pub fn new(config: &Config, base: Box<dyn VectorField>, noise_seed: u32) -> Self {
+        let noise = |seed: u32| NoiseSource(NoiseConfig::heading(config).build(noise_seed + seed));
+
+        let layers = config.fields.iter().map(|layer| {
+            let source = match &layer.source {
+                LayerSource::Noise { scale, seed } => {
+                    Source::Noise(noise(*seed), FieldKind::Angle, *scale)
+                }
+                LayerSource::Curl { scale, seed } => {
+                    Source::Noise(noise(*seed), FieldKind::Curl, *scale)
+                }
+                LayerSource::Radial { kind, center } => Source::Radial(*kind, *center),
+                LayerSource::Wind { direction: [x, y] } => Source::Wind(Vector2 { x: *x, y: *y }),
+                LayerSource::Image { image } => Source::Image(ImageSource::load(image)),
+            };
+            let mask = layer.mask.as_deref().map(ImageSource::load);
+            (layer.clone(), source, mask)
+        });
+
+        FieldStack {
+            base,
+            layers: layers.collect(),
+        }
+    }
+}
+
+impl VectorField for FieldStack {
+    fn vector(&self, config: &Config, pos: Vector2, extent: Vector2, steps: u64) -> Vector2 {
+        let unit = |vector: Vector2| match vector.length() {
+            length if length > 0.0 => vector / length,
+            _ => vector,
+        };
+
+        let (u, v) = (pos.x / extent.x, pos.y / extent.y);
+        let scale = extent.x.min(extent.y);
+        let (x, y) = (
+            (pos.x - extent.x / 2.0) * 2.0 / scale,
+            (pos.y - extent.y / 2.0) * 2.0 / scale,
+        );
+        let time = steps as f64 * config.evolution_speed;
+
+        let mut sum = unit(self.base.vector(config, pos, extent, steps)) * config.field_weight;
+        for (layer, source, mask) in &self.layers {
+            let mut weight = layer.weight;
+            if let Some(Falloff { center, radius }) = &layer.falloff {
+                let t = (1.0 - (x - center[0]).hypot(y - center[1]) / radius).clamp(0.0, 1.0);
+                weight *= t * t * (3.0 - 2.0 * t); // smoothstep
+            }
+            if let Some(mask) = mask {
+                weight *= mask.potential(config, u, v, time);
+            }
+            if weight == 0.0 {
+                continue;
+            }
+
+            let vector = match source {
+                Source::Noise(noise, kind, scale) => {
+                    field::source_vector(noise, config, *kind, [u * scale, v * scale], extent, time)
+                }
+                Source::Radial(radial, [cx, cy]) => radial.vector([x - cx, y - cy]),
+                Source::Wind(direction) => *direction,
+                Source::Image(image) => {
+                    let (gradient_u, gradient_v) = image.gradient(config, u, v, time);
+                    Vector2 {
+                        x: gradient_u / extent.x,
+                        y: gradient_v / extent.y,
+                    }
+                }
+            };
+            sum += unit(vector) * weight;
+        }
+
+        sum
+    }
+}
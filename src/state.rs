@@ -0,0 +1,110 @@
+use crate::config::Config;
+use crate::flow_field::Model;
+use crate::history::Snapshot;
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Everything needed to continue a piece after a restart. It is written as JSON, with the trails
+// next to it as PNGs of the canvas and of every species layer and the additive buffer as raw
+// little-endian floats.
+#[derive(Serialize, Deserialize)]
+pub struct State {
+    pub config: Config,           // with the seed resolved
+    pub snapshots: Vec<Snapshot>, // the main simulation first, then every species
+}
+
+pub fn save(window: &Window, model: &Model, path: &Path) {
+    let mut config = model.config.clone();
+    config.seed = model.simulation.seed;
+    let state = State {
+        config,
+        snapshots: crate::flow_field::snapshots(model),
+    };
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).unwrap();
+    }
+    fs::write(path, serde_json::to_string(&state).unwrap()).unwrap();
+
+    let (device, queue) = (window.device(), window.queue());
+    crate::headless::save(
+        device,
+        queue,
+        &model.canvas.target.texture,
+        &image_path(path, None),
+    );
+    for (i, species) in model.species.iter().enumerate() {
+        if let Some((_, layer)) = &species.layer {
+            crate::headless::save(
+                device,
+                queue,
+                &layer.target.texture,
+                &image_path(path, Some(i)),
+            );
+        }
+    }
+
+    if let Some((buffer, _)) = &model.accumulation {
+        let bytes: Vec<u8> = buffer
+            .cells
+            .iter()
+            .flat_map(|cell| [cell.red, cell.green, cell.blue])
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        fs::write(accumulation_path(path), bytes).unwrap();
+    }
+}
+
+pub fn load(path: &Path) -> State {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("Could not read state {}: {}", path.display(), err));
+    serde_json::from_str(&text)
+        .unwrap_or_else(|err| panic!("Invalid state {}: {}", path.display(), err))
+}
+
+// Continue from `state`, once the model has been built from its config
+pub fn resume(app: &App, model: &mut Model, state: &State, path: &Path) {
+    model.simulation.restore(&state.snapshots[0]);
+    for (species, snapshot) in model.species.iter_mut().zip(&state.snapshots[1..]) {
+        species.simulation.restore(snapshot);
+    }
+
+    // Drawn over the background the canvas starts from, stretched if the window size changed
+    let size = model.simulation.size();
+    let canvas = wgpu::Texture::from_path(app, image_path(path, None)).unwrap();
+    model.canvas.draw.texture(&canvas).wh(size);
+    for (i, species) in model.species.iter().enumerate() {
+        if let Some((_, layer)) = &species.layer {
+            let texture = wgpu::Texture::from_path(app, image_path(path, Some(i))).unwrap();
+            layer.draw.texture(&texture).wh(size);
+        }
+    }
+
+    // Only restored if the window is the size it was saved at
+    if let Some((buffer, _)) = &mut model.accumulation {
+        let bytes = fs::read(accumulation_path(path)).unwrap_or_default();
+        let floats: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        if floats.len() == buffer.cells.len() * 3 {
+            for (cell, rgb) in buffer.cells.iter_mut().zip(floats.chunks_exact(3)) {
+                *cell = LinSrgb::new(rgb[0], rgb[1], rgb[2]);
+            }
+        }
+    }
+}
+
+// `state.png` for the canvas and `state.0.png` and so on for the species layers
+fn image_path(path: &Path, layer: Option<usize>) -> PathBuf {
+    match layer {
+        Some(i) => path.with_extension(format!("{}.png", i)),
+        None => path.with_extension("png"),
+    }
+}
+
+fn accumulation_path(path: &Path) -> PathBuf {
+    path.with_extension("accumulation")
+}
@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+const WINDOW: usize = 60; // number of frames the averages are taken over
+
+// Mean of the last `WINDOW` samples
+struct Rolling {
+    samples: VecDeque<f64>,
+    sum: f64,
+}
+
+impl Rolling {
+    fn new() -> Self {
+        Rolling {
+            samples: VecDeque::with_capacity(WINDOW),
+            sum: 0.0,
+        }
+    }
+
+    fn push(&mut self, sample: f64) {
+        if self.samples.len() == WINDOW {
+            self.sum -= self.samples.pop_front().unwrap();
+        }
+        self.samples.push_back(sample);
+        self.sum += sample;
+    }
+
+    fn mean(&self) -> f64 {
+        match self.samples.len() {
+            0 => 0.0,
+            len => self.sum / len as f64,
+        }
+    }
+}
+
+// Frame timings, averaged for the HUD, logged with `--verbose` and optionally saved frame by
+// frame to a CSV file
+pub struct Stats {
+    update: Rolling, // stepping the simulation, in milliseconds
+    draw: Rolling,   // drawing and rendering the canvas
+    frame: Rolling,  // time between frames
+    total: [f64; 3], // of the update, draw and frame times over the session
+    count: u64,      // frames recorded over the session
+    csv: Option<File>,
+}
+
+impl Stats {
+    pub fn new(csv: Option<&Path>) -> Self {
+        let csv = csv.map(|path| {
+            if let Some(directory) = path.parent() {
+                std::fs::create_dir_all(directory).unwrap();
+            }
+
+            let mut file = File::create(path).unwrap();
+            writeln!(file, "step,particles,update_ms,draw_ms,frame_ms").unwrap();
+            file
+        });
+
+        Stats {
+            update: Rolling::new(),
+            draw: Rolling::new(),
+            frame: Rolling::new(),
+            total: [0.0; 3],
+            count: 0,
+            csv,
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        step: u64,
+        particles: usize,
+        update: Duration,
+        draw: Duration,
+        frame: Duration,
+    ) {
+        let (update, draw, frame) = (
+            update.as_secs_f64() * 1000.0,
+            draw.as_secs_f64() * 1000.0,
+            frame.as_secs_f64() * 1000.0,
+        );
+        self.update.push(update);
+        self.draw.push(draw);
+        self.frame.push(frame);
+        for (total, sample) in self.total.iter_mut().zip([update, draw, frame]) {
+            *total += sample;
+        }
+        self.count += 1;
+        crate::logging::frame(step, particles, update, draw, frame);
+
+        // Written unbuffered, as the app exits without dropping the model
+        if let Some(file) = &mut self.csv {
+            writeln!(
+                file,
+                "{},{},{:.3},{:.3},{:.3}",
+                step, particles, update, draw, frame
+            )
+            .unwrap();
+        }
+    }
+
+    pub fn update_ms(&self) -> f64 {
+        self.update.mean()
+    }
+
+    pub fn draw_ms(&self) -> f64 {
+        self.draw.mean()
+    }
+
+    // Mean update, draw and frame times over the whole session rather than the last frames
+    pub fn session_ms(&self) -> [f64; 3] {
+        self.total.map(|total| total / self.count.max(1) as f64)
+    }
+
+    pub fn fps(&self) -> f64 {
+        match self.frame.mean() {
+            ms if ms > 0.0 => 1000.0 / ms,
+            _ => 0.0,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::config::Config;
+use crate::Vector2;
+
+// Velocity a flow moving at `prev` takes up when the field asks for `target`: kept towards
+// `prev` by `inertia` and turned by no more than `max_turn` degrees, so sharp changes in the
+// field bend paths into curves instead of kinks. Flows just placed have no heading to keep yet.
+pub fn steer(config: &Config, prev: Vector2, target: Vector2, age: u64) -> Vector2 {
+    if age == 0 || prev.length() == 0.0 {
+        return target;
+    }
+    let mut vel = match config.inertia {
+        inertia if inertia > 0.0 => prev.lerp(target, 1.0 - inertia.min(1.0)),
+        _ => target,
+    };
+
+    if config.max_turn > 0.0 {
+        let cross = prev.x * vel.y - prev.y * vel.x;
+        let turn = cross.atan2(prev.dot(vel));
+        let limit = config.max_turn.to_radians();
+        if turn.abs() > limit {
+            let length = vel.length();
+            let mut heading = prev;
+            heading.normalize();
+            vel = heading.rotate(limit.copysign(turn)) * length;
+        }
+    }
+    vel
+}
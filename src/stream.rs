@@ -0,0 +1,49 @@
+use nannou::image::{imageops, RgbaImage};
+use nannou::prelude::*;
+use std::io::Write;
+use std::path::Path;
+
+// Live output of the canvas as raw RGBA frames, written to a file or a named pipe every frame so
+// another program, or a tool like ffmpeg forwarding them, can pick them up without capturing the
+// screen
+pub struct FrameStream {
+    writer: std::io::BufWriter<std::fs::File>,
+    size: [u32; 2], // of every frame, fixed when the stream opens
+}
+
+impl FrameStream {
+    // Opening a named pipe waits until something starts reading from it
+    pub fn open(path: &Path, size: [u32; 2]) -> Self {
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .unwrap_or_else(|err| panic!("Could not open stream {}: {}", path.display(), err));
+        log::info!(
+            "Streaming {} by {} rgba frames to {}",
+            size[0],
+            size[1],
+            path.display()
+        );
+
+        FrameStream {
+            writer: std::io::BufWriter::new(file),
+            size,
+        }
+    }
+
+    // Read the texture back and write it out, stretched to the size of the stream if the window
+    // was resized since it opened. Stops streaming once the reader goes away.
+    pub fn write(&mut self, window: &Window, texture: &wgpu::Texture) -> bool {
+        let image = crate::headless::read(window.device(), window.queue(), texture);
+        let [width, height] = self.size;
+        let image: RgbaImage = match image.dimensions() == (width, height) {
+            true => image,
+            false => imageops::resize(&image, width, height, imageops::FilterType::Triangle),
+        };
+
+        let written = self.writer.write_all(image.as_raw());
+        written.and_then(|_| self.writer.flush()).is_ok()
+    }
+}
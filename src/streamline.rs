@@ -0,0 +1,126 @@
+use crate::collision::DrawnPaths;
+use crate::config::Config;
+use crate::obstacle::ObstacleMode;
+use crate::particles::Simulation;
+use crate::Vector2;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// How traced streamlines index into the palette
+#[derive(Copy, Clone, PartialEq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StreamlineColor {
+    Start,  // by the color noise where the streamline starts, like plot layers
+    Length, // from the shortest streamline to the longest
+}
+
+// The whole path a particle takes from where it is placed
+pub struct Streamline {
+    pub points: Vec<Vector2>,
+    pub length: f64,
+}
+
+// Trace a streamline from every particle of a freshly seeded simulation to completion: for
+// `streamline_steps` steps, until it leaves the window, is absorbed by an obstacle, stalls or,
+// with `path_spacing` set, comes too close to a streamline traced before it. Streamlines are
+// independent and traced in parallel unless they have to keep their distance.
+pub fn trace(config: &Config, simulation: &Simulation) -> Vec<Streamline> {
+    let starts: Vec<Vector2> = simulation.flow_field.iter().map(|flow| flow.pos).collect();
+    let mut streamlines: Vec<Streamline> = match DrawnPaths::build(config) {
+        Some(mut drawn) => starts
+            .iter()
+            .enumerate()
+            .map(|(i, &start)| trace_one(config, simulation, start, i, Some(&mut drawn)))
+            .collect(),
+        None => starts
+            .par_iter()
+            .enumerate()
+            .map(|(i, &start)| trace_one(config, simulation, start, i, None))
+            .collect(),
+    };
+    streamlines.retain(|streamline| streamline.points.len() >= 2);
+
+    // Shortest first, so the long ones are drawn on top
+    if config.sort_streamlines {
+        streamlines.sort_by(|a, b| a.length.total_cmp(&b.length));
+    }
+    streamlines
+}
+
+fn trace_one(
+    config: &Config,
+    simulation: &Simulation,
+    start: Vector2,
+    index: usize,
+    mut drawn: Option<&mut DrawnPaths>,
+) -> Streamline {
+    let h = config.velocity_multiplier * config.dt;
+    let (x_end, y_end) = (simulation.x_end, simulation.y_end);
+    let absorbing = match (&simulation.obstacles, config.obstacle_mode) {
+        (Some(obstacles), ObstacleMode::Absorb) => Some(obstacles),
+        _ => None,
+    };
+
+    let mut streamline = Streamline {
+        points: vec![start],
+        length: 0.0,
+    };
+    if let Some(drawn) = drawn.as_deref_mut() {
+        if drawn.blocked(start, (index, 0), 0, 0) {
+            return streamline; // starting too close to another streamline
+        }
+        drawn.add(start, (index, 0), 0);
+    }
+
+    let mut pos = start;
+    for age in 1..=config.streamline_steps as u64 {
+        let vel = config
+            .integrator
+            .velocity(pos, h, |pos| simulation.velocity(config, pos));
+        let step = (vel * h).length();
+        if step < f64::EPSILON {
+            break; // stalled, at a sink or a dead end
+        }
+        pos += vel * h;
+
+        let inside = (0.0..=x_end).contains(&pos.x) && (0.0..=y_end).contains(&pos.y);
+        let absorbed = absorbing.is_some_and(|obstacles| {
+            let (distance, _) = obstacles.sample(pos, simulation.extent());
+            distance < 0.0
+        });
+        let collided = drawn.as_deref().is_some_and(|drawn| {
+            let recent = (config.path_spacing / step).ceil();
+            drawn.blocked(pos, (index, 0), age, recent.min(u64::MAX as f64) as u64)
+        });
+        if !inside || absorbed || collided {
+            break;
+        }
+
+        if let Some(drawn) = drawn.as_deref_mut() {
+            drawn.add(pos, (index, 0), age);
+        }
+        streamline.points.push(pos);
+        streamline.length += step;
+    }
+
+    streamline
+}
+
+// Position in the palette of every streamline, in order
+pub fn values(config: &Config, simulation: &Simulation, streamlines: &[Streamline]) -> Vec<f64> {
+    let longest = streamlines
+        .iter()
+        .map(|streamline| streamline.length)
+        .fold(0.0, f64::max);
+
+    streamlines
+        .iter()
+        .map(|streamline| match config.streamline_color {
+            StreamlineColor::Start => simulation.color_value(config, streamline.points[0]),
+            StreamlineColor::Length if longest > 0.0 => {
+                config.palette_mapping.apply(streamline.length / longest)
+            }
+            StreamlineColor::Length => 0.0,
+        })
+        .collect()
+}
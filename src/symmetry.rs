@@ -0,0 +1,54 @@
+use nannou::prelude::*;
+
+// Transforms everything is drawn through around the center of the window: `order` rotations
+// evenly around it, each also mirrored across the vertical axis if `mirror` is set, giving
+// mandala-like pieces from a single simulation
+pub struct Symmetry(Vec<([f64; 2], bool)>); // rotation as cosine and sine, and whether mirrored first
+
+impl Symmetry {
+    pub fn new(order: u32, mirror: bool) -> Self {
+        let order = order.max(1);
+        let rotations = (0..order).map(|i| {
+            let angle = i as f64 / order as f64 * std::f64::consts::TAU;
+            [angle.cos(), angle.sin()]
+        });
+
+        let mirrors: &[bool] = if mirror { &[false, true] } else { &[false] };
+        Symmetry(
+            rotations
+                .flat_map(|rotation| mirrors.iter().map(move |&mirror| (rotation, mirror)))
+                .collect(),
+        )
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.0.len() == 1
+    }
+
+    // Number of copies, the first of which is the original
+    pub fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    // Copy `i` of a point relative to the center
+    pub fn image(&self, i: usize, [x, y]: [f64; 2]) -> [f64; 2] {
+        let ([cos, sin], mirror) = self.0[i];
+        let x = if mirror { -x } else { x };
+        [x * cos - y * sin, x * sin + y * cos]
+    }
+
+    // Every copy of a triangle list relative to the center, keeping the triangles together
+    pub fn copies<T: Copy>(&self, vertices: Vec<(Vec2, T)>) -> Vec<(Vec2, T)> {
+        if self.is_identity() {
+            return vertices;
+        }
+        (0..self.count())
+            .flat_map(|i| {
+                vertices.iter().map(move |&(vertex, data)| {
+                    let [x, y] = self.image(i, [vertex.x as f64, vertex.y as f64]);
+                    (vec2(x as f32, y as f32), data)
+                })
+            })
+            .collect()
+    }
+}
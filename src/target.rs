@@ -0,0 +1,120 @@
+use crate::config::Config;
+use nannou::draw::{Renderer, RendererBuilder};
+use nannou::image::{Rgba, RgbaImage};
+use nannou::prelude::*;
+
+// Offscreen texture drawn into with `samples` samples per pixel. With more than one, drawings
+// go into a multisampled texture that keeps everything drawn so far, resolved into `texture`
+// after every render for reading back, sampling and presenting.
+pub struct Target {
+    pub texture: wgpu::Texture,
+    multisampled: Option<wgpu::Texture>,
+    renderer: Renderer,
+}
+
+impl Target {
+    pub fn new(device: &wgpu::Device, size: [u32; 2], samples: u32) -> Self {
+        let texture = wgpu::TextureBuilder::new()
+            .size(size)
+            .format(Frame::TEXTURE_FORMAT) // high precision so faint trails fade out smoothly
+            .usage(
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC, // read back for saving
+            )
+            .build(device);
+        let multisampled = (samples > 1).then(|| {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .format(Frame::TEXTURE_FORMAT)
+                .sample_count(samples)
+                .usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+                .build(device)
+        });
+
+        let descriptor = multisampled.as_ref().unwrap_or(&texture).descriptor();
+        let renderer = RendererBuilder::new().build_from_texture_descriptor(device, descriptor);
+
+        Target {
+            texture,
+            multisampled,
+            renderer,
+        }
+    }
+
+    // Render everything drawn since the last call on top of the target
+    pub fn render(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, draw: &Draw) {
+        let descriptor = wgpu::CommandEncoderDescriptor {
+            label: Some("target"),
+        };
+        let mut encoder = device.create_command_encoder(&descriptor);
+
+        match &self.multisampled {
+            Some(multisampled) => {
+                let resolved = self.texture.view().build();
+                self.renderer.encode_render_pass(
+                    device,
+                    &mut encoder,
+                    draw,
+                    1.0,
+                    self.texture.size(),
+                    &multisampled.view().build(),
+                    Some(&resolved),
+                );
+            }
+            None => self
+                .renderer
+                .render_to_texture(device, &mut encoder, draw, &self.texture),
+        }
+        queue.submit(Some(encoder.finish()));
+
+        draw.reset();
+    }
+}
+
+// Samples per pixel of the canvas. The compute shader draws its particles straight onto the
+// resolved texture, which a multisampled canvas would overwrite.
+pub fn samples(config: &Config) -> u32 {
+    match config.gpu {
+        true => 1,
+        false => config.msaa.max(1),
+    }
+}
+
+// Average every `factor` by `factor` block of pixels into one, in linear light so thin lines
+// keep their brightness
+pub fn downscale(image: &RgbaImage, factor: u32) -> RgbaImage {
+    if factor <= 1 {
+        return image.clone();
+    }
+    let linear: Vec<f32> = (0..=255)
+        .map(|value| Srgb::new(value as f32 / 255.0, 0.0, 0.0).into_linear().red)
+        .collect();
+    let encode = |value: f32| {
+        let srgb = Srgb::from_linear(LinSrgb::new(value, 0.0, 0.0));
+        (srgb.red * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+
+    let (width, height) = (image.width() / factor, image.height() / factor);
+    let count = (factor * factor) as f32;
+    RgbaImage::from_fn(width.max(1), height.max(1), |x, y| {
+        let mut sum = [0.0f32; 4];
+        for (dx, dy) in (0..factor).flat_map(|dx| (0..factor).map(move |dy| (dx, dy))) {
+            let pixel = image.get_pixel(
+                (x * factor + dx).min(image.width() - 1),
+                (y * factor + dy).min(image.height() - 1),
+            );
+            for channel in 0..3 {
+                sum[channel] += linear[pixel[channel] as usize];
+            }
+            sum[3] += pixel[3] as f32 / 255.0;
+        }
+        let [red, green, blue, alpha] = sum.map(|value| value / count);
+        Rgba([
+            encode(red),
+            encode(green),
+            encode(blue),
+            (alpha * 255.0).round() as u8,
+        ])
+    })
+}
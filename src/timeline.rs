@@ -0,0 +1,84 @@
+use crate::config::Config;
+use crate::modulation::Parameter;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+// Parameter animated over time, given as `[[timeline]]` tables in the config file. Its value
+// follows the keyframes, holding the first before it and the last after it, plus a sine wave if
+// it oscillates, so a single keyframe is the center the wave swings around.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Track {
+    pub parameter: Parameter,
+    #[serde(default)]
+    pub keyframes: Vec<Keyframe>, // in order of time
+    #[serde(default)]
+    pub oscillate: Option<Oscillation>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: f64, // seconds
+    pub value: f64,
+    #[serde(default)]
+    pub easing: Easing, // of the transition from the previous keyframe into this one
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct Oscillation {
+    pub period: f64, // seconds
+    pub amplitude: f64,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,    // starting slowly
+    EaseOut,   // ending slowly
+    EaseInOut, // starting and ending slowly
+    Step,      // jumping at the keyframe
+}
+
+impl Easing {
+    // Fraction of the transition done `t` of the way through it
+    fn ease(self, t: f64) -> f64 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Step => (t >= 1.0) as u8 as f64,
+        }
+    }
+}
+
+impl Track {
+    pub fn value(&self, time: f64) -> f64 {
+        let keyframes = &self.keyframes;
+        let next = keyframes.iter().position(|keyframe| keyframe.time > time);
+        let base = match (keyframes.first(), next) {
+            (None, _) => 0.0,
+            (Some(first), Some(0)) => first.value,
+            (Some(_), None) => keyframes[keyframes.len() - 1].value,
+            (Some(_), Some(i)) => {
+                let (from, to) = (&keyframes[i - 1], &keyframes[i]);
+                let t = (time - from.time) / (to.time - from.time);
+                from.value + (to.value - from.value) * to.easing.ease(t)
+            }
+        };
+
+        let wave = match &self.oscillate {
+            Some(Oscillation { period, amplitude }) => amplitude * (time / period * TAU).sin(),
+            None => 0.0,
+        };
+        base + wave
+    }
+}
+
+// Set every animated parameter to its value `time` seconds in
+pub fn apply(config: &mut Config, time: f64) {
+    for track in config.timeline.clone() {
+        track.parameter.set(config, track.value(time));
+    }
+}
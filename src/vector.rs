@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use std::ops;
+
+// Position, velocity or direction on the canvas, in pixels
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vector2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl ops::Add<Vector2> for Vector2 {
+    type Output = Vector2;
+
+    fn add(self, other: Vector2) -> Vector2 {
+        Vector2 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+impl ops::Sub<Vector2> for Vector2 {
+    type Output = Vector2;
+
+    fn sub(self, other: Vector2) -> Vector2 {
+        Vector2 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl ops::Neg for Vector2 {
+    type Output = Vector2;
+
+    fn neg(self) -> Vector2 {
+        Vector2 {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl ops::Div<f64> for Vector2 {
+    type Output = Vector2;
+
+    fn div(self, other: f64) -> Vector2 {
+        Vector2 {
+            x: self.x / other,
+            y: self.y / other,
+        }
+    }
+}
+
+impl ops::Mul<f64> for Vector2 {
+    type Output = Vector2;
+
+    fn mul(self, other: f64) -> Vector2 {
+        Vector2 {
+            x: self.x * other,
+            y: self.y * other,
+        }
+    }
+}
+
+impl ops::AddAssign<Vector2> for Vector2 {
+    fn add_assign(&mut self, other: Vector2) {
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+impl ops::SubAssign<Vector2> for Vector2 {
+    fn sub_assign(&mut self, other: Vector2) {
+        self.x -= other.x;
+        self.y -= other.y;
+    }
+}
+
+impl Vector2 {
+    pub fn dot(&self, other: Vector2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(&self) -> f64 {
+        self.dot(*self).sqrt()
+    }
+
+    // Scale to a length of 1, leaving the zero vector as it is
+    pub fn normalize(&mut self) -> &mut Self {
+        let length = self.length();
+        if length > 0.0 {
+            self.x /= length;
+            self.y /= length;
+        }
+
+        self
+    }
+
+    // Turned counterclockwise by an angle in radians
+    pub fn rotate(self, angle: f64) -> Vector2 {
+        let (sin, cos) = angle.sin_cos();
+        Vector2 {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+        }
+    }
+
+    // Linear interpolation, `self` at 0 and `other` at 1
+    pub fn lerp(self, other: Vector2, t: f64) -> Vector2 {
+        self + (other - self) * t
+    }
+}
+
+// Position or direction in the volume of the 3D sketch
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vector3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl ops::Add<Vector3> for Vector3 {
+    type Output = Vector3;
+
+    fn add(self, other: Vector3) -> Vector3 {
+        Vector3 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl ops::Sub<Vector3> for Vector3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Vector3) -> Vector3 {
+        Vector3 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl ops::Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Vector3 {
+        Vector3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl ops::Mul<f64> for Vector3 {
+    type Output = Vector3;
+
+    fn mul(self, other: f64) -> Vector3 {
+        Vector3 {
+            x: self.x * other,
+            y: self.y * other,
+            z: self.z * other,
+        }
+    }
+}
+
+impl ops::AddAssign<Vector3> for Vector3 {
+    fn add_assign(&mut self, other: Vector3) {
+        self.x += other.x;
+        self.y += other.y;
+        self.z += other.z;
+    }
+}
+
+impl Vector3 {
+    // Unit vector at an azimuth around the vertical axis and an inclination above the horizontal,
+    // in radians
+    pub fn from_angles(azimuth: f64, inclination: f64) -> Vector3 {
+        Vector3 {
+            x: inclination.cos() * azimuth.sin(),
+            y: inclination.sin(),
+            z: inclination.cos() * azimuth.cos(),
+        }
+    }
+
+    pub fn dot(&self, other: Vector3) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn length(&self) -> f64 {
+        self.dot(*self).sqrt()
+    }
+
+    // Linear interpolation, `self` at 0 and `other` at 1
+    pub fn lerp(self, other: Vector3, t: f64) -> Vector3 {
+        self + (other - self) * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    const EPSILON: f64 = 1e-12;
+
+    fn close(a: Vector2, b: Vector2) -> bool {
+        (a - b).length() < EPSILON
+    }
+
+    #[test]
+    fn normalize() {
+        let mut vector = Vector2 { x: 3.0, y: -4.0 };
+        vector.normalize();
+        assert!(close(vector, Vector2 { x: 0.6, y: -0.8 }));
+
+        let mut zero = Vector2::default();
+        zero.normalize();
+        assert_eq!(zero, Vector2::default());
+    }
+
+    #[test]
+    fn sub_and_neg() {
+        let a = Vector2 { x: 1.0, y: 2.0 };
+        let b = Vector2 { x: 4.0, y: -1.0 };
+        assert_eq!(a - b, Vector2 { x: -3.0, y: 3.0 });
+        assert_eq!(-a, Vector2 { x: -1.0, y: -2.0 });
+        assert_eq!(a - b, a + -b);
+
+        let mut c = a;
+        c -= b;
+        assert_eq!(c, a - b);
+
+        let (a, b) = (
+            Vector3 {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            },
+            Vector3 {
+                x: 0.5,
+                y: -1.0,
+                z: 4.0,
+            },
+        );
+        assert_eq!(a - b, a + -b);
+        assert_eq!(
+            -a,
+            Vector3 {
+                x: -1.0,
+                y: -2.0,
+                z: -3.0
+            }
+        );
+    }
+
+    #[test]
+    fn dot() {
+        let a = Vector2 { x: 1.0, y: 2.0 };
+        let b = Vector2 { x: 3.0, y: -4.0 };
+        assert_eq!(a.dot(b), -5.0);
+        assert_eq!(a.dot(a), 5.0);
+        assert_eq!(a.dot(Vector2 { x: -2.0, y: 1.0 }), 0.0); // perpendicular
+
+        let c = Vector3 {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+        };
+        assert_eq!(c.dot(c), 14.0);
+    }
+
+    #[test]
+    fn rotate() {
+        let x = Vector2 { x: 1.0, y: 0.0 };
+        let y = Vector2 { x: 0.0, y: 1.0 };
+        assert!(close(x.rotate(FRAC_PI_2), y)); // counterclockwise
+        assert!(close(y.rotate(-FRAC_PI_2), x));
+
+        let a = Vector2 { x: 3.0, y: -4.0 };
+        assert!((a.rotate(1.0).length() - a.length()).abs() < EPSILON);
+        assert!(close(a.rotate(0.3).rotate(-0.3), a));
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Vector2 { x: 1.0, y: 2.0 };
+        let b = Vector2 { x: 5.0, y: -2.0 };
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(a.lerp(b, 0.5), Vector2 { x: 3.0, y: 0.0 });
+
+        let (c, d) = (
+            Vector3::default(),
+            Vector3 {
+                x: 2.0,
+                y: 4.0,
+                z: -6.0,
+            },
+        );
+        assert_eq!(
+            c.lerp(d, 0.25),
+            Vector3 {
+                x: 0.5,
+                y: 1.0,
+                z: -1.5
+            }
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use crate::config::Config;
+use crate::metadata::Metadata;
+use crate::noise::{Noise, NoiseConfig};
+use crate::pacing::{self, Pacer};
+use crate::palette;
+use crate::seed::{self, color_noise_seed, noise_seed};
+use crate::sketch::Sketch;
+use crate::target::{self, Target};
+use crate::Vector3;
+use nannou::image::RgbaImage;
+use nannou::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use rayon::prelude::*;
+use std::collections::VecDeque;
+use std::f64::consts::{FRAC_PI_2, TAU};
+use std::path::Path;
+use std::sync::Arc;
+
+const HALF_SIDE: f64 = 250.0; // pixels the half side of the volume counts as, scaling velocities
+const CORNER: f64 = 1.7320508075688772; // distance from the center to a corner of the volume
+const INCLINATION_OFFSET: f64 = 100.0; // along x, between the two headings' noise samples
+
+// Particle in the volume, a cube of side 2 around the origin with y pointing up
+struct Particle {
+    pos: Vector3,
+    trail: VecDeque<Vector3>, // most recent last, `path_length` long at most
+}
+
+// The flow field in three dimensions: particles head along two noise samples taken as spherical
+// angles, an azimuth around the vertical axis and an inclination above the horizontal
+pub struct Volume {
+    particles: Vec<Particle>,
+    heading: Box<dyn Noise>,
+    color: Box<dyn Noise>,
+    rng: ChaCha12Rng,
+    pub seed: u64,
+    pub steps: u64,
+}
+
+impl Volume {
+    // `point_count` squared particles at random positions
+    pub fn new(config: &Config, seed: u64) -> Self {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let count = (config.point_count * config.point_count) as usize;
+        let particles = (0..count)
+            .map(|_| Particle {
+                pos: random_pos(&mut rng),
+                trail: VecDeque::new(),
+            })
+            .collect();
+
+        Volume {
+            particles,
+            heading: NoiseConfig::heading(config).build(noise_seed(seed)),
+            color: NoiseConfig::color(config).build(color_noise_seed(config, seed)),
+            rng,
+            seed,
+            steps: 0,
+        }
+    }
+
+    // Heading noise at a position, moving through time as a fourth dimension if the field evolves
+    fn sample(&self, config: &Config, pos: Vector3) -> f64 {
+        let point = pos * config.heading_noise_factor;
+        match config.evolution_speed {
+            0.0 => self.heading.sample_3d([point.x, point.y, point.z]),
+            speed => {
+                let time = self.steps as f64 * speed;
+                self.heading.sample_4d([point.x, point.y, point.z, time])
+            }
+        }
+    }
+
+    // Unit vector of the field at a position
+    fn direction(&self, config: &Config, pos: Vector3) -> Vector3 {
+        let offset = Vector3 {
+            x: INCLINATION_OFFSET,
+            y: 0.0,
+            z: 0.0,
+        };
+        let multiplier = config.heading_noise_multiplier;
+        let azimuth = self.sample(config, pos) * multiplier * TAU;
+        let inclination = self.sample(config, pos + offset) * multiplier * FRAC_PI_2;
+        Vector3::from_angles(azimuth, inclination)
+    }
+
+    pub fn step(&mut self, config: &Config) {
+        let h = config.velocity_multiplier * config.dt / HALF_SIDE;
+        let length = config.path_length.max(1);
+
+        let directions: Vec<Vector3> = self
+            .particles
+            .par_iter()
+            .map(|particle| self.direction(config, particle.pos))
+            .collect();
+
+        // Particles leaving the volume start over at a random position, drawn in order from the
+        // seeded generator for reproducibility
+        for (particle, direction) in self.particles.iter_mut().zip(directions) {
+            particle.trail.push_back(particle.pos);
+            while particle.trail.len() > length {
+                particle.trail.pop_front();
+            }
+
+            particle.pos += direction * h;
+            let Vector3 { x, y, z } = particle.pos;
+            if x.abs() > 1.0 || y.abs() > 1.0 || z.abs() > 1.0 {
+                particle.pos = random_pos(&mut self.rng);
+                particle.trail.clear();
+            }
+        }
+
+        self.steps += 1;
+    }
+
+    // Palette value of a position, from the color noise like the flat flow field's
+    fn color_value(&self, config: &Config, pos: Vector3) -> f64 {
+        let point = pos * config.color_noise_factor;
+        let value = (self.color.sample_3d([point.x, point.y, point.z])
+            * config.color_noise_multiplier
+            + 1.0)
+            / 2.0;
+        config.palette_mapping.apply(value)
+    }
+}
+
+fn random_pos(rng: &mut ChaCha12Rng) -> Vector3 {
+    Vector3 {
+        x: rng.gen_range(-1.0..1.0),
+        y: rng.gen_range(-1.0..1.0),
+        z: rng.gen_range(-1.0..1.0),
+    }
+}
+
+// Perspective camera on a sphere around the volume, looking at its center
+#[derive(Copy, Clone)]
+pub struct Orbit {
+    pub azimuth: f64,   // around the vertical axis, in radians
+    pub elevation: f64, // above the horizontal, in radians
+    pub distance: f64,  // from the center, in half sides of the volume
+    pub focal: f64,     // pixels a unit at unit depth spans on screen
+}
+
+impl Orbit {
+    pub fn new(config: &Config, azimuth: f64, height: f32) -> Self {
+        let half_angle = (config.field_of_view / 2.0).to_radians();
+        Orbit {
+            azimuth,
+            elevation: config.camera_elevation.to_radians(),
+            distance: config.camera_distance,
+            focal: height as f64 / 2.0 / half_angle.tan(),
+        }
+    }
+
+    // Position on screen in pixels from the center and depth in front of the camera, None behind
+    // it
+    pub fn project(&self, pos: Vector3) -> Option<(Point2, f64)> {
+        let (sin_a, cos_a) = self.azimuth.sin_cos();
+        let (sin_e, cos_e) = self.elevation.sin_cos();
+        let x = pos.x * cos_a - pos.z * sin_a;
+        let z = pos.x * sin_a + pos.z * cos_a;
+        let y = pos.y * cos_e - z * sin_e;
+        let depth = self.distance - (pos.y * sin_e + z * cos_e);
+
+        (depth > 0.0).then(|| {
+            let scale = self.focal / depth;
+            (pt2((x * scale) as f32, (y * scale) as f32), depth)
+        })
+    }
+
+    // Size multiplier at a depth, 1 at the center of the volume
+    fn size(&self, depth: f64) -> f32 {
+        (self.distance / depth) as f32
+    }
+
+    // Opacity at a depth, fading by `depth_fade` from the nearest corner of the volume to the
+    // farthest
+    fn alpha(&self, config: &Config, depth: f64) -> f32 {
+        let near = self.distance - CORNER;
+        let t = ((depth - near) / (2.0 * CORNER)).clamp(0.0, 1.0) as f32;
+        1.0 - config.depth_fade * t
+    }
+}
+
+// Background, then the particles' trails projected through the camera from the farthest to the
+// nearest, thinner and fainter with depth
+pub fn draw_volume(draw: &Draw, config: &Config, volume: &Volume, orbit: &Orbit) {
+    draw.background().color(config.background.linear());
+
+    let mut particles: Vec<(f64, &Particle)> = volume
+        .particles
+        .iter()
+        .filter_map(|particle| Some((orbit.project(particle.pos)?.1, particle)))
+        .collect();
+    particles.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    for (depth, particle) in particles {
+        let color = palette::color(config, volume.color_value(config, particle.pos));
+        let colored = |pos: Vector3| {
+            let (point, depth) = orbit.project(pos)?;
+            let alpha = orbit.alpha(config, depth);
+            Some((
+                point,
+                LinSrgba::new(color.red, color.green, color.blue, alpha),
+            ))
+        };
+        let size = orbit.size(depth);
+
+        if particle.trail.is_empty() {
+            if let Some((point, color)) = colored(particle.pos) {
+                draw.ellipse()
+                    .xy(point)
+                    .radius(config.point_size as f32 * size / 2.0)
+                    .color(color);
+            }
+            continue;
+        }
+        let points: Vec<_> = particle
+            .trail
+            .iter()
+            .copied()
+            .chain(std::iter::once(particle.pos))
+            .filter_map(colored)
+            .collect();
+        draw.polyline()
+            .weight(config.path_width as f32 * size)
+            .points_colored(points);
+    }
+}
+
+// The volume seen from the camera at `azimuth`, rendered offscreen at the render resolution
+fn snapshot(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    config: &Config,
+    volume: &Volume,
+    azimuth: f64,
+) -> RgbaImage {
+    let size = [config.render_width, config.render_height];
+    let mut target = Target::new(device, size, target::samples(config));
+    let draw = Draw::new();
+    let orbit = Orbit::new(config, azimuth, size[1] as f32);
+    draw_volume(&draw, config, volume, &orbit);
+    target.render(device, queue, &draw);
+    crate::headless::read(device, queue, &target.texture)
+}
+
+// Run `render_steps` steps and save the volume from the camera's starting position at the render
+// resolution, without a window
+fn render(config: &Config, path: &Path) {
+    let seed = crate::seed::resolve(config);
+    let path = crate::seed::expand(path, seed);
+
+    let mut volume = Volume::new(config, seed);
+    for step in 0..config.render_steps {
+        volume.step(config);
+        crate::logging::progress("Stepping", step as usize + 1, config.render_steps as usize);
+    }
+
+    let (device, queue) = crate::headless::device();
+    let image = snapshot(&device, &queue, config, &volume, 0.0);
+
+    let metadata = Metadata::new(config, seed);
+    if config.sidecar {
+        metadata.save_sidecar(&path);
+    }
+    metadata.save_png(&image, &path);
+    log::info!("Saved {}", path.display());
+}
+
+pub struct Model {
+    config: Config,
+    volume: Volume,
+    azimuth: f64, // of the camera, advancing by `orbit_speed`
+    paused: bool,
+    capture_next: bool,
+    pacer: Pacer,                       // holds frames to `max_fps`
+    device: Arc<wgpu::DeviceQueuePair>, // kept to render the final frame once the window closed
+}
+
+impl Sketch for Model {
+    fn export(config: &Config) -> bool {
+        if let Some(path) = &config.render {
+            render(config, path);
+        }
+        config.render.is_some()
+    }
+
+    fn model(app: &App) -> Self {
+        model(app)
+    }
+
+    fn update(app: &App, model: &mut Self, frame: Update) {
+        update(app, model, frame)
+    }
+
+    fn exit(app: &App, model: Self) {
+        exit(app, model)
+    }
+}
+
+fn model(app: &App) -> Model {
+    let config = Config::load();
+    let window = app
+        .new_window()
+        .surface_conf_builder(pacing::surface(&config))
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    pacing::apply(app, &config);
+    let seed = crate::seed::resolve(&config);
+
+    Model {
+        volume: Volume::new(&config, seed),
+        config,
+        azimuth: 0.0,
+        paused: false,
+        capture_next: false,
+        pacer: Pacer::new(),
+        device: app.window(window).unwrap().device_queue_pair().clone(),
+    }
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.pacer.wait(&model.config);
+
+    if std::mem::take(&mut model.capture_next) {
+        let path = crate::export::capture_path(model.volume.seed);
+        log::info!("Saving {}", path.display());
+        Metadata::new(&model.config, model.volume.seed).save_sidecar(&path);
+        app.main_window().capture_frame(path);
+    }
+
+    // `speed` steps per frame, at least one unless paused, with the camera orbiting along
+    if !model.paused {
+        for _ in 0..(model.config.speed.round() as usize).max(1) {
+            model.volume.step(&model.config);
+        }
+        let seconds = update.since_last.as_secs_f64();
+        model.azimuth += model.config.orbit_speed.to_radians() * seconds;
+    }
+}
+
+// Saved at the render resolution, as the window is gone by now
+fn exit(app: &App, model: Model) {
+    let (config, volume) = (&model.config, &model.volume);
+    crate::session::summarize(app, config, volume.seed, volume.steps, None);
+    if config.save_on_exit {
+        let (device, queue) = (model.device.device(), model.device.queue());
+        let image = snapshot(device, queue, config, volume, model.azimuth);
+        crate::session::save(config, volume.seed, &image);
+    }
+}
+
+fn key_pressed(_app: &App, model: &mut Model, key: Key) {
+    match key {
+        Key::S => model.capture_next = true,
+        Key::Space => model.paused = !model.paused,
+        Key::R => {
+            let seed = seed::random(&mut rand::thread_rng());
+            log::info!("Seed: {}", seed);
+            model.volume = Volume::new(&model.config, seed);
+        }
+        _ => {}
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let draw = app.draw();
+    let orbit = Orbit::new(&model.config, model.azimuth, frame.rect().h());
+    draw_volume(&draw, &model.config, &model.volume, &orbit);
+    draw.to_frame(app, &frame).unwrap();
+}
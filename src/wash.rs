@@ -0,0 +1,88 @@
+use crate::config::Config;
+use crate::particles::Simulation;
+use crate::Vector2;
+use nannou::color::{hsl, Rgb};
+use nannou::image::{DynamicImage, Rgb as Pixel, RgbImage};
+use nannou::wgpu;
+
+// Render the heading of the field at a low resolution, each pixel colored by the heading angle at
+// its center and dimmed by the wash strength. Scaled up with linear filtering, it becomes a soft
+// wash.
+pub fn field_image(simulation: &Simulation, config: &Config, width: u32, height: u32) -> RgbImage {
+    let strength = config.background_wash_strength;
+
+    RgbImage::from_fn(width, height, |x, y| {
+        let pos = Vector2 {
+            x: (x as f64 + 0.5) / width as f64 * simulation.x_end,
+            y: (1.0 - (y as f64 + 0.5) / height as f64) * simulation.y_end,
+        };
+
+        let turns = simulation.heading(config, pos) / (2.0 * std::f64::consts::PI);
+        let color = Rgb::from(hsl(turns.rem_euclid(1.0) as f32, 1.0, 0.5));
+        let channel = |value: f32| (value as f64 * strength * 255.0).round() as u8;
+
+        Pixel([
+            channel(color.red),
+            channel(color.green),
+            channel(color.blue),
+        ])
+    })
+}
+
+// Upload the field image as a texture, its height following the aspect ratio of the canvas
+pub fn texture<T: wgpu::WithDeviceQueuePair>(
+    src: T,
+    simulation: &Simulation,
+    config: &Config,
+) -> wgpu::Texture {
+    let width = config.background_wash_resolution;
+    let height = ((width as f64) * simulation.y_end / simulation.x_end).ceil() as u32;
+    let image = field_image(simulation, config, width, height);
+    wgpu::Texture::from_image(src, &DynamicImage::ImageRgb8(image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::VectorField;
+
+    // Up on the left half of the canvas, down on the right half
+    struct Split;
+
+    impl VectorField for Split {
+        fn vector(&self, _: &Config, pos: Vector2, extent: Vector2, _: u64) -> Vector2 {
+            let y = if pos.x < extent.x / 2.0 { 1.0 } else { -1.0 };
+            Vector2 { x: 0.0, y }
+        }
+    }
+
+    #[test]
+    fn field_image_of_known_field() {
+        let config = Config {
+            background_wash_strength: 1.0,
+            ..Config::default()
+        };
+        let mut simulation = Simulation::new(&config, 1, 200.0, 100.0);
+        simulation.field = Box::new(Split);
+
+        // heading 0 is red, half a turn is cyan
+        let image = field_image(&simulation, &config, 4, 2);
+        for y in 0..2 {
+            for x in 0..4 {
+                let expected = if x < 2 { [255, 0, 0] } else { [0, 255, 255] };
+                assert_eq!(image.get_pixel(x, y).0, expected);
+            }
+        }
+
+        let dimmed = field_image(
+            &simulation,
+            &Config {
+                background_wash_strength: 0.2,
+                ..config
+            },
+            4,
+            2,
+        );
+        assert_eq!(dimmed.get_pixel(0, 0).0, [51, 0, 0]);
+    }
+}
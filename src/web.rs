@@ -0,0 +1,66 @@
+// What differs when compiled to wasm32 and embedded in a web page: there are no command line
+// arguments or files there, and `std`'s clocks panic
+
+pub use instant::{Instant, SystemTime}; // `std::time` on native, `performance.now()` on the web
+
+// Whether outputs and config files can be written and read
+pub const FILES: bool = cfg!(not(target_arch = "wasm32"));
+
+// Command line arguments, taken from the URL query of the page on the web
+pub fn args() -> Vec<String> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let search = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default();
+        query_args(&search)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    std::env::args().collect()
+}
+
+// Arguments spelled as a URL query, so `?sketch=physarum&points=64&palette=magma&loop` is
+// `physarum --points 64 --palette magma --loop`
+pub fn query_args(query: &str) -> Vec<String> {
+    let mut args = vec![env!("CARGO_PKG_NAME").to_string()];
+    let pairs = query.trim_start_matches('?').split('&');
+    for pair in pairs.filter(|pair| !pair.is_empty()) {
+        let (key, value) = match pair.split_once('=') {
+            Some((key, value)) => (decode(key), Some(decode(value))),
+            None => (decode(pair), None),
+        };
+        match (key.as_str(), value) {
+            ("sketch", Some(sketch)) => args.insert(1, sketch), // positional
+            (_, value) => {
+                args.push(format!("--{}", key));
+                args.extend(value);
+            }
+        }
+    }
+    args
+}
+
+// Undo percent-encoding, and `+` standing for a space
+fn decode(text: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (byte, hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            (b'+', _) => {
+                bytes.push(b' ');
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}